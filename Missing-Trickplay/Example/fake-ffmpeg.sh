@@ -0,0 +1,22 @@
+#!/bin/sh
+# Faux ffmpeg pour tester `generate` sans encodeur :
+#   missing_trickplay generate --ffmpeg ./Example/fake-ffmpeg.sh
+# Écrit deux tuiles JPEG minimales au chemin de sortie (dernier argument).
+# Échoue si le nom de la vidéo contient "FAIL".
+
+input=""
+while [ $# -gt 1 ]; do
+    if [ "$1" = "-i" ]; then
+        input="$2"
+    fi
+    shift
+done
+output="$1"
+
+case "$input" in
+    *FAIL*) echo "fake-ffmpeg: erreur simulée pour $input" >&2; exit 1 ;;
+esac
+
+for i in 0 1; do
+    printf '\377\330\377\340fake tile\377\331' > "$(printf "$output" "$i")"
+done
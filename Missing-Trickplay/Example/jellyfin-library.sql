@@ -0,0 +1,10 @@
+-- Base de test au format library.db de Jellyfin (10.8/10.9), réduite aux colonnes lues :
+--   sqlite3 /tmp/jellyfin/data/library.db < Example/jellyfin-library.sql
+-- Les Trickplay de l'élément sont alors attendus dans
+--   /tmp/jellyfin/metadata/library/01/0123456789abcdef0123456789abcdef/trickplay/
+CREATE TABLE TypedBaseItems (guid GUID PRIMARY KEY NOT NULL, type TEXT NOT NULL, Path TEXT, IsFolder BIT);
+
+-- Guid .NET 01234567-89ab-cdef-0123-456789abcdef (trois premiers groupes en petit-boutiste)
+INSERT INTO TypedBaseItems VALUES (X'67452301AB89EFCD0123456789ABCDEF', 'MediaBrowser.Controller.Entities.Movies.Movie', '/media/Films/Alien 3/FAIL.mkv', 0);
+INSERT INTO TypedBaseItems VALUES (X'00112233445566778899AABBCCDDEEFF', 'MediaBrowser.Controller.Entities.Movies.Movie', '/media/Films/Alien/Alien.mkv', 0);
+INSERT INTO TypedBaseItems VALUES (X'FFEEDDCCBBAA99887766554433221100', 'MediaBrowser.Controller.Entities.Folder', '/media/Films', 1);
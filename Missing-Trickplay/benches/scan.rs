@@ -0,0 +1,175 @@
+//! Chronomètre le scan sur une bibliothèque factice :
+//!
+//! ```text
+//! cargo bench --bench scan -- [--entries 100000] [--dir /mnt/nas/bench] [--threads 8] [--keep]
+//! ```
+//!
+//! Les deux parcours de référence comparent un `stat` par entrée au type donné par `readdir`,
+//! puis le binaire compilé scanne la bibliothèque avec 1 et `--threads` workers. Le gain des
+//! workers vient de la latence d'un partage réseau : `--dir` doit alors pointer sur le partage.
+
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Chaque mesure est répétée, la meilleure est gardée (cache disque chaud, bruit minimal).
+const RUNS: usize = 3;
+
+struct Options {
+    entries: usize,
+    dir: Option<PathBuf>,
+    threads: usize,
+    keep: bool,
+}
+
+/// Options après `--` ; `cargo bench` ajoute lui-même `--bench`, ignoré comme tout le reste.
+fn options() -> Options {
+    let mut options = Options { entries: 100_000, dir: None, threads: 8, keep: false };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--entries" => options.entries = args.next().and_then(|n| n.parse().ok()).expect("--entries <nombre>"),
+            "--threads" => options.threads = args.next().and_then(|n| n.parse().ok()).expect("--threads <nombre>"),
+            "--dir" => options.dir = Some(args.next().expect("--dir <dossier>").into()),
+            "--keep" => options.keep = true,
+            _ => {}
+        }
+    }
+    options
+}
+
+/// Bibliothèque factice d'au moins `entries` fichiers et dossiers : des films avec `.nfo`,
+/// deux sur trois avec un `.trickplay`, et une série à saisons tous les dix éléments.
+fn generate_tree(dir: &Path, entries: usize) -> io::Result<usize> {
+    let mut created = 0;
+
+    for item in 0.. {
+        if created >= entries {
+            break;
+        }
+
+        let name = format!("Film {:06}", item);
+        let folder = dir.join(&name);
+        fs::create_dir_all(&folder)?;
+        File::create(folder.join(format!("{}.nfo", name)))?;
+        created += 2;
+
+        let videos = if item % 10 == 0 {
+            let season = folder.join("Saison 01");
+            fs::create_dir(&season)?;
+            created += 1;
+            (1..=4).map(|episode| season.join(format!("{} S01E{:02}", name, episode))).collect()
+        } else {
+            vec![folder.join(&name)]
+        };
+
+        for video in videos {
+            File::create(video.with_extension("mkv"))?;
+            created += 1;
+            if item % 3 != 0 {
+                let tiles = video.with_extension("trickplay").join("320 - 10x10");
+                fs::create_dir_all(&tiles)?;
+                File::create(tiles.join("0.jpg"))?;
+                created += 3;
+            }
+        }
+    }
+
+    Ok(created)
+}
+
+/// L'ancien parcours, un `stat` par entrée via `Path::is_dir`.
+fn walk_with_stat(folder: &Path) -> usize {
+    let Ok(entries) = fs::read_dir(folder) else { return 0 };
+    let mut count = 1;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() && !entry.file_name().to_string_lossy().ends_with(".trickplay") {
+            count += walk_with_stat(&path);
+        }
+    }
+    count
+}
+
+/// Le parcours du scan, avec le type donné par `readdir`.
+fn walk_with_file_type(folder: &Path) -> usize {
+    let Ok(entries) = fs::read_dir(folder) else { return 0 };
+    let mut count = 1;
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else { continue };
+        let path = entry.path();
+        let is_dir = if file_type.is_symlink() { path.is_dir() } else { file_type.is_dir() };
+        if is_dir && !entry.file_name().to_string_lossy().ends_with(".trickplay") {
+            count += walk_with_file_type(&path);
+        }
+    }
+    count
+}
+
+/// Scan complet par le binaire, sans snapshot ni lecture des tuiles : renvoie le nombre d'entrées du rapport.
+fn scan(config: &Path, threads: usize) -> usize {
+    let output = Command::new(env!("CARGO_BIN_EXE_missing_trickplay"))
+        .args(["--no-snapshot", "--skip-validation", "-f", "json", "--scan-threads", &threads.to_string(), "-c"])
+        .arg(config)
+        .stderr(Stdio::null())
+        .output()
+        .expect("missing_trickplay introuvable");
+    assert!(output.status.success(), "missing_trickplay a échoué : {}", output.status);
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).expect("rapport JSON illisible");
+    report["entries"].as_array().map_or(0, Vec::len)
+}
+
+fn best_of(run: impl Fn() -> usize) -> (Duration, usize) {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            let count = run();
+            (start.elapsed(), count)
+        })
+        .min()
+        .unwrap()
+}
+
+fn measure(dir: &Path, config: &Path, threads: usize) {
+    let runs: [(String, Box<dyn Fn() -> usize>); 4] = [
+        ("parcours avec stat".to_string(), Box::new(|| walk_with_stat(dir))),
+        ("parcours file_type()".to_string(), Box::new(|| walk_with_file_type(dir))),
+        ("scan, 1 worker".to_string(), Box::new(|| scan(config, 1))),
+        (format!("scan, {} workers", threads), Box::new(|| scan(config, threads))),
+    ];
+
+    // Premier passage à blanc pour remplir le cache disque
+    runs[0].1();
+    println!("\n{:<24} {:>10} {:>10}", "Mesure", "Temps", "Compte");
+    for (label, run) in &runs {
+        let (elapsed, count) = best_of(run);
+        println!("{:<24} {:>7} ms {:>10}", label, elapsed.as_millis(), count);
+    }
+}
+
+fn main() -> io::Result<()> {
+    let options = options();
+    let tmp = tempfile::tempdir()?;
+    let dir = options.dir.clone().unwrap_or_else(|| tmp.path().join("library"));
+    if dir.exists() {
+        return Err(io::Error::other(format!("{} existe déjà, choisir un autre --dir", dir.display())));
+    }
+
+    let start = Instant::now();
+    let created = generate_tree(&dir, options.entries)?;
+    println!("🌳 {} entrées générées dans {} en {:.1} s", created, dir.display(), start.elapsed().as_secs_f64());
+
+    let config = tmp.path().join("config.toml");
+    fs::write(&config, format!("[[category]]\nname = \"Bench\"\nroots = [{:?}]\n", dir))?;
+    measure(&dir, &config, options.threads);
+
+    match (&options.dir, options.keep) {
+        (Some(_), false) => fs::remove_dir_all(&dir)?,
+        (None, true) => println!("📁 Gardée dans {}", tmp.keep().display()),
+        _ => {}
+    }
+    Ok(())
+}
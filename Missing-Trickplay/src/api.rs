@@ -0,0 +1,339 @@
+use crate::config::JellyfinConfig;
+use crate::report::{csv_field, OutputFormat, ScanReport, Status, SCHEMA_VERSION};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
+use unicode_normalization::UnicodeNormalization;
+
+const PAGE_SIZE: usize = 500;
+
+/// Élément Jellyfin, réduit aux champs utiles (`/Items?Fields=Path,Trickplay`).
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Item {
+    pub id: String,
+    pub path: Option<String>,
+    /// Source média → largeur → infos de tuiles ; vide si Jellyfin n'a pas de Trickplay.
+    #[serde(default)]
+    pub trickplay: Option<HashMap<String, HashMap<String, serde_json::Value>>>,
+}
+
+impl Item {
+    pub fn has_trickplay(&self) -> bool {
+        self.trickplay.as_ref().is_some_and(|sources| sources.values().any(|widths| !widths.is_empty()))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ItemsPage {
+    items: Vec<Item>,
+    total_record_count: usize,
+}
+
+pub struct Client {
+    url: String,
+    api_key: String,
+    agent: ureq::Agent,
+}
+
+impl Client {
+    pub fn new(url: &str, api_key: &str) -> Self {
+        Client {
+            url: url.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+            agent: ureq::AgentBuilder::new().timeout(Duration::from_secs(60)).build(),
+        }
+    }
+
+    /// `url` et `api_key` de la config, la clé pouvant venir de `$JELLYFIN_API_KEY`.
+    pub fn from_config(config: &JellyfinConfig) -> Result<Self, String> {
+        let url = config.url.as_deref().ok_or("url Jellyfin manquante ([jellyfin] url ou --url)")?;
+        let api_key = config
+            .api_key
+            .clone()
+            .or_else(|| std::env::var("JELLYFIN_API_KEY").ok())
+            .ok_or("clé d'API Jellyfin manquante ([jellyfin] api_key, --api-key ou $JELLYFIN_API_KEY)")?;
+        Ok(Client::new(url, &api_key))
+    }
+
+    /// Tous les films, épisodes et vidéos de la bibliothèque, page par page.
+    pub fn items(&self) -> Result<Vec<Item>, String> {
+        let mut items = Vec::new();
+
+        loop {
+            let page: ItemsPage = self
+                .agent
+                .get(&format!("{}/Items", self.url))
+                .set("Authorization", &format!("MediaBrowser Token=\"{}\"", self.api_key))
+                .query("Recursive", "true")
+                .query("IncludeItemTypes", "Movie,Episode,Video")
+                .query("IsVirtualItem", "false")
+                .query("Fields", "Path,Trickplay")
+                .query("StartIndex", &items.len().to_string())
+                .query("Limit", &PAGE_SIZE.to_string())
+                .call()
+                .map_err(|e| e.to_string())?
+                .into_json()
+                .map_err(|e| format!("{}: réponse illisible : {}", self.url, e))?;
+
+            let received = page.items.len();
+            items.extend(page.items);
+            if received == 0 || items.len() >= page.total_record_count {
+                return Ok(items);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mismatch {
+    /// Vidéo sur le disque sans élément Jellyfin (pas encore scannée, ou ignorée)
+    NotInJellyfin,
+    /// Élément sans Trickplay, ni pour Jellyfin ni sur le disque
+    NoTrickplay,
+    /// Trickplay sur le disque que Jellyfin ne connaît pas
+    Unregistered,
+    /// Trickplay connu de Jellyfin mais introuvable sur le disque (supprimé, ou `library_db` absent
+    /// de la config alors que Jellyfin les range dans son dossier metadata)
+    NotOnDisk,
+}
+
+impl Mismatch {
+    pub const ALL: [Mismatch; 4] = [Mismatch::NotInJellyfin, Mismatch::NoTrickplay, Mismatch::Unregistered, Mismatch::NotOnDisk];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Mismatch::NotInJellyfin => "not_in_jellyfin",
+            Mismatch::NoTrickplay => "no_trickplay",
+            Mismatch::Unregistered => "unregistered",
+            Mismatch::NotOnDisk => "not_on_disk",
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            Mismatch::NotInJellyfin => "👻 Sur le disque, inconnues de Jellyfin",
+            Mismatch::NoTrickplay => "❌ Connues de Jellyfin, sans Trickplay",
+            Mismatch::Unregistered => "📎 Trickplay sur le disque, non enregistrés dans Jellyfin",
+            Mismatch::NotOnDisk => "🕳️ Trickplay connus de Jellyfin, absents du disque",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Difference<'a> {
+    pub mismatch: Mismatch,
+    pub category: &'a str,
+    pub video: &'a Path,
+    pub item_id: Option<&'a str>,
+    pub trickplay: Option<&'a Path>,
+}
+
+#[derive(Serialize)]
+struct JsonComparison<'a> {
+    schema_version: u32,
+    items: usize,
+    totals: BTreeMap<&'static str, usize>,
+    differences: &'a [Difference<'a>],
+}
+
+/// Compare le scan du disque aux éléments Jellyfin, vidéo par vidéo.
+pub struct Comparison<'a> {
+    pub items: usize,
+    pub differences: Vec<Difference<'a>>,
+}
+
+impl<'a> Comparison<'a> {
+    pub fn new(report: &'a ScanReport, items: &'a [Item], config: &JellyfinConfig) -> Self {
+        let by_path: HashMap<String, &Item> = items
+            .iter()
+            .filter_map(|item| Some((config.local_path(item.path.as_deref()?).to_string_lossy().nfc().collect(), item)))
+            .collect();
+
+        let mut differences = Vec::new();
+        for entry in report.entries.iter().filter(|e| e.is_trickplay()) {
+            let Some(video) = entry.video.as_deref() else { continue };
+            let key: String = video.to_string_lossy().nfc().collect();
+            let item = by_path.get(&key);
+
+            let mismatch = match item {
+                None => Mismatch::NotInJellyfin,
+                // Une vidéo ambiguë a peut-être son Trickplay, simplement pas apparié
+                Some(item) if item.has_trickplay() && entry.status == Status::Missing => Mismatch::NotOnDisk,
+                Some(item) if item.has_trickplay() => continue,
+                Some(_) if entry.trickplay.is_some() => Mismatch::Unregistered,
+                Some(_) => Mismatch::NoTrickplay,
+            };
+            differences.push(Difference {
+                mismatch,
+                category: &entry.category,
+                video,
+                item_id: item.map(|item| item.id.as_str()),
+                trickplay: entry.trickplay.as_deref(),
+            });
+        }
+
+        Comparison { items: items.len(), differences }
+    }
+
+    pub fn count(&self, mismatch: Mismatch) -> usize {
+        self.differences.iter().filter(|d| d.mismatch == mismatch).count()
+    }
+
+    pub fn write(&self, format: OutputFormat, out: &mut impl Write) -> io::Result<()> {
+        match format {
+            OutputFormat::Text => self.write_text(out),
+            OutputFormat::Json => self.write_json(out),
+            OutputFormat::Csv => self.write_csv(out),
+        }
+    }
+
+    fn write_json(&self, out: &mut impl Write) -> io::Result<()> {
+        let comparison = JsonComparison {
+            schema_version: SCHEMA_VERSION,
+            items: self.items,
+            totals: Mismatch::ALL.iter().map(|m| (m.as_str(), self.count(*m))).collect(),
+            differences: &self.differences,
+        };
+
+        serde_json::to_writer_pretty(&mut *out, &comparison)?;
+        writeln!(out)
+    }
+
+    fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "mismatch,category,video,item_id,trickplay")?;
+
+        for difference in &self.differences {
+            writeln!(
+                out,
+                "{},{},{},{},{}",
+                difference.mismatch.as_str(),
+                csv_field(difference.category),
+                csv_field(&difference.video.display().to_string()),
+                difference.item_id.unwrap_or_default(),
+                csv_field(&difference.trickplay.map(|p| p.display().to_string()).unwrap_or_default()),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn write_text(&self, out: &mut impl Write) -> io::Result<()> {
+        for mismatch in Mismatch::ALL {
+            let differences: Vec<&Difference> = self.differences.iter().filter(|d| d.mismatch == mismatch).collect();
+            if differences.is_empty() {
+                continue;
+            }
+
+            writeln!(out, "\n{} ({})", mismatch.title(), differences.len())?;
+            for difference in differences {
+                writeln!(out, "  {} : {}", difference.category, difference.video.display())?;
+            }
+        }
+
+        writeln!(out, "\n--- Résumé Jellyfin ---")?;
+        writeln!(out, "Éléments Jellyfin         : {}", self.items)?;
+        writeln!(out, "Inconnues de Jellyfin     : {}", self.count(Mismatch::NotInJellyfin))?;
+        writeln!(out, "Sans Trickplay            : {}", self.count(Mismatch::NoTrickplay))?;
+        writeln!(out, "Trickplay non enregistrés : {}", self.count(Mismatch::Unregistered))?;
+        writeln!(out, "Trickplay absents disque  : {}", self.count(Mismatch::NotOnDisk))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::{Entry, Found, TRICKPLAY_KIND};
+    use std::path::PathBuf;
+
+    /// Sert `Example/jellyfin-api/Items` deux éléments à la fois, quelle que soit la `Limit` demandée,
+    /// et renvoie les `StartIndex` reçus.
+    fn serve_recorded_items(server: tiny_http::Server, pages: usize) -> std::thread::JoinHandle<Vec<usize>> {
+        let recorded: serde_json::Value =
+            serde_json::from_str(include_str!("../Example/jellyfin-api/Items")).expect("réponse enregistrée illisible");
+
+        std::thread::spawn(move || {
+            let items = recorded["Items"].as_array().expect("Items").clone();
+            let mut start_indexes = Vec::new();
+            for request in server.incoming_requests().take(pages) {
+                let start: usize = request
+                    .url()
+                    .split_once('?')
+                    .and_then(|(_, query)| query.split('&').find_map(|pair| pair.strip_prefix("StartIndex=")))
+                    .map_or(0, |value| value.parse().expect("StartIndex"));
+                start_indexes.push(start);
+
+                let page = serde_json::json!({
+                    "Items": items.iter().skip(start).take(2).collect::<Vec<_>>(),
+                    "TotalRecordCount": items.len(),
+                    "StartIndex": start,
+                });
+                let header = tiny_http::Header::from_bytes("Content-Type", "application/json").expect("header");
+                request.respond(tiny_http::Response::from_string(page.to_string()).with_header(header)).expect("réponse");
+            }
+            start_indexes
+        })
+    }
+
+    fn entry(video: &str, status: Status, trickplay: bool) -> Entry {
+        let video = Path::new("/mnt/nas/Films").join(video);
+        Entry {
+            category: "Films".to_string(),
+            root: PathBuf::from("/mnt/nas/Films"),
+            kind: TRICKPLAY_KIND.to_string(),
+            status,
+            trickplay: trickplay.then(|| video.with_extension("trickplay")),
+            video: Some(video),
+            sidecar: None,
+            detail: None,
+        }
+    }
+
+    #[test]
+    fn pages_through_items_and_classifies_mismatches() {
+        let server = tiny_http::Server::http("127.0.0.1:0").expect("serveur de test");
+        let url = format!("http://{}", server.server_addr().to_ip().expect("adresse IP"));
+        let handle = serve_recorded_items(server, 2);
+
+        let items = Client::new(&url, "test").items().expect("items");
+        assert_eq!(handle.join().expect("serveur"), [0, 2]);
+        assert_eq!(items.len(), 4);
+
+        let config = JellyfinConfig {
+            path_map: BTreeMap::from([("/media".to_string(), PathBuf::from("/mnt/nas"))]),
+            ..JellyfinConfig::default()
+        };
+        let mut report = ScanReport {
+            entries: vec![
+                entry("Alien/Alien.mkv", Status::Ok, true),
+                entry("Alien 3/Alien 3.mkv", Status::Ok, true),
+                entry("Broken/Broken.mkv", Status::Missing, false),
+                entry("Nouveau/Nouveau.mkv", Status::Missing, false),
+            ],
+            estimates: Vec::new(),
+            found: Found::default(),
+        };
+
+        let comparison = Comparison::new(&report, &items, &config);
+        let mismatches: Vec<(Mismatch, &Path)> = comparison.differences.iter().map(|d| (d.mismatch, d.video)).collect();
+        assert_eq!(
+            mismatches,
+            [
+                (Mismatch::Unregistered, Path::new("/mnt/nas/Films/Alien 3/Alien 3.mkv")),
+                (Mismatch::NoTrickplay, Path::new("/mnt/nas/Films/Broken/Broken.mkv")),
+                (Mismatch::NotInJellyfin, Path::new("/mnt/nas/Films/Nouveau/Nouveau.mkv")),
+            ]
+        );
+        assert_eq!(comparison.differences[0].item_id, Some("2a1b0c9d8e7f60514233241506f7e8d9"));
+
+        // Le Trickplay d'Alien, connu de Jellyfin, a disparu du disque
+        report.entries[0] = entry("Alien/Alien.mkv", Status::Missing, false);
+        let comparison = Comparison::new(&report, &items, &config);
+        assert_eq!(comparison.count(Mismatch::NotOnDisk), 1);
+        assert_eq!(comparison.differences[0].video, Path::new("/mnt/nas/Films/Alien/Alien.mkv"));
+    }
+}
@@ -0,0 +1,260 @@
+use crate::media;
+use crate::report::{format_size, ScanReport, Status};
+use crate::trickplay::{self, TileSet};
+use image::codecs::jpeg::JpegEncoder;
+use image::{imageops, RgbImage};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// Signature Roku : `\x89BIF\r\n\x1a\n`
+const MAGIC: [u8; 8] = [0x89, b'B', b'I', b'F', 0x0D, 0x0A, 0x1A, 0x0A];
+const VERSION: u32 = 0;
+/// En-tête fixe avant la table d'index (octets 20 à 63 réservés, à zéro).
+const HEADER_SIZE: usize = 64;
+const INDEX_END: u32 = u32::MAX;
+
+/// Écrit un BIF : en-tête, table d'index `(horodatage, offset)` terminée par `0xFFFFFFFF`,
+/// puis les JPEG bout à bout. L'horodatage de la vignette `i` vaut `i`, en multiples de `interval_ms`.
+pub fn write_bif(frames: &[Vec<u8>], interval_ms: u32, out: &mut impl Write) -> io::Result<()> {
+    let index_size = (frames.len() + 1) * 8;
+    let total = HEADER_SIZE + index_size + frames.iter().map(Vec::len).sum::<usize>();
+    if total > u32::MAX as usize {
+        return Err(io::Error::other("BIF de plus de 4 Go"));
+    }
+
+    let mut header = Vec::with_capacity(HEADER_SIZE + index_size);
+    header.extend(MAGIC);
+    header.extend(VERSION.to_le_bytes());
+    header.extend((frames.len() as u32).to_le_bytes());
+    header.extend(interval_ms.to_le_bytes());
+    header.resize(HEADER_SIZE, 0);
+
+    let mut offset = (HEADER_SIZE + index_size) as u32;
+    for (index, frame) in frames.iter().enumerate() {
+        header.extend((index as u32).to_le_bytes());
+        header.extend(offset.to_le_bytes());
+        offset += frame.len() as u32;
+    }
+    header.extend(INDEX_END.to_le_bytes());
+    header.extend(offset.to_le_bytes());
+
+    out.write_all(&header)?;
+    for frame in frames {
+        out.write_all(frame)?;
+    }
+    Ok(())
+}
+
+/// Contenu d'un BIF relu : intervalle en ms et `(horodatage, JPEG)` de chaque vignette.
+pub struct Bif<'a> {
+    pub interval_ms: u32,
+    pub frames: Vec<(u32, &'a [u8])>,
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, String> {
+    let bytes = data.get(offset..offset + 4).ok_or("BIF tronqué")?;
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+pub fn read_bif(data: &[u8]) -> Result<Bif<'_>, String> {
+    if !data.starts_with(&MAGIC) {
+        return Err("signature BIF absente".to_string());
+    }
+    let version = read_u32(data, 8)?;
+    if version != VERSION {
+        return Err(format!("version BIF {} inconnue", version));
+    }
+
+    let count = read_u32(data, 12)? as usize;
+    // 0 veut dire 1000 ms d'après la spécification
+    let interval_ms = match read_u32(data, 16)? {
+        0 => 1000,
+        interval => interval,
+    };
+
+    let entry = |i: usize| -> Result<(u32, usize), String> {
+        Ok((read_u32(data, HEADER_SIZE + i * 8)?, read_u32(data, HEADER_SIZE + i * 8 + 4)? as usize))
+    };
+    let mut frames = Vec::with_capacity(count);
+    for i in 0..count {
+        let ((timestamp, start), (_, end)) = (entry(i)?, entry(i + 1)?);
+        let frame = data.get(start..end).ok_or_else(|| format!("vignette {} hors du fichier", i))?;
+        frames.push((timestamp, frame));
+    }
+    if entry(count)?.0 != INDEX_END {
+        return Err("table d'index sans fin 0xFFFFFFFF".to_string());
+    }
+
+    Ok(Bif { interval_ms, frames })
+}
+
+/// Une case de planche entièrement noire : remplissage ajouté après la dernière vignette.
+fn is_blank(frame: &RgbImage) -> bool {
+    frame.pixels().all(|pixel| pixel.0.iter().all(|&c| c <= 16))
+}
+
+/// Découpe les planches d'un jeu de tuiles en vignettes JPEG, dans l'ordre de lecture.
+/// Sans `count` (durée illisible), les cases noires en fin de dernière planche sont retirées.
+fn slice_tile_set(set: &TileSet, count: Option<usize>, quality: u8) -> Result<Vec<Vec<u8>>, String> {
+    let mut frames: Vec<(Vec<u8>, bool)> = Vec::new();
+    let mut frame_size = None;
+
+    'sheets: for index in 0..set.tiles {
+        let path = set.path.join(format!("{}.jpg", index));
+        let sheet = image::open(&path).map_err(|e| format!("{}: {}", path.display(), e))?.to_rgb8();
+        // Jellyfin raccourcit la dernière planche : la taille des cases vient de la première.
+        let (width, height) = *frame_size.get_or_insert((sheet.width() / set.cols, sheet.height() / set.rows));
+        if width == 0 || height == 0 {
+            return Err(format!("{}: planche plus petite que la grille", path.display()));
+        }
+
+        for row in 0..(sheet.height() / height).min(set.rows) {
+            for col in 0..set.cols {
+                if count.is_some_and(|count| frames.len() >= count) {
+                    break 'sheets;
+                }
+                let frame = imageops::crop_imm(&sheet, col * width, row * height, width, height).to_image();
+                let mut jpeg = Vec::new();
+                JpegEncoder::new_with_quality(&mut jpeg, quality).encode_image(&frame).map_err(|e| e.to_string())?;
+                frames.push((jpeg, is_blank(&frame)));
+            }
+        }
+    }
+
+    if count.is_none() {
+        while frames.last().is_some_and(|(_, blank)| *blank) {
+            frames.pop();
+        }
+    }
+    Ok(frames.into_iter().map(|(jpeg, _)| jpeg).collect())
+}
+
+/// Écrit `<vidéo>.bif` depuis son `.trickplay`, via un fichier temporaire relu avant d'être renommé.
+fn export_one(video: &Path, tp: &Path, interval: Duration, width: Option<u32>, quality: u8) -> Result<(usize, u64), String> {
+    let tile_sets = trickplay::read_tile_sets(tp).map_err(|e| e.to_string())?;
+    let set = match width {
+        Some(width) => tile_sets.iter().find(|set| set.width == width).ok_or_else(|| format!("pas de tuiles en {} px", width))?,
+        None => tile_sets.iter().max_by_key(|set| set.width).unwrap(),
+    };
+
+    let count = media::probe(video)
+        .duration
+        .filter(|_| !interval.is_zero())
+        .map(|duration| duration.as_millis().div_ceil(interval.as_millis()) as usize);
+    let frames = slice_tile_set(set, count, quality)?;
+
+    let mut data = Vec::new();
+    write_bif(&frames, interval.as_millis() as u32, &mut data).map_err(|e| e.to_string())?;
+    // Relu avant d'écrire, un BIF invalide bloquerait la lecture sur certains clients.
+    let written = read_bif(&data)?;
+    let sequential = written.frames.iter().enumerate().all(|(i, (timestamp, jpeg))| *timestamp as usize == i && jpeg.starts_with(&[0xFF, 0xD8]));
+    if written.frames.len() != frames.len() || written.interval_ms != interval.as_millis() as u32 || !sequential {
+        return Err(format!("BIF relu incohérent ({} vignettes sur {})", written.frames.len(), frames.len()));
+    }
+
+    let target = video.with_extension("bif");
+    let tmp = video.with_extension("bif.tmp");
+    fs::write(&tmp, &data).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, &target).map_err(|e| e.to_string())?;
+    Ok((frames.len(), data.len() as u64))
+}
+
+/// Un BIF plus récent que son `.trickplay` est considéré à jour.
+fn is_up_to_date(bif: &Path, tp: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    matches!((modified(bif), modified(tp)), (Some(bif), Some(tp)) if bif >= tp)
+}
+
+/// Exporte un BIF à côté de chaque vidéo dont le Trickplay est valide.
+pub fn export(report: &ScanReport, interval: Duration, width: Option<u32>, quality: u8, force: bool, dry_run: bool) -> io::Result<()> {
+    let (mut exported, mut skipped, mut failed) = (0, 0, 0);
+
+    for entry in report.entries.iter().filter(|e| e.is_trickplay() && e.status == Status::Ok) {
+        let (Some(video), Some(tp)) = (&entry.video, &entry.trickplay) else { continue };
+        let name = video.file_name().unwrap_or_default().to_string_lossy();
+
+        if !force && is_up_to_date(&video.with_extension("bif"), tp) {
+            skipped += 1;
+            continue;
+        }
+        if dry_run {
+            println!("  📼 {}", video.with_extension("bif").display());
+            exported += 1;
+            continue;
+        }
+
+        match export_one(video, tp, interval, width, quality) {
+            Ok((frames, size)) => {
+                println!("✅ {} ({} vignettes, {})", name, frames, format_size(size));
+                exported += 1;
+            }
+            Err(e) => {
+                println!("❌ {} : {}", name, e);
+                failed += 1;
+            }
+        }
+    }
+
+    let verb = if dry_run { "à exporter" } else { "exportés" };
+    println!("\n📼 {} BIF {}, {} déjà à jour, {} en échec", exported, verb, skipped, failed);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    const TILE: (u32, u32) = (32, 18);
+
+    /// Planche de `cols` × `rows` cases unies, la dernière planche étant raccourcie comme chez Jellyfin.
+    fn save_sheet(dir: &Path, index: usize, cols: u32, colors: &[[u8; 3]]) {
+        let rows = (colors.len() as u32).div_ceil(cols);
+        let sheet = RgbImage::from_fn(cols * TILE.0, rows * TILE.1, |x, y| {
+            let case = (y / TILE.1 * cols + x / TILE.0) as usize;
+            Rgb(colors.get(case).copied().unwrap_or([0, 0, 0]))
+        });
+        sheet.save(dir.join(format!("{}.jpg", index))).expect("planche");
+    }
+
+    #[test]
+    fn round_trip_trims_blank_trailing_tiles() {
+        let dir = tempfile::tempdir().unwrap();
+        let white = [240, 240, 240];
+        save_sheet(dir.path(), 0, 2, &[white, [200, 40, 40], [40, 200, 40], [40, 40, 200]]);
+        // Dernière planche : une vraie vignette puis une case noire de remplissage
+        save_sheet(dir.path(), 1, 2, &[white, [0, 0, 0]]);
+        let set = TileSet { path: dir.path().to_path_buf(), width: TILE.0 * 2, cols: 2, rows: 2, tiles: 2 };
+
+        let frames = slice_tile_set(&set, None, 80).expect("découpage");
+        assert_eq!(frames.len(), 5);
+        // Avec la durée de la vidéo, le nombre de vignettes fait foi
+        assert_eq!(slice_tile_set(&set, Some(3), 80).expect("découpage").len(), 3);
+
+        let mut data = Vec::new();
+        write_bif(&frames, 10_000, &mut data).expect("écriture");
+
+        assert_eq!(data[..8], MAGIC);
+        assert_eq!(read_u32(&data, 8), Ok(VERSION));
+        assert_eq!(read_u32(&data, 12), Ok(5));
+        assert_eq!(read_u32(&data, 16), Ok(10_000));
+        assert!(data[20..HEADER_SIZE].iter().all(|&byte| byte == 0));
+
+        let index_end = HEADER_SIZE + frames.len() * 8;
+        assert_eq!(read_u32(&data, HEADER_SIZE + 4), Ok((index_end + 8) as u32));
+        assert_eq!(read_u32(&data, index_end), Ok(INDEX_END));
+        assert_eq!(read_u32(&data, index_end + 4), Ok(data.len() as u32));
+
+        let bif = read_bif(&data).expect("relecture");
+        assert_eq!(bif.interval_ms, 10_000);
+        assert_eq!(bif.frames.len(), frames.len());
+        for (i, ((timestamp, jpeg), frame)) in bif.frames.iter().zip(&frames).enumerate() {
+            assert_eq!(*timestamp as usize, i);
+            assert_eq!(jpeg, frame);
+            let decoded = image::load_from_memory(jpeg).expect("JPEG").to_rgb8();
+            assert_eq!(decoded.dimensions(), TILE);
+        }
+    }
+}
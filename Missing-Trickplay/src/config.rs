@@ -0,0 +1,250 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const CONFIG_FILE_NAME: &str = "missing_trickplay.toml";
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct LibraryConfig {
+    /// Intervalle entre deux vignettes configuré dans Jellyfin (10 s par défaut).
+    #[serde(default = "default_interval_ms")]
+    pub trickplay_interval_ms: u64,
+    /// Vitesse de génération par défaut, en multiple du temps réel pour une vidéo 1080p.
+    #[serde(default = "default_generation_speed")]
+    pub generation_speed: f64,
+    /// Dossiers d'éléments scannés en parallèle, toutes racines confondues.
+    #[serde(default = "default_scan_threads")]
+    pub scan_threads: usize,
+    /// Snapshots gardés après chaque scan, les plus anciens sont supprimés. Tous par défaut.
+    pub keep_snapshots: Option<usize>,
+    #[serde(default)]
+    pub generate: GenerateConfig,
+    #[serde(default)]
+    pub jellyfin: JellyfinConfig,
+    /// Règles sidecars communes à toutes les catégories.
+    #[serde(rename = "sidecar", alias = "sidecars", default)]
+    pub sidecars: Vec<SidecarRule>,
+    #[serde(rename = "category", alias = "categories", default)]
+    pub categories: Vec<CategoryConfig>,
+}
+
+/// Fichier attendu à côté des vidéos (`.nfo`, affiche, sous-titres...).
+#[derive(Debug, Deserialize, Clone)]
+pub struct SidecarRule {
+    pub name: String,
+    /// Nom de fichier ou de dossier, alternatives séparées par `|`, avec `*`, `?` et `{stem}`.
+    pub pattern: String,
+    /// Par défaut `video` si le motif contient `{stem}`, sinon `folder`.
+    pub scope: Option<SidecarScope>,
+    /// Noms jamais signalés orphelins, même syntaxe que `pattern`. Par défaut les fichiers
+    /// que Jellyfin écrit lui-même (`tvshow.nfo`, `season.nfo`, `poster.jpg`...).
+    pub ignore: Option<String>,
+}
+
+/// Métadonnées propres à Jellyfin : un `{stem}.nfo` les accepterait sans qu'elles soient liées à une vidéo.
+pub const JELLYFIN_METADATA: &str = "tvshow.nfo|season.nfo|movie.nfo|video_ts.nfo|folder.*|poster.*|cover.*|\
+    backdrop*|fanart*|background*|banner.*|logo.*|clearlogo.*|clearart.*|landscape.*|thumb.*|disc.*|cdart.*|theme.*|season*-*.*";
+
+impl SidecarRule {
+    pub fn ignore(&self) -> &str {
+        self.ignore.as_deref().unwrap_or(JELLYFIN_METADATA)
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SidecarScope {
+    /// Un par vidéo, dans son dossier
+    Video,
+    /// Un par dossier contenant des vidéos
+    Folder,
+    /// Un dans le dossier de l'élément (film ou série), directement sous la racine
+    Item,
+}
+
+/// Réglages de `generate`, par défaut ceux de Jellyfin (320 px, tuiles 10x10).
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct GenerateConfig {
+    pub ffmpeg: PathBuf,
+    pub width: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    /// Qualité JPEG ffmpeg (`-q:v`), de 2 (meilleure) à 31.
+    pub quality: u32,
+    pub jobs: usize,
+}
+
+impl Default for GenerateConfig {
+    fn default() -> Self {
+        GenerateConfig {
+            ffmpeg: PathBuf::from("ffmpeg"),
+            width: 320,
+            tile_width: 10,
+            tile_height: 10,
+            quality: 4,
+            jobs: 2,
+        }
+    }
+}
+
+/// Accès à Jellyfin, pour les Trickplay rangés dans son dossier de métadonnées.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct JellyfinConfig {
+    /// `library.db` (ou `jellyfin.db` depuis 10.10), ouvert en lecture seule.
+    pub library_db: Option<PathBuf>,
+    /// Dossier `metadata`, par défaut `<dossier de la base>/../metadata`.
+    pub metadata_dir: Option<PathBuf>,
+    /// Préfixes des chemins vus par Jellyfin → chemins locaux (Docker, montage réseau).
+    pub path_map: BTreeMap<String, PathBuf>,
+    /// Serveur pour la sous-commande `jellyfin`, par exemple `http://localhost:8096`.
+    pub url: Option<String>,
+    /// Clé d'API (Tableau de bord → Clés d'API), sinon `$JELLYFIN_API_KEY`.
+    pub api_key: Option<String>,
+}
+
+impl JellyfinConfig {
+    pub fn metadata_dir(&self) -> Option<PathBuf> {
+        self.metadata_dir
+            .clone()
+            .or_else(|| Some(self.library_db.as_deref()?.parent()?.parent()?.join("metadata")))
+    }
+
+    /// Chemin Jellyfin → chemin local, par le plus long préfixe de `path_map`.
+    pub fn local_path(&self, jellyfin_path: &str) -> PathBuf {
+        self.path_map
+            .iter()
+            .filter(|(prefix, _)| Path::new(jellyfin_path).starts_with(prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(prefix, local)| local.join(Path::new(jellyfin_path).strip_prefix(prefix).unwrap_or(Path::new(""))))
+            .unwrap_or_else(|| PathBuf::from(jellyfin_path))
+    }
+}
+
+fn default_interval_ms() -> u64 {
+    10_000
+}
+
+fn default_generation_speed() -> f64 {
+    20.0
+}
+
+/// Surtout de l'attente réseau sur un NAS : plus de workers que de cœurs reste utile.
+fn default_scan_threads() -> usize {
+    8
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct CategoryConfig {
+    pub name: String,
+    #[serde(default)]
+    pub roots: Vec<PathBuf>,
+    /// Temps de génération par fichier quand la durée de la vidéo est illisible, en minutes.
+    #[serde(default)]
+    pub minutes_per_file: u64,
+    /// Remplace `generation_speed` pour cette catégorie.
+    pub generation_speed: Option<f64>,
+    /// Règles sidecars propres à cette catégorie, en plus des communes.
+    #[serde(rename = "sidecar", alias = "sidecars", default)]
+    pub sidecars: Vec<SidecarRule>,
+}
+
+impl LibraryConfig {
+    /// Charge la config depuis un fichier TOML, ou JSON si l'extension est `.json`.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Impossible de lire {}: {}", path.display(), e))?;
+
+        let config: LibraryConfig = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))?
+        } else {
+            toml::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))?
+        };
+
+        if config.categories.is_empty() {
+            return Err(format!("{}: aucune catégorie définie", path.display()));
+        }
+
+        Ok(config)
+    }
+
+    /// `--config` en priorité, puis `./missing_trickplay.toml`, puis
+    /// `$XDG_CONFIG_HOME/missing_trickplay/config.toml` (ou `~/.config/...`).
+    pub fn find_default_path() -> Option<PathBuf> {
+        let local = PathBuf::from(CONFIG_FILE_NAME);
+        if local.is_file() {
+            return Some(local);
+        }
+
+        let config_dir = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+        let user = config_dir.join("missing_trickplay").join("config.toml");
+        user.is_file().then_some(user)
+    }
+
+    /// `$XDG_DATA_HOME/missing_trickplay` (ou `~/.local/share/...`) : journaux, files d'attente, historique.
+    pub fn data_dir() -> PathBuf {
+        env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")))
+            .unwrap_or_else(env::temp_dir)
+            .join("missing_trickplay")
+    }
+
+    /// Bibliothèque utilisée quand aucun fichier de config n'est trouvé.
+    pub fn builtin() -> Self {
+        let category = |name: &str, roots: [&str; 3], minutes_per_file| CategoryConfig {
+            name: name.to_string(),
+            roots: roots.iter().map(PathBuf::from).collect(),
+            minutes_per_file,
+            generation_speed: None,
+            sidecars: Vec::new(),
+        };
+
+        LibraryConfig {
+            trickplay_interval_ms: default_interval_ms(),
+            generation_speed: default_generation_speed(),
+            scan_threads: default_scan_threads(),
+            keep_snapshots: None,
+            generate: GenerateConfig::default(),
+            jellyfin: JellyfinConfig::default(),
+            sidecars: Vec::new(),
+            categories: vec![
+                category("Films", ["/Volumes/3To/Films", "/Volumes/470G M2/film", "/Users/anna/Movies/film"], 40),
+                category("Anime", ["/Volumes/3To/Anime", "/Volumes/470G M2/Anime", "/Users/anna/Movies/Anime"], 1),
+                category("Séries", ["/Volumes/3To/Séries", "/Volumes/470G M2/séries", "/Users/anna/Movies/séries"], 20),
+            ],
+        }
+    }
+
+    pub fn speed_for(&self, category: &CategoryConfig) -> f64 {
+        category.generation_speed.unwrap_or(self.generation_speed)
+    }
+
+    pub fn sidecar_rules_for<'a>(&'a self, category: &'a CategoryConfig) -> Vec<&'a SidecarRule> {
+        self.sidecars.iter().chain(&category.sidecars).collect()
+    }
+
+    /// Garde uniquement les catégories demandées (insensible à la casse).
+    pub fn restrict_to(&mut self, names: &[String]) -> Result<(), String> {
+        if names.is_empty() {
+            return Ok(());
+        }
+
+        for name in names {
+            if !self.categories.iter().any(|c| c.name.to_lowercase() == name.to_lowercase()) {
+                let known: Vec<&str> = self.categories.iter().map(|c| c.name.as_str()).collect();
+                return Err(format!("Catégorie inconnue '{}' (disponibles : {})", name, known.join(", ")));
+            }
+        }
+
+        self.categories
+            .retain(|c| names.iter().any(|name| c.name.to_lowercase() == name.to_lowercase()));
+        Ok(())
+    }
+}
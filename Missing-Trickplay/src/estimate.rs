@@ -0,0 +1,47 @@
+use crate::media::MediaInfo;
+use std::time::Duration;
+
+/// Résolution de référence de `generation_speed` : une 4K compte quatre fois plus qu'une 1080p.
+const REFERENCE_PIXELS: f64 = 1920.0 * 1080.0;
+
+/// Temps de génération cumulé pour un ensemble de vidéos.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Estimate {
+    pub files: usize,
+    /// Durée totale des vidéos dont l'en-tête a pu être lu.
+    pub runtime: Duration,
+    /// Vidéos sans durée lisible, comptées avec `minutes_per_file`.
+    pub unknown: usize,
+    pub time: Duration,
+}
+
+impl Estimate {
+    /// `speed` : combien de fois plus vite que le temps réel Jellyfin génère une vidéo 1080p.
+    pub fn add(&mut self, info: &MediaInfo, speed: f64, minutes_per_file: u64) {
+        self.files += 1;
+
+        match info.duration {
+            Some(duration) if speed > 0.0 => {
+                let scale = info.pixels().map_or(1.0, |pixels| (pixels as f64 / REFERENCE_PIXELS).max(0.25));
+                self.runtime += duration;
+                self.time += duration.mul_f64(scale / speed);
+            }
+            _ => {
+                self.unknown += 1;
+                self.time += Duration::from_secs(minutes_per_file * 60);
+            }
+        }
+    }
+
+    pub fn merge(&mut self, other: &Estimate) {
+        self.files += other.files;
+        self.runtime += other.runtime;
+        self.unknown += other.unknown;
+        self.time += other.time;
+    }
+}
+
+pub fn format_minutes(duration: Duration) -> String {
+    let minutes = duration.as_secs().div_ceil(60);
+    format!("~{} min ({}h{}min)", minutes, minutes / 60, minutes % 60)
+}
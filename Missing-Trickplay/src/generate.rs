@@ -0,0 +1,311 @@
+use crate::config::GenerateConfig;
+use crate::media;
+use crate::report::{ScanReport, Status};
+use crate::stale::SourceInfo;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Pending,
+    Running,
+    Done,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub video: PathBuf,
+    pub trickplay: PathBuf,
+    pub state: JobState,
+    pub attempts: u32,
+    pub error: Option<String>,
+}
+
+/// File d'attente persistée en JSON : un arrêt en cours de route reprend au lancement suivant.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Queue {
+    #[serde(skip)]
+    path: PathBuf,
+    pub jobs: Vec<Job>,
+}
+
+impl Queue {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut queue: Queue = match File::open(path) {
+            Ok(file) => serde_json::from_reader(file).map_err(io::Error::other)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Queue::default(),
+            Err(e) => return Err(e),
+        };
+        queue.path = path.to_path_buf();
+
+        // Jobs interrompus par un arrêt brutal
+        for job in &mut queue.jobs {
+            if job.state == JobState::Running {
+                job.state = JobState::Pending;
+            }
+        }
+        Ok(queue)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Écrit à côté puis renomme, pour ne jamais laisser une file tronquée.
+        let tmp = self.path.with_extension("json.tmp");
+        serde_json::to_writer_pretty(File::create(&tmp)?, self).map_err(io::Error::other)?;
+        fs::rename(tmp, &self.path)
+    }
+
+    /// Ajoute les vidéos manquantes (et à regénérer si demandé) qui ne sont pas déjà en file.
+    pub fn enqueue(&mut self, report: &ScanReport, include_incomplete: bool, retry_failed: bool) -> usize {
+        self.jobs.retain(|job| job.state != JobState::Done);
+        if retry_failed {
+            for job in self.jobs.iter_mut().filter(|job| job.state == JobState::Failed) {
+                job.state = JobState::Pending;
+            }
+        }
+
+        let mut added = 0;
+        for entry in report.entries.iter().filter(|e| e.is_trickplay()) {
+            let wanted = match entry.status {
+                Status::Missing => true,
+                status if status.needs_regeneration() => include_incomplete,
+                _ => false,
+            };
+            let Some(video) = entry.video.as_ref().filter(|_| wanted) else { continue };
+            if self.jobs.iter().any(|job| &job.video == video) {
+                continue;
+            }
+
+            let trickplay = entry.trickplay.clone().unwrap_or_else(|| video.with_extension("trickplay"));
+            self.jobs.push(Job { video: video.clone(), trickplay, state: JobState::Pending, attempts: 0, error: None });
+            added += 1;
+        }
+        added
+    }
+
+    pub fn count(&self, state: JobState) -> usize {
+        self.jobs.iter().filter(|job| job.state == state).count()
+    }
+}
+
+/// FNV-1a 64 bits : stable d'une version de Rust à l'autre, contrairement à `DefaultHasher`.
+fn path_hash(path: &Path) -> u64 {
+    path.as_os_str()
+        .as_encoded_bytes()
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// `<nom>-<hash du chemin complet>.log` : deux `S01E01.mkv` de séries différentes ont chacun leur log.
+fn log_path(log_dir: &Path, video: &Path) -> PathBuf {
+    let stem = video.file_stem().unwrap_or_default().to_string_lossy();
+    log_dir.join(format!("{}-{:016x}.log", stem.replace(['/', '\\'], "_"), path_hash(video)))
+}
+
+/// Lance ffmpeg sur une vidéo. Les tuiles sont écrites dans un dossier temporaire caché,
+/// renommé en `<nom>.trickplay` seulement si ffmpeg réussit.
+fn run_ffmpeg(settings: &GenerateConfig, interval: Duration, job: &Job, log_dir: &Path) -> Result<(), String> {
+    let trickplay_name = job.trickplay.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = job.trickplay.with_file_name(format!(".{}.partial", trickplay_name));
+    let resolution = format!("{} - {}x{}", settings.width, settings.tile_width, settings.tile_height);
+    let _ = fs::remove_dir_all(&tmp);
+    fs::create_dir_all(tmp.join(&resolution)).map_err(|e| e.to_string())?;
+
+    fs::create_dir_all(log_dir).map_err(|e| e.to_string())?;
+    let log_file = log_path(log_dir, &job.video);
+    let mut log = File::create(&log_file).map_err(|e| e.to_string())?;
+
+    let filter = format!(
+        "fps=1000/{},scale={}:-2,tile={}x{}",
+        interval.as_millis(),
+        settings.width,
+        settings.tile_width,
+        settings.tile_height
+    );
+    let mut command = Command::new(&settings.ffmpeg);
+    command
+        .args(["-hide_banner", "-loglevel", "error", "-nostdin", "-y", "-i"])
+        .arg(&job.video)
+        .args(["-an", "-sn", "-vf", &filter, "-q:v", &settings.quality.to_string(), "-start_number", "0"])
+        .arg(tmp.join(&resolution).join("%d.jpg"))
+        .stdout(Stdio::null())
+        .stderr(log.try_clone().map_err(|e| e.to_string())?);
+    let _ = writeln!(log, "{:?}", command);
+
+    let status = command.status().map_err(|e| format!("{}: {}", settings.ffmpeg.display(), e))?;
+    if !status.success() {
+        let _ = fs::remove_dir_all(&tmp);
+        return Err(format!("ffmpeg a échoué ({}), voir {}", status, log_file.display()));
+    }
+
+    // Source de la génération, pour reconnaître plus tard une vidéo remplacée. Pas bloquant :
+    // sans ce fichier, la détection retombe sur les dates.
+    if let Ok(source) = SourceInfo::read(&job.video, &media::probe(&job.video)) {
+        let _ = source.save(&tmp);
+    }
+
+    // Remplace un éventuel ancien dossier incomplet
+    if job.trickplay.exists() {
+        fs::remove_dir_all(&job.trickplay).map_err(|e| e.to_string())?;
+    }
+    fs::rename(&tmp, &job.trickplay).map_err(|e| e.to_string())
+}
+
+/// Traite les jobs en attente avec `jobs` ffmpeg en parallèle.
+pub fn run(queue: Queue, settings: &GenerateConfig, interval: Duration, jobs: usize, log_dir: &Path) -> io::Result<Queue> {
+    let total = queue.count(JobState::Pending);
+    let queue = Mutex::new(queue);
+    let finished = Mutex::new(0usize);
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.max(1) {
+            scope.spawn(|| {
+                loop {
+                    let job = {
+                        let mut queue = queue.lock().unwrap();
+                        let Some(index) = queue.jobs.iter().position(|job| job.state == JobState::Pending) else { break };
+                        queue.jobs[index].state = JobState::Running;
+                        queue.jobs[index].attempts += 1;
+                        if let Err(e) = queue.save() {
+                            eprintln!("Failed to save queue: {}", e);
+                        }
+                        queue.jobs[index].clone()
+                    };
+
+                    let started = Instant::now();
+                    let result = run_ffmpeg(settings, interval, &job, log_dir);
+
+                    let mut queue = queue.lock().unwrap();
+                    let mut finished = finished.lock().unwrap();
+                    *finished += 1;
+                    let name = job.video.file_name().unwrap_or_default().to_string_lossy();
+
+                    if let Some(queued) = queue.jobs.iter_mut().find(|queued| queued.video == job.video) {
+                        match result {
+                            Ok(()) => {
+                                println!("[{}/{}] ✅ {} ({}s)", finished, total, name, started.elapsed().as_secs());
+                                queued.state = JobState::Done;
+                                queued.error = None;
+                            }
+                            Err(e) => {
+                                println!("[{}/{}] ❌ {} : {}", finished, total, name, e);
+                                queued.state = JobState::Failed;
+                                queued.error = Some(e);
+                            }
+                        }
+                    }
+                    if let Err(e) = queue.save() {
+                        eprintln!("Failed to save queue: {}", e);
+                    }
+                }
+            });
+        }
+    });
+
+    Ok(queue.into_inner().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    const RESOLUTION: &str = "320 - 10x10";
+
+    /// Copie `Example/fake-ffmpeg.sh` dans `dir`, exécutable.
+    fn fake_ffmpeg(dir: &Path) -> GenerateConfig {
+        let ffmpeg = dir.join("fake-ffmpeg.sh");
+        fs::copy(Path::new(env!("CARGO_MANIFEST_DIR")).join("Example/fake-ffmpeg.sh"), &ffmpeg).unwrap();
+        fs::set_permissions(&ffmpeg, fs::Permissions::from_mode(0o755)).unwrap();
+        GenerateConfig { ffmpeg, ..GenerateConfig::default() }
+    }
+
+    fn video(dir: &Path, relative: &str) -> PathBuf {
+        let path = dir.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        File::create(&path).unwrap();
+        path
+    }
+
+    fn job(video: &Path, state: JobState) -> Job {
+        Job { video: video.to_path_buf(), trickplay: video.with_extension("trickplay"), state, attempts: 0, error: None }
+    }
+
+    fn partial(video: &Path) -> PathBuf {
+        video.with_file_name(format!(".{}.trickplay.partial", video.file_stem().unwrap().to_string_lossy()))
+    }
+
+    #[test]
+    fn run_renames_partial_and_records_failures() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = fake_ffmpeg(dir.path());
+        let ok = video(dir.path(), "Films/Ok/Ok.mkv");
+        let failing = video(dir.path(), "Films/FAIL/FAIL.mkv");
+
+        let mut queue = Queue::load(&dir.path().join("queue.json")).unwrap();
+        queue.jobs = vec![job(&ok, JobState::Pending), job(&failing, JobState::Pending)];
+        let queue = run(queue, &settings, Duration::from_secs(10), 2, &dir.path().join("logs")).unwrap();
+
+        let tiles = ok.with_extension("trickplay").join(RESOLUTION);
+        assert!(tiles.join("0.jpg").is_file() && tiles.join("1.jpg").is_file());
+        assert!(!partial(&ok).exists());
+        assert_eq!(queue.jobs[0].state, JobState::Done);
+
+        assert!(!failing.with_extension("trickplay").exists());
+        assert!(!partial(&failing).exists());
+        assert_eq!(queue.jobs[1].state, JobState::Failed);
+        assert_eq!(queue.jobs[1].attempts, 1);
+        assert!(queue.jobs[1].error.as_deref().is_some_and(|e| e.contains("ffmpeg a échoué")));
+
+        // La file sur disque garde le même résultat
+        let saved = Queue::load(&dir.path().join("queue.json")).unwrap();
+        assert_eq!(saved.count(JobState::Done), 1);
+        assert_eq!(saved.count(JobState::Failed), 1);
+    }
+
+    #[test]
+    fn resume_skips_completed_jobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = fake_ffmpeg(dir.path());
+        let done = video(dir.path(), "Séries/Show A/S01E01.mkv");
+        let interrupted = video(dir.path(), "Séries/Show A/S01E02.mkv");
+        let queue_path = dir.path().join("queue.json");
+
+        // Un job terminé avant l'arrêt, un autre interrompu en cours de route
+        fs::create_dir_all(done.with_extension("trickplay")).unwrap();
+        let marker = done.with_extension("trickplay").join("déjà là");
+        File::create(&marker).unwrap();
+        let mut queue = Queue::load(&queue_path).unwrap();
+        queue.jobs = vec![Job { attempts: 1, ..job(&done, JobState::Done) }, Job { attempts: 1, ..job(&interrupted, JobState::Running) }];
+        queue.save().unwrap();
+
+        let queue = Queue::load(&queue_path).unwrap();
+        assert_eq!(queue.count(JobState::Pending), 1);
+        let queue = run(queue, &settings, Duration::from_secs(10), 1, &dir.path().join("logs")).unwrap();
+
+        assert!(marker.is_file(), "le job terminé a été relancé");
+        assert_eq!(queue.jobs[0].attempts, 1);
+        assert_eq!(queue.jobs[1].state, JobState::Done);
+        assert_eq!(queue.jobs[1].attempts, 2);
+        assert!(interrupted.with_extension("trickplay").join(RESOLUTION).join("0.jpg").is_file());
+    }
+
+    #[test]
+    fn log_names_differ_for_same_stem() {
+        let logs = Path::new("/logs");
+        let a = log_path(logs, Path::new("/tv/Show A/Season 1/S01E01.mkv"));
+        let b = log_path(logs, Path::new("/tv/Show B/Season 1/S01E01.mkv"));
+        assert_ne!(a, b);
+        assert!(a.file_name().unwrap().to_string_lossy().starts_with("S01E01-"));
+    }
+}
@@ -0,0 +1,136 @@
+use crate::config::JellyfinConfig;
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use unicode_normalization::UnicodeNormalization;
+
+/// Éléments de la base Jellyfin, pour retrouver les Trickplay rangés dans
+/// `metadata/library/<2 premiers>/<itemId>/trickplay` au lieu d'à côté du média.
+pub struct LibraryIndex {
+    metadata_dir: PathBuf,
+    /// Chemin local du média (NFC) → identifiant au format `N` (32 hex minuscules)
+    items: HashMap<String, String>,
+}
+
+fn path_key(path: &Path) -> String {
+    path.to_string_lossy().nfc().collect()
+}
+
+/// Identifiant Jellyfin : BLOB de 16 octets dans `library.db`, texte avec tirets dans `jellyfin.db`.
+fn item_id(value: ValueRef) -> Option<String> {
+    match value {
+        // Guid .NET : les trois premiers groupes sont stockés en petit-boutiste
+        ValueRef::Blob(bytes) if bytes.len() == 16 => Some(
+            [3, 2, 1, 0, 5, 4, 7, 6, 8, 9, 10, 11, 12, 13, 14, 15]
+                .iter()
+                .map(|&i| format!("{:02x}", bytes[i]))
+                .collect(),
+        ),
+        ValueRef::Text(text) => {
+            let id: String = String::from_utf8_lossy(text).chars().filter(|c| *c != '-').collect();
+            (id.len() == 32).then(|| id.to_lowercase())
+        }
+        _ => None,
+    }
+}
+
+impl LibraryIndex {
+    /// `None` si `library_db` n'est pas configuré.
+    pub fn open(config: &JellyfinConfig) -> Result<Option<Self>, String> {
+        let Some(db) = &config.library_db else { return Ok(None) };
+        let err = |e: rusqlite::Error| format!("{}: {}", db.display(), e);
+
+        let conn = Connection::open_with_flags(db, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX).map_err(err)?;
+        let metadata_dir = config.metadata_dir().ok_or_else(|| format!("{}: dossier metadata introuvable", db.display()))?;
+        let has_table = |name: &str| {
+            conn.query_row("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1", [name], |_| Ok(()))
+                .is_ok()
+        };
+
+        // Jusqu'à 10.9 : `TypedBaseItems` ; depuis 10.10 (EF Core) : `BaseItems`
+        let query = if has_table("TypedBaseItems") {
+            "SELECT guid, Path FROM TypedBaseItems WHERE Path IS NOT NULL"
+        } else if has_table("BaseItems") {
+            "SELECT Id, Path FROM BaseItems WHERE Path IS NOT NULL"
+        } else {
+            return Err(format!("{}: ni TypedBaseItems ni BaseItems, est-ce une base Jellyfin ?", db.display()));
+        };
+
+        let mut items = HashMap::new();
+        let mut statement = conn.prepare(query).map_err(err)?;
+        let mut rows = statement.query([]).map_err(err)?;
+        while let Some(row) = rows.next().map_err(err)? {
+            let (Some(id), Ok(path)) = (item_id(row.get_ref(0).map_err(err)?), row.get::<_, String>(1)) else { continue };
+            items.insert(path_key(&config.local_path(&path)), id);
+        }
+
+        Ok(Some(LibraryIndex { metadata_dir, items }))
+    }
+
+    /// Dossier Trickplay de l'élément dans le dossier metadata, s'il existe.
+    pub fn trickplay_for(&self, video: &Path) -> Option<PathBuf> {
+        let id = self.items.get(&path_key(video))?;
+        let dir = self.metadata_dir.join("library").join(&id[..2]).join(id).join("trickplay");
+        dir.is_dir().then_some(dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// `<tmp>/data/library.db` construit depuis `Example/jellyfin-library.sql`, `/media` monté sur `<tmp>/nas`.
+    fn library(dir: &Path) -> JellyfinConfig {
+        let db = dir.join("data").join("library.db");
+        fs::create_dir_all(db.parent().unwrap()).unwrap();
+        Connection::open(&db).unwrap().execute_batch(include_str!("../Example/jellyfin-library.sql")).unwrap();
+        JellyfinConfig {
+            library_db: Some(db),
+            path_map: [("/media".to_string(), dir.join("nas"))].into(),
+            ..JellyfinConfig::default()
+        }
+    }
+
+    #[test]
+    fn finds_trickplay_by_dotnet_guid_and_path_map() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = library(dir.path());
+        let library_dir = dir.path().join("metadata").join("library");
+        let expected = library_dir.join("01").join("0123456789abcdef0123456789abcdef").join("trickplay");
+        fs::create_dir_all(&expected).unwrap();
+
+        let index = LibraryIndex::open(&config).unwrap().unwrap();
+        assert_eq!(index.trickplay_for(&dir.path().join("nas/Films/Alien 3/FAIL.mkv")), Some(expected));
+        // Le chemin vu par Jellyfin n'est pas le chemin local
+        assert_eq!(index.trickplay_for(Path::new("/media/Films/Alien 3/FAIL.mkv")), None);
+
+        // Pas de dossier trickplay pour cet élément, jusqu'à ce qu'il soit créé
+        let alien = dir.path().join("nas/Films/Alien/Alien.mkv");
+        assert_eq!(index.trickplay_for(&alien), None);
+        let expected = library_dir.join("33").join("33221100554477668899aabbccddeeff").join("trickplay");
+        fs::create_dir_all(&expected).unwrap();
+        assert_eq!(index.trickplay_for(&alien), Some(expected));
+    }
+
+    #[test]
+    fn reads_text_ids_of_jellyfin_10_10() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("data").join("jellyfin.db");
+        fs::create_dir_all(db.parent().unwrap()).unwrap();
+        Connection::open(&db)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE BaseItems (Id TEXT PRIMARY KEY NOT NULL, Path TEXT);
+                 INSERT INTO BaseItems VALUES ('01234567-89AB-CDEF-0123-456789ABCDEF', '/media/Films/Alien/Alien.mkv');",
+            )
+            .unwrap();
+        let expected = dir.path().join("metadata/library/01/0123456789abcdef0123456789abcdef/trickplay");
+        fs::create_dir_all(&expected).unwrap();
+
+        let config = JellyfinConfig { library_db: Some(db), ..JellyfinConfig::default() };
+        let index = LibraryIndex::open(&config).unwrap().unwrap();
+        assert_eq!(index.trickplay_for(Path::new("/media/Films/Alien/Alien.mkv")), Some(expected));
+    }
+}
@@ -1,182 +1,373 @@
-use std::collections::BTreeMap;
-use std::fs;
-use std::os::unix::prelude::OsStrExt;
+mod api;
+mod bif;
+mod config;
+mod estimate;
+mod generate;
+mod jellyfin;
+mod media;
+mod pairing;
+mod prune;
+mod report;
+mod scan;
+mod sidecar;
+mod snapshot;
+mod stale;
+mod trickplay;
+mod watch;
+
+use clap::{Parser, Subcommand};
+use config::LibraryConfig;
+use report::{OutputFormat, Status};
+use std::io;
 use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Duration;
 
-fn find_videos_and_trickplay(folder: &Path) -> (Vec<PathBuf>, Vec<PathBuf>) {
-    let mut video_files = Vec::new();
-    let mut trickplay_folders = Vec::new();
-
-    if let Ok(entries) = fs::read_dir(folder) {
-        for entry in entries.flatten() {
-            let sub_path = entry.path();
-
-            if sub_path.is_dir() {
-                if sub_path.file_name().map_or(false, |name| name.to_string_lossy().ends_with(".trickplay")) {
-                    trickplay_folders.push(sub_path);
-                } else {
-                    let (sub_videos, sub_trickplay) = find_videos_and_trickplay(&sub_path);
-                    video_files.extend(sub_videos);
-                    trickplay_folders.extend(sub_trickplay);
-                }
-            } else if let Some(ext) = sub_path.extension() {
-                if ext == "mkv" || ext == "mp4" {
-                    video_files.push(sub_path);
-                }
-            }
+#[derive(Parser, Debug)]
+#[command(author = "CatAnnaDev", version, about = "Find Jellyfin videos without trickplay", long_about = None)]
+struct ClapArgs {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[arg(short = 'c', long, global = true, help = "Library config file (TOML, or JSON with a .json extension)")]
+    config: Option<PathBuf>,
+
+    #[arg(short = 'C', long = "category", global = true, help = "Only check this category, can be repeated")]
+    categories: Vec<String>,
+
+    #[arg(short = 'r', long = "root", global = true, requires = "categories", help = "Replace the roots of the selected categories, can be repeated")]
+    roots: Vec<PathBuf>,
+
+    #[arg(short = 'm', long, global = true, help = "Override the estimated minutes per file when its duration can't be read")]
+    minutes_per_file: Option<u64>,
+
+    #[arg(short = 's', long, global = true, help = "Override the generation speed, as a multiple of real time for a 1080p video")]
+    speed: Option<f64>,
+
+    #[arg(long, global = true, help = "Jellyfin library.db (or jellyfin.db), to find trickplay stored in its metadata folder")]
+    library_db: Option<PathBuf>,
+
+    #[arg(long, global = true, help = "Number of item folders scanned in parallel, default from the config")]
+    scan_threads: Option<usize>,
+
+    #[arg(long, help = "Only check that .trickplay folders exist, without reading their tiles")]
+    skip_validation: bool,
+
+    #[arg(short = 'f', long, value_enum, default_value = "text", help = "Output format")]
+    format: OutputFormat,
+
+    #[arg(long, help = "Exit with code 1 when more videos than this are missing trickplay")]
+    max_missing: Option<usize>,
+
+    #[arg(long, help = "Exit with code 1 when there are more orphan .trickplay folders than this")]
+    max_orphan: Option<usize>,
+
+    #[arg(long, help = "Exit with code 1 when more .trickplay folders than this are incomplete or corrupt")]
+    max_incomplete: Option<usize>,
+
+    #[arg(long, help = "Exit with code 1 when more .trickplay folders than this are older than their video")]
+    max_stale: Option<usize>,
+
+    #[arg(long, help = "Exit with code 1 when more sidecar files than this are missing")]
+    max_sidecar_missing: Option<usize>,
+
+    #[arg(long, help = "Don't save this scan as a snapshot for `diff`")]
+    no_snapshot: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Move orphan .trickplay folders to a quarantine directory
+    Prune {
+        #[arg(long, help = "Only list the orphans and the reclaimable space")]
+        dry_run: bool,
+
+        #[arg(short = 'q', long, help = "Quarantine directory, default is <root>/.trickplay-quarantine on each volume")]
+        quarantine: Option<PathBuf>,
+
+        #[arg(long, help = "Also quarantine the trickplay of videos replaced since it was generated")]
+        stale: bool,
+    },
+    /// Generate the missing trickplay with ffmpeg, through a resumable queue
+    Generate {
+        #[arg(short = 'j', long, help = "Number of ffmpeg processes in parallel, default from the config")]
+        jobs: Option<usize>,
+
+        #[arg(long, help = "Path of the ffmpeg binary, default from the config")]
+        ffmpeg: Option<PathBuf>,
+
+        #[arg(long, help = "Also regenerate incomplete, corrupt or stale trickplay")]
+        include_incomplete: bool,
+
+        #[arg(long, help = "Put failed jobs back in the queue")]
+        retry_failed: bool,
+
+        #[arg(long, help = "Only fill the queue, without running ffmpeg")]
+        dry_run: bool,
+    },
+    /// Compare two saved scans: newly missing, generated and orphaned trickplay
+    Diff {
+        #[arg(help = "Older snapshot, default is the one before the latest")]
+        from: Option<String>,
+
+        #[arg(help = "Newer snapshot, default is the latest one")]
+        to: Option<String>,
+
+        #[arg(long, help = "Show the coverage per category of every snapshot instead")]
+        history: bool,
+
+        #[arg(short = 'f', long, value_enum, default_value = "text", help = "Output format")]
+        format: OutputFormat,
+    },
+    /// Compare the library on disk with the items and trickplay known by the Jellyfin API
+    Jellyfin {
+        #[arg(long, help = "Jellyfin server URL, default from the config")]
+        url: Option<String>,
+
+        #[arg(long, help = "Jellyfin API key, default from the config or $JELLYFIN_API_KEY")]
+        api_key: Option<String>,
+
+        #[arg(short = 'f', long, value_enum, default_value = "text", help = "Output format")]
+        format: OutputFormat,
+    },
+    /// Write a Roku .bif next to each video from its trickplay tiles
+    ExportBif {
+        #[arg(short = 'w', long, help = "Tile width to export, default is the widest one")]
+        width: Option<u32>,
+
+        #[arg(short = 'q', long, default_value_t = 85, value_parser = clap::value_parser!(u8).range(1..=100), help = "JPEG quality of the frames")]
+        quality: u8,
+
+        #[arg(long, help = "Rewrite .bif files newer than their trickplay")]
+        force: bool,
+
+        #[arg(long, help = "Only list the .bif files to write")]
+        dry_run: bool,
+    },
+    /// Watch the roots and serve the live coverage over HTTP/JSON
+    Watch {
+        #[arg(short = 'l', long, default_value = "127.0.0.1:8787", help = "Address of the HTTP endpoint")]
+        listen: String,
+
+        #[arg(long, help = "Only check that .trickplay folders exist, without reading their tiles")]
+        skip_validation: bool,
+    },
+    /// Move back the folders of a prune batch
+    Restore {
+        #[arg(help = "Batch to restore, default is the latest one")]
+        batch: Option<String>,
+
+        #[arg(long, help = "Only list what would be restored")]
+        dry_run: bool,
+
+        #[arg(short = 'l', long, help = "List the quarantined batches")]
+        list: bool,
+    },
+}
+
+fn load_config(args: &ClapArgs) -> Result<LibraryConfig, String> {
+    let mut config = match args.config.clone().or_else(LibraryConfig::find_default_path) {
+        Some(path) => LibraryConfig::load(&path)?,
+        None => LibraryConfig::builtin(),
+    };
+
+    config.restrict_to(&args.categories)?;
+    if args.library_db.is_some() {
+        config.jellyfin.library_db = args.library_db.clone();
+    }
+    if let Some(threads) = args.scan_threads {
+        config.scan_threads = threads.max(1);
+    }
+
+    for category in &mut config.categories {
+        if !args.roots.is_empty() {
+            category.roots = args.roots.clone();
+        }
+        if let Some(minutes) = args.minutes_per_file {
+            category.minutes_per_file = minutes;
+        }
+        if args.speed.is_some() {
+            category.generation_speed = args.speed;
         }
     }
 
-    (video_files, trickplay_folders)
+    Ok(config)
 }
 
-fn main() {
-    let paths = [
-        ("Films", [
-            Path::new("/Volumes/3To/Films"),
-            Path::new("/Volumes/470G M2/film"),
-            Path::new("/Users/anna/Movies/film"),
-        ]),
-        ("Anime", [
-            Path::new("/Volumes/3To/Anime"),
-            Path::new("/Volumes/470G M2/Anime"),
-            Path::new("/Users/anna/Movies/Anime"),
-        ]),
-        ("Séries", [
-            Path::new("/Volumes/3To/Séries"),
-            Path::new("/Volumes/470G M2/séries"),
-            Path::new("/Users/anna/Movies/séries"),
-        ]),
+/// Ouvre la base Jellyfin si elle est configurée, quitte avec le code 2 si elle est illisible.
+fn open_library(config: &LibraryConfig) -> Option<jellyfin::LibraryIndex> {
+    jellyfin::LibraryIndex::open(&config.jellyfin).unwrap_or_else(|e| {
+        eprintln!("Erreur : {}", e);
+        process::exit(2);
+    })
+}
+
+/// Seuils `--max-*` dépassés, sous la forme `(libellé, nombre, seuil)`.
+fn exceeded_thresholds(args: &ClapArgs, report: &report::ScanReport) -> Vec<(&'static str, usize, usize)> {
+    let checks = [
+        ("missing", report.count(Status::Missing), args.max_missing),
+        ("orphan", report.count(Status::Orphan), args.max_orphan),
+        ("incomplete", report.count(Status::Incomplete) + report.count(Status::Corrupt), args.max_incomplete),
+        ("stale", report.count(Status::Stale), args.max_stale),
+        ("sidecar-missing", report.count_sidecars(Status::Missing), args.max_sidecar_missing),
     ];
 
-    let mut total_mkv = 0;
-    let mut total_trickplay = 0;
-    let mut total_missing_trickplay = 0;
-    let mut total_orphan_trickplay = 0;
-    let mut estimated_time = 0;
-
-    let mut missing_trickplay_by_category: BTreeMap<&str, BTreeMap<&Path, Vec<String>>> = BTreeMap::new();
-    let mut orphan_trickplay_by_category: BTreeMap<&str, BTreeMap<&Path, Vec<String>>> = BTreeMap::new();
-
-    for (category, base_paths) in &paths {
-        let mut missing_results: BTreeMap<&Path, Vec<String>> = BTreeMap::new();
-        let mut orphan_results: BTreeMap<&Path, Vec<String>> = BTreeMap::new();
-
-        for base_path in base_paths {
-            if let Ok(entries) = fs::read_dir(base_path) {
-                let mut missing_files = Vec::new();
-                let mut orphan_files = Vec::new();
-
-                for entry in entries.flatten() {
-                    let folder_path = entry.path();
-                    if folder_path.is_dir() {
-                        let (mkv_files, trickplay_folders) = find_videos_and_trickplay(&folder_path);
-
-                        total_mkv += mkv_files.len();
-                        total_trickplay += trickplay_folders.len();
-
-                        let trickplay_names: Vec<&std::ffi::OsStr> = trickplay_folders
-                            .iter()
-                            .filter_map(|tp| tp.file_name())
-                            .collect();
-
-                        let mkv_names: Vec<&std::ffi::OsStr> = mkv_files
-                            .iter()
-                            .filter_map(|mkv| mkv.file_stem())
-                            .collect();
-
-                        // Vérifie les fichiers MKV sans Trickplay
-                        let missing_trickplay: Vec<String> = mkv_names
-                            .iter()
-                            .filter(|name| !trickplay_names.iter().any(|tp| tp.as_bytes().starts_with(name.as_bytes())))
-                            .map(|name| name.to_string_lossy().into_owned())
-                            .collect();
-
-                        // Vérifie les Trickplay sans MKV
-                        let orphan_trickplay: Vec<String> = trickplay_names
-                            .iter()
-                            .filter(|tp| !mkv_names.iter().any(|mkv| tp.as_bytes().starts_with(mkv.as_bytes())))
-                            .map(|tp| tp.to_string_lossy().into_owned())
-                            .collect();
-
-                        total_missing_trickplay += missing_trickplay.len();
-                        total_orphan_trickplay += orphan_trickplay.len();
-                        missing_files.extend(missing_trickplay);
-                        orphan_files.extend(orphan_trickplay);
-                    }
-                }
+    checks
+        .into_iter()
+        .filter_map(|(label, count, max)| max.filter(|max| count > *max).map(|max| (label, count, max)))
+        .collect()
+}
 
-                if !missing_files.is_empty() {
-                    missing_results.insert(base_path, missing_files);
-                }
-                if !orphan_files.is_empty() {
-                    orphan_results.insert(base_path, orphan_files);
-                }
-            }
-        }
+fn main() {
+    let args = ClapArgs::parse();
 
-        if !missing_results.is_empty() {
-            missing_trickplay_by_category.insert(category, missing_results);
-        }
-        if !orphan_results.is_empty() {
-            orphan_trickplay_by_category.insert(category, orphan_results);
+    let config = match load_config(&args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Erreur de configuration : {}", e);
+            process::exit(2);
         }
-    }
+    };
 
-    for (category, paths) in &missing_trickplay_by_category {
-        println!("\n📂 **{}**", category);
-        for (base_path, files) in paths {
-            println!("  📁 {:?} ({} manquants)", base_path, files.len());
-            for file in files {
-                println!("    ❌ {}", file);
+    let journal_dir = LibraryConfig::data_dir().join("journal");
+    let snapshot_dir = LibraryConfig::data_dir().join("snapshots");
+    let result = match &args.command {
+        Some(Command::Prune { dry_run, quarantine, stale }) => {
+            let report = scan::scan_library(&config, open_library(&config).as_ref(), *stale);
+            prune::prune(&report, quarantine.as_deref(), &journal_dir, *stale, *dry_run)
+        }
+        Some(Command::Generate { jobs, ffmpeg, include_incomplete, retry_failed, dry_run }) => {
+            let mut settings = config.generate.clone();
+            if let Some(ffmpeg) = ffmpeg {
+                settings.ffmpeg = ffmpeg.clone();
             }
+            let jobs = jobs.unwrap_or(settings.jobs);
+            run_generate(&config, &settings, jobs, *include_incomplete, *retry_failed, *dry_run)
+        }
+        Some(Command::Diff { history: true, format, .. }) => run_history(&snapshot_dir, *format),
+        Some(Command::Diff { from, to, format, .. }) => run_diff(&snapshot_dir, from.as_deref(), to.as_deref(), *format),
+        Some(Command::Jellyfin { url, api_key, format }) => {
+            let mut jellyfin = config.jellyfin.clone();
+            jellyfin.url = url.clone().or(jellyfin.url);
+            jellyfin.api_key = api_key.clone().or(jellyfin.api_key);
+            run_jellyfin(&config, &jellyfin, *format)
         }
+        Some(Command::ExportBif { width, quality, force, dry_run }) => {
+            let report = scan::scan_library(&config, open_library(&config).as_ref(), true);
+            let interval = Duration::from_millis(config.trickplay_interval_ms);
+            bif::export(&report, interval, *width, *quality, *force, *dry_run)
+        }
+        Some(Command::Watch { listen, skip_validation }) => {
+            let library = open_library(&config);
+            let scanner = scan::Scanner::new(&config, library.as_ref(), !skip_validation);
+            watch::run(&config, &scanner, !skip_validation, listen)
+        }
+        Some(Command::Restore { list: true, .. }) => prune::list(&journal_dir),
+        Some(Command::Restore { batch, dry_run, .. }) => prune::restore(&journal_dir, batch.as_deref(), *dry_run),
+        None => {
+            run_scan(&args, &config, &snapshot_dir);
+            return;
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("Erreur : {}", e);
+        process::exit(2);
     }
+}
 
-    for (category, paths) in &orphan_trickplay_by_category {
-        println!("\n📂 **{}** (Trickplay orphelins)", category);
-        for (base_path, files) in paths {
-            println!("  📁 {:?} ({} orphelins)", base_path, files.len());
-            for file in files {
-                println!("    🚨 {}", file);
-            }
-        }
+fn run_generate(
+    config: &LibraryConfig,
+    settings: &config::GenerateConfig,
+    jobs: usize,
+    include_incomplete: bool,
+    retry_failed: bool,
+    dry_run: bool,
+) -> io::Result<()> {
+    let data_dir = LibraryConfig::data_dir();
+    let mut queue = generate::Queue::load(&data_dir.join("generate-queue.json"))?;
+
+    let report = scan::scan_library(config, open_library(config).as_ref(), include_incomplete);
+    let added = queue.enqueue(&report, include_incomplete, retry_failed);
+    queue.save()?;
+
+    let pending = queue.count(generate::JobState::Pending);
+    println!("{} ajoutés à la file, {} en attente, {} en échec", added, pending, queue.count(generate::JobState::Failed));
+    if dry_run || pending == 0 {
+        return Ok(());
     }
 
-    let computed_mkv = total_trickplay + total_missing_trickplay - total_orphan_trickplay;
-    println!("\n--- Résumé ---");
-    println!("Total .mkv trouvés       : {}", total_mkv);
-    println!("Total .trickplay trouvés : {}", total_trickplay);
-    println!("Total manquants          : {}", total_missing_trickplay);
-    println!("Total Trickplay orphelins: {}", total_orphan_trickplay);
+    let interval = Duration::from_millis(config.trickplay_interval_ms);
+    let queue = generate::run(queue, settings, interval, jobs, &data_dir.join("logs"))?;
     println!(
-        "Vérification : {} + {} - {} → {}",
-        total_trickplay, total_missing_trickplay, total_orphan_trickplay, computed_mkv
+        "\n--- Génération terminée ---\n✅ {} générés, ❌ {} en échec (relancer avec --retry-failed)",
+        queue.count(generate::JobState::Done),
+        queue.count(generate::JobState::Failed)
     );
+    Ok(())
+}
+
+fn run_jellyfin(config: &LibraryConfig, jellyfin: &config::JellyfinConfig, format: OutputFormat) -> io::Result<()> {
+    let client = api::Client::from_config(jellyfin).map_err(io::Error::other)?;
+    let items = client.items().map_err(io::Error::other)?;
+
+    let report = scan::scan_library(config, open_library(config).as_ref(), false);
+    api::Comparison::new(&report, &items, jellyfin).write(format, &mut io::stdout().lock())
+}
+
+fn run_history(snapshot_dir: &Path, format: OutputFormat) -> io::Result<()> {
+    let snapshots = snapshot::Snapshot::list(snapshot_dir)
+        .iter()
+        .map(|id| snapshot::Snapshot::load(snapshot_dir, id))
+        .collect::<io::Result<Vec<_>>>()?;
+    snapshot::write_history(&snapshots, format, &mut io::stdout().lock())
+}
+
+fn run_diff(snapshot_dir: &Path, from: Option<&str>, to: Option<&str>, format: OutputFormat) -> io::Result<()> {
+    let ids = snapshot::Snapshot::list(snapshot_dir);
+    let (from, to) = match (from, to) {
+        (Some(from), Some(to)) => (from, to),
+        (Some(from), None) => (from, ids.last().map_or("", String::as_str)),
+        (None, _) if ids.len() >= 2 => (ids[ids.len() - 2].as_str(), ids[ids.len() - 1].as_str()),
+        (None, _) => return Err(io::Error::other("il faut au moins deux snapshots, lancer un scan d'abord")),
+    };
 
-    if total_mkv != computed_mkv {
-        println!("⚠️  Attention : Il y a une différence de {} fichiers non comptabilisés.",
-                 (total_mkv as isize - computed_mkv as isize).abs());
+    let from = snapshot::Snapshot::load(snapshot_dir, from)?;
+    let to = snapshot::Snapshot::load(snapshot_dir, to)?;
+    snapshot::SnapshotDiff::new(&from, &to).write(format, &mut io::stdout().lock())
+}
+
+fn run_scan(args: &ClapArgs, config: &LibraryConfig, snapshot_dir: &Path) {
+    let report = scan::scan_library(config, open_library(config).as_ref(), !args.skip_validation);
+
+    if let Err(e) = report.write(args.format, config, &mut io::stdout().lock()) {
+        eprintln!("Failed to write report: {}", e);
+        process::exit(2);
     }
 
-    println!("\n--- Trickplay manquants ---");
-    for (category, paths) in &missing_trickplay_by_category {
-        let count: usize = paths.values().map(|v| v.len()).sum();
-        println!("{} : {} fichiers manquants", category, count);
-
-        let time_per_file = match *category {
-            "Anime" => 1,
-            "Films" => 40,
-            "Séries" => 20,
-            _ => 0,
-        };
-        estimated_time += count * time_per_file;
+    if !args.no_snapshot {
+        let mut snapshot = snapshot::Snapshot::new(&report, config, !args.skip_validation);
+        match snapshot.save(snapshot_dir) {
+            Ok(_) if args.format == OutputFormat::Text => println!("\n📸 Snapshot {} enregistré (missing_trickplay diff)", snapshot.id()),
+            Ok(_) => {}
+            Err(e) => eprintln!("Failed to save snapshot: {}", e),
+        }
+        if let Some(keep) = config.keep_snapshots {
+            match snapshot::Snapshot::remove_oldest(snapshot_dir, keep) {
+                Ok(removed) if removed > 0 && args.format == OutputFormat::Text => {
+                    println!("🧹 {} anciens snapshots supprimés (keep_snapshots = {})", removed, keep)
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("Failed to remove old snapshots: {}", e),
+            }
+        }
     }
 
-    println!("\n--- Estimation du temps pour générer les Trickplay ---");
-    println!("Temps total estimé : ~{} min ({}h{}min)",
-             estimated_time,
-             estimated_time / 60,
-             estimated_time % 60
-    );
+    let exceeded = exceeded_thresholds(args, &report);
+    for (label, count, max) in &exceeded {
+        eprintln!("Seuil dépassé : {} {} > {}", count, label, max);
+    }
+    if !exceeded.is_empty() {
+        process::exit(1);
+    }
 }
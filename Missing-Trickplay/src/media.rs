@@ -0,0 +1,361 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::Duration;
+
+// IDs EBML (Matroska)
+const EBML_HEADER: u64 = 0x1A45_DFA3;
+const SEGMENT: u64 = 0x1853_8067;
+const INFO: u64 = 0x1549_A966;
+const TRACKS: u64 = 0x1654_AE6B;
+const CLUSTER: u64 = 0x1F43_B675;
+const TIMECODE_SCALE: u64 = 0x2A_D7B1;
+const DURATION: u64 = 0x4489;
+const TRACK_ENTRY: u64 = 0xAE;
+const TRACK_VIDEO: u64 = 0xE0;
+const PIXEL_WIDTH: u64 = 0xB0;
+const PIXEL_HEIGHT: u64 = 0xBA;
+
+/// Ce qu'on sait lire d'une vidéo sans ffmpeg, directement dans l'en-tête du conteneur.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MediaInfo {
+    pub duration: Option<Duration>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+impl MediaInfo {
+    pub fn pixels(&self) -> Option<u64> {
+        Some(self.width? as u64 * self.height? as u64)
+    }
+}
+
+/// Lit durée et résolution d'un MKV (EBML) ou d'un MP4 (`mvhd`/`tkhd`).
+pub fn probe(path: &Path) -> MediaInfo {
+    let ext = path.extension().map(|ext| ext.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let Ok(file) = File::open(path) else { return MediaInfo::default() };
+    let mut reader = BufReader::new(file);
+    let mut info = MediaInfo::default();
+
+    // Une erreur en cours de lecture garde ce qui a déjà été trouvé.
+    let _ = match ext.as_str() {
+        "mkv" | "webm" => mkv_probe(&mut reader, &mut info),
+        "mp4" | "m4v" | "mov" => mp4_probe(&mut reader, &mut info),
+        _ => Ok(()),
+    };
+    info
+}
+
+fn read_vint<R: Read>(reader: &mut R, keep_marker: bool) -> io::Result<(u64, usize)> {
+    let mut first = [0u8; 1];
+    reader.read_exact(&mut first)?;
+    let len = first[0].leading_zeros() as usize + 1;
+    if len > 8 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "vint invalide"));
+    }
+
+    let mut value = if keep_marker { first[0] as u64 } else { (first[0] as u64) & (0xFF >> len) };
+    for _ in 1..len {
+        reader.read_exact(&mut first)?;
+        value = (value << 8) | first[0] as u64;
+    }
+    Ok((value, len))
+}
+
+/// Renvoie `(id, taille)`, taille `None` si inconnue (tous les bits à 1).
+fn read_element_header<R: Read>(reader: &mut R) -> io::Result<(u64, Option<u64>)> {
+    let (id, _) = read_vint(reader, true)?;
+    let (size, len) = read_vint(reader, false)?;
+    let unknown = size == (1u64 << (7 * len)) - 1;
+    Ok((id, (!unknown).then_some(size)))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Saute `size` octets : une taille qui ne tient pas dans un `i64` vient d'un en-tête corrompu.
+fn skip<R: Seek>(reader: &mut R, size: u64) -> io::Result<()> {
+    let offset = i64::try_from(size).map_err(|_| invalid("taille d'élément invalide"))?;
+    reader.seek(SeekFrom::Current(offset))?;
+    Ok(())
+}
+
+/// Position de la fin d'un élément de `size` octets qui commence ici.
+fn end_of<R: Seek>(reader: &mut R, size: u64) -> io::Result<u64> {
+    reader.stream_position()?.checked_add(size).ok_or_else(|| invalid("taille d'élément invalide"))
+}
+
+fn read_uint<R: Read>(reader: &mut R, size: u64) -> io::Result<u64> {
+    let mut value = 0u64;
+    let mut byte = [0u8; 1];
+    for _ in 0..size.min(8) {
+        reader.read_exact(&mut byte)?;
+        value = (value << 8) | byte[0] as u64;
+    }
+    Ok(value)
+}
+
+fn read_float<R: Read>(reader: &mut R, size: u64) -> io::Result<f64> {
+    match size {
+        4 => {
+            let mut buf = [0u8; 4];
+            reader.read_exact(&mut buf)?;
+            Ok(f32::from_be_bytes(buf) as f64)
+        }
+        8 => {
+            let mut buf = [0u8; 8];
+            reader.read_exact(&mut buf)?;
+            Ok(f64::from_be_bytes(buf))
+        }
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData, "taille de float invalide")),
+    }
+}
+
+fn mkv_probe<R: Read + Seek>(reader: &mut R, info: &mut MediaInfo) -> io::Result<()> {
+    let (id, size) = read_element_header(reader)?;
+    if id != EBML_HEADER {
+        return Ok(());
+    }
+    skip(reader, size.unwrap_or(0))?;
+
+    let (id, _) = read_element_header(reader)?;
+    if id != SEGMENT {
+        return Ok(());
+    }
+
+    // Info et Tracks sont toujours avant le premier Cluster.
+    loop {
+        let (id, size) = read_element_header(reader)?;
+        let Some(size) = size else { return Ok(()) };
+
+        match id {
+            INFO => info.duration = mkv_info_duration(reader, size)?,
+            TRACKS => mkv_tracks_resolution(reader, size, info)?,
+            CLUSTER => return Ok(()),
+            _ => skip(reader, size)?,
+        }
+
+        if info.duration.is_some() && info.width.is_some() {
+            return Ok(());
+        }
+    }
+}
+
+fn mkv_info_duration<R: Read + Seek>(reader: &mut R, info_size: u64) -> io::Result<Option<Duration>> {
+    let mut timecode_scale = 1_000_000u64;
+    let mut duration = None;
+    let end = end_of(reader, info_size)?;
+
+    while reader.stream_position()? < end {
+        let (id, size) = read_element_header(reader)?;
+        let Some(size) = size else { break };
+
+        match id {
+            TIMECODE_SCALE => timecode_scale = read_uint(reader, size)?,
+            DURATION => duration = Some(read_float(reader, size)?),
+            _ => skip(reader, size)?,
+        }
+    }
+    reader.seek(SeekFrom::Start(end))?;
+
+    // Un float absurde ne tient pas dans une `Duration` : durée inconnue plutôt que panique
+    Ok(duration
+        .filter(|d| d.is_finite() && *d > 0.0)
+        .and_then(|d| Duration::try_from_secs_f64(d * timecode_scale as f64 / 1e9).ok()))
+}
+
+/// Descend Tracks → TrackEntry → Video et garde la résolution de la première piste vidéo.
+fn mkv_tracks_resolution<R: Read + Seek>(reader: &mut R, tracks_size: u64, info: &mut MediaInfo) -> io::Result<()> {
+    let end = end_of(reader, tracks_size)?;
+
+    while reader.stream_position()? < end {
+        let (id, size) = read_element_header(reader)?;
+        let Some(size) = size else { break };
+
+        match id {
+            // Conteneurs : on entre dedans
+            TRACK_ENTRY | TRACK_VIDEO if info.width.is_none() => {}
+            PIXEL_WIDTH if info.width.is_none() => info.width = Some(read_uint(reader, size)? as u32),
+            PIXEL_HEIGHT if info.height.is_none() => info.height = Some(read_uint(reader, size)? as u32),
+            _ => skip(reader, size)?,
+        }
+    }
+    reader.seek(SeekFrom::Start(end))?;
+    Ok(())
+}
+
+/// Renvoie `(type, taille du contenu)` d'une box MP4, `None` si elle va jusqu'à la fin du fichier.
+fn read_box_header<R: Read>(reader: &mut R) -> io::Result<([u8; 4], Option<u64>)> {
+    let mut header = [0u8; 8];
+    reader.read_exact(&mut header)?;
+    let size = u32::from_be_bytes(header[0..4].try_into().unwrap()) as u64;
+    let kind: [u8; 4] = header[4..8].try_into().unwrap();
+
+    let content = match size {
+        0 => None,
+        1 => {
+            let mut large = [0u8; 8];
+            reader.read_exact(&mut large)?;
+            Some(u64::from_be_bytes(large).saturating_sub(16))
+        }
+        _ => Some(size.saturating_sub(8)),
+    };
+    Ok((kind, content))
+}
+
+/// Cherche une box parmi ses sœurs et renvoie la taille de son contenu.
+fn find_box<R: Read + Seek>(reader: &mut R, wanted: &[u8; 4], end: Option<u64>) -> io::Result<Option<u64>> {
+    while end.is_none_or(|end| reader.stream_position().is_ok_and(|pos| pos < end)) {
+        let (kind, size) = match read_box_header(reader) {
+            Ok(header) => header,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        if &kind == wanted {
+            return Ok(Some(size.unwrap_or(u64::MAX)));
+        }
+
+        match size {
+            Some(size) => skip(reader, size)?,
+            None => return Ok(None),
+        }
+    }
+    Ok(None)
+}
+
+fn mp4_probe<R: Read + Seek>(reader: &mut R, info: &mut MediaInfo) -> io::Result<()> {
+    let Some(moov_size) = find_box(reader, b"moov", None)? else { return Ok(()) };
+    let moov_start = reader.stream_position()?;
+    let moov_end = moov_start.saturating_add(moov_size);
+
+    if find_box(reader, b"mvhd", Some(moov_end))?.is_some() {
+        info.duration = mp4_mvhd_duration(reader)?;
+    }
+
+    // Première piste `trak` dont le `tkhd` a une taille non nulle (les pistes audio sont à 0x0).
+    reader.seek(SeekFrom::Start(moov_start))?;
+    while let Some(trak_size) = find_box(reader, b"trak", Some(moov_end))? {
+        let trak_end = reader.stream_position()?.saturating_add(trak_size);
+
+        if find_box(reader, b"tkhd", Some(trak_end))?.is_some() {
+            let mut version = [0u8; 1];
+            reader.read_exact(&mut version)?;
+            // flags + dates/id/durée + réservé/layer/volume/matrice
+            let skip = if version[0] == 1 { 3 + 32 + 52 } else { 3 + 20 + 52 };
+            reader.seek(SeekFrom::Current(skip))?;
+            let width = (read_uint(reader, 4)? >> 16) as u32;
+            let height = (read_uint(reader, 4)? >> 16) as u32;
+
+            if width > 0 && height > 0 {
+                info.width = Some(width);
+                info.height = Some(height);
+                break;
+            }
+        }
+        reader.seek(SeekFrom::Start(trak_end))?;
+    }
+
+    Ok(())
+}
+
+fn mp4_mvhd_duration<R: Read + Seek>(reader: &mut R) -> io::Result<Option<Duration>> {
+    let mut version = [0u8; 4];
+    reader.read_exact(&mut version)?;
+    let (timescale, duration) = if version[0] == 1 {
+        reader.seek(SeekFrom::Current(16))?;
+        (read_uint(reader, 4)?, read_uint(reader, 8)?)
+    } else {
+        reader.seek(SeekFrom::Current(8))?;
+        (read_uint(reader, 4)?, read_uint(reader, 4)?)
+    };
+
+    if timescale == 0 || duration == 0 || duration == u32::MAX as u64 || duration == u64::MAX {
+        return Ok(None);
+    }
+    Ok(Duration::try_from_secs_f64(duration as f64 / timescale as f64).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Élément EBML dont la taille tient sur un octet.
+    fn element(id: &[u8], content: &[u8]) -> Vec<u8> {
+        [id, &[0x80 | content.len() as u8], content].concat()
+    }
+
+    fn mkv(duration: f64) -> Vec<u8> {
+        let info = [element(&[0x2A, 0xD7, 0xB1], &[0x0F, 0x42, 0x40]), element(&[0x44, 0x89], &duration.to_be_bytes())].concat();
+        let video = [element(&[0xB0], &[0x07, 0x80]), element(&[0xBA], &[0x04, 0x38])].concat();
+        let tracks = element(&[0xAE], &element(&[0xE0], &video));
+        // Segment de taille inconnue, comme à l'enregistrement en direct
+        let segment = [0x18, 0x53, 0x80, 0x67, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+        [element(&[0x1A, 0x45, 0xDF, 0xA3], &[]), segment.to_vec(), element(&[0x15, 0x49, 0xA9, 0x66], &info), element(&[0x16, 0x54, 0xAE, 0x6B], &tracks)].concat()
+    }
+
+    fn mp4_box(kind: &[u8; 4], content: &[u8]) -> Vec<u8> {
+        [&(content.len() as u32 + 8).to_be_bytes(), kind.as_slice(), content].concat()
+    }
+
+    fn mp4(timescale: u32, duration: u64) -> Vec<u8> {
+        let mvhd = [&[1, 0, 0, 0][..], &[0; 16], &timescale.to_be_bytes(), &duration.to_be_bytes()].concat();
+        let tkhd = [&[0u8; 4][..], &[0; 20 + 52], &(1920u32 << 16).to_be_bytes(), &(1080u32 << 16).to_be_bytes()].concat();
+        let moov = [mp4_box(b"mvhd", &mvhd), mp4_box(b"trak", &mp4_box(b"tkhd", &tkhd))].concat();
+        [mp4_box(b"ftyp", b"isom"), mp4_box(b"moov", &moov)].concat()
+    }
+
+    fn probe_bytes(data: Vec<u8>, mkv: bool) -> (MediaInfo, io::Result<()>) {
+        let mut info = MediaInfo::default();
+        let mut reader = Cursor::new(data);
+        let result = if mkv { mkv_probe(&mut reader, &mut info) } else { mp4_probe(&mut reader, &mut info) };
+        (info, result)
+    }
+
+    #[test]
+    fn reads_mkv_duration_and_resolution() {
+        let (info, result) = probe_bytes(mkv(5_000.0), true);
+        assert!(result.is_ok());
+        assert_eq!(info.duration, Some(Duration::from_secs(5)));
+        assert_eq!((info.width, info.height), (Some(1920), Some(1080)));
+    }
+
+    #[test]
+    fn absurd_mkv_duration_is_unknown() {
+        let (info, _) = probe_bytes(mkv(1e300), true);
+        assert_eq!(info.duration, None);
+        assert_eq!(info.width, Some(1920));
+    }
+
+    #[test]
+    fn truncated_mkv_keeps_what_was_read() {
+        let mut data = mkv(5_000.0);
+        data.truncate(data.len() - 4);
+        let (info, result) = probe_bytes(data, true);
+        assert!(result.is_err());
+        assert_eq!(info.duration, Some(Duration::from_secs(5)));
+        assert_eq!(info.height, None);
+    }
+
+    #[test]
+    fn reads_mp4_duration_and_resolution() {
+        let (info, result) = probe_bytes(mp4(1_000, 7_200_000), false);
+        assert!(result.is_ok());
+        assert_eq!(info.duration, Some(Duration::from_secs(7_200)));
+        assert_eq!((info.width, info.height), (Some(1920), Some(1080)));
+    }
+
+    #[test]
+    fn absurd_mp4_headers_fail_without_panicking() {
+        let (info, _) = probe_bytes(mp4(1, u64::MAX - 1), false);
+        assert_eq!(info.duration, None);
+
+        // largesize au-delà de i64::MAX avant le moov
+        let huge = [&1u32.to_be_bytes()[..], b"free", &u64::MAX.to_be_bytes()].concat();
+        let (info, result) = probe_bytes([huge, mp4(1_000, 7_200_000)].concat(), false);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(info.duration, None);
+    }
+}
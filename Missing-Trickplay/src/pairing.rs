@@ -0,0 +1,55 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use unicode_normalization::UnicodeNormalization;
+
+/// Plusieurs vidéos et/ou plusieurs `.trickplay` d'un même dossier qui réclament le même nom.
+#[derive(Debug, Clone)]
+pub struct Ambiguity {
+    pub videos: Vec<PathBuf>,
+    pub trickplays: Vec<PathBuf>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Pairing {
+    /// `(vidéo, dossier .trickplay)`
+    pub paired: Vec<(PathBuf, PathBuf)>,
+    pub missing: Vec<PathBuf>,
+    pub orphans: Vec<PathBuf>,
+    pub ambiguous: Vec<Ambiguity>,
+}
+
+/// Clé d'appariement : dossier parent + nom normalisé en NFC (macOS écrit les accents en NFD).
+fn pairing_key(parent: Option<&Path>, stem: &str) -> (PathBuf, String) {
+    (parent.map(Path::to_path_buf).unwrap_or_default(), stem.nfc().collect())
+}
+
+/// Associe chaque `<stem>.mkv` au `<stem>.trickplay` du même dossier, à l'identique.
+pub fn pair(videos: &[PathBuf], trickplays: &[PathBuf]) -> Pairing {
+    let mut groups: BTreeMap<(PathBuf, String), (Vec<PathBuf>, Vec<PathBuf>)> = BTreeMap::new();
+
+    for video in videos {
+        let Some(stem) = video.file_stem() else { continue };
+        let key = pairing_key(video.parent(), &stem.to_string_lossy());
+        groups.entry(key).or_default().0.push(video.clone());
+    }
+
+    for trickplay in trickplays {
+        let Some(name) = trickplay.file_name() else { continue };
+        let name = name.to_string_lossy();
+        let stem = name.strip_suffix(".trickplay").unwrap_or(&name);
+        let key = pairing_key(trickplay.parent(), stem);
+        groups.entry(key).or_default().1.push(trickplay.clone());
+    }
+
+    let mut pairing = Pairing::default();
+    for (_, (videos, trickplays)) in groups {
+        match (videos.len(), trickplays.len()) {
+            (1, 1) => pairing.paired.push((videos[0].clone(), trickplays[0].clone())),
+            (_, 0) => pairing.missing.extend(videos),
+            (0, _) => pairing.orphans.extend(trickplays),
+            _ => pairing.ambiguous.push(Ambiguity { videos, trickplays }),
+        }
+    }
+
+    pairing
+}
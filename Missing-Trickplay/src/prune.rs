@@ -0,0 +1,312 @@
+use crate::report::{format_size, ScanReport, Status};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Nom du dossier de quarantaine créé à la racine de chaque bibliothèque.
+/// Caché, donc ignoré par Jellyfin et par le scan.
+pub const QUARANTINE_DIR_NAME: &str = ".trickplay-quarantine";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub original: PathBuf,
+    pub quarantined: PathBuf,
+    pub size: u64,
+}
+
+/// Un passage de `prune`, rejouable à l'envers par `restore`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Journal {
+    pub batch: String,
+    pub created_at: u64,
+    pub entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    fn path(journal_dir: &Path, batch: &str) -> PathBuf {
+        journal_dir.join(format!("{}.json", batch))
+    }
+
+    /// Réserve `<horodatage>.json`, ou `<horodatage>-2.json`… si un autre `prune` a démarré dans la
+    /// même seconde (cron et lancement manuel) : deux lots ne s'écrasent jamais.
+    fn create(journal_dir: &Path, created_at: u64) -> io::Result<Self> {
+        fs::create_dir_all(journal_dir)?;
+        let mut attempt = 1;
+        loop {
+            let batch = if attempt == 1 { created_at.to_string() } else { format!("{}-{}", created_at, attempt) };
+            match OpenOptions::new().write(true).create_new(true).open(Self::path(journal_dir, &batch)) {
+                Ok(_) => {
+                    let journal = Journal { batch, created_at, entries: Vec::new() };
+                    journal.save(journal_dir)?;
+                    return Ok(journal);
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => attempt += 1,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn save(&self, journal_dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(journal_dir)?;
+        let file = File::create(Self::path(journal_dir, &self.batch))?;
+        serde_json::to_writer_pretty(file, self).map_err(io::Error::other)
+    }
+
+    fn load(journal_dir: &Path, batch: &str) -> io::Result<Self> {
+        let file = File::open(Self::path(journal_dir, batch))?;
+        serde_json::from_reader(file).map_err(io::Error::other)
+    }
+
+    /// Lots existants, du plus ancien au plus récent.
+    pub fn list(journal_dir: &Path) -> io::Result<Vec<Journal>> {
+        let mut journals = Vec::new();
+        let Ok(entries) = fs::read_dir(journal_dir) else { return Ok(journals) };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "json")
+                && let Some(batch) = path.file_stem()
+            {
+                journals.push(Self::load(journal_dir, &batch.to_string_lossy())?);
+            }
+        }
+
+        journals.sort_by_key(|j| (j.created_at, j.batch.clone()));
+        Ok(journals)
+    }
+}
+
+pub fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else { return 0 };
+
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(t) if t.is_dir() => dir_size(&entry.path()),
+            Ok(_) => entry.metadata().map_or(0, |m| m.len()),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// Déplace un dossier, en copiant puis supprimant si la destination est sur un autre volume.
+pub fn move_dir(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            copy_dir(from, to)?;
+            fs::remove_dir_all(from)
+        }
+        result => result,
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+/// `mnt/films/A/A.trickplay` → `<quarantaine>/<lot>/mnt/films/A/A.trickplay`
+fn quarantine_path(quarantine: &Path, batch: &str, original: &Path) -> PathBuf {
+    let relative: PathBuf = original
+        .components()
+        .filter(|c| matches!(c, Component::Normal(_)))
+        .collect();
+    quarantine.join(batch).join(relative)
+}
+
+/// Met en quarantaine les `.trickplay` orphelins du rapport, et obsolètes avec `stale`. Sans
+/// `quarantine`, chaque dossier reste sur son volume dans `<racine>/.trickplay-quarantine`, ce qui évite une copie.
+pub fn prune(report: &ScanReport, quarantine: Option<&Path>, journal_dir: &Path, stale: bool, dry_run: bool) -> io::Result<()> {
+    let (orphans, metadata): (Vec<_>, Vec<_>) = report
+        .entries
+        .iter()
+        .filter(|e| e.is_trickplay() && (e.status == Status::Orphan || (stale && e.status == Status::Stale)))
+        .filter_map(|e| Some((e.root.as_path(), e.trickplay.as_deref()?)))
+        .partition(|(root, tp): &(&Path, &Path)| tp.starts_with(root));
+    let orphans: Vec<(&Path, &Path, u64)> = orphans.into_iter().map(|(root, tp)| (root, tp, dir_size(tp))).collect();
+
+    // Trickplay appariés par library.db : le dossier metadata appartient à Jellyfin, qui les régénère
+    if !metadata.is_empty() {
+        println!("⚠️  {} Trickplay obsolètes dans le dossier metadata de Jellyfin, laissés en place :", metadata.len());
+        for (_, tp) in &metadata {
+            println!("  🔄 {}", tp.display());
+        }
+        println!("À régénérer depuis Jellyfin (Tableau de bord → Tâches planifiées → Générer les Trickplay).\n");
+    }
+
+    if orphans.is_empty() {
+        println!("{}", if stale { "Aucun Trickplay orphelin ou obsolète." } else { "Aucun Trickplay orphelin." });
+        return Ok(());
+    }
+
+    let total: u64 = orphans.iter().map(|(_, _, size)| size).sum();
+    for (_, tp, size) in &orphans {
+        println!("  🚨 {} ({})", tp.display(), format_size(*size));
+    }
+    println!("\n{} dossiers, {} récupérables", orphans.len(), format_size(total));
+
+    if dry_run {
+        println!("Dry-run : rien n'a été déplacé.");
+        return Ok(());
+    }
+
+    let created_at = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let mut journal = Journal::create(journal_dir, created_at)?;
+
+    for (root, tp, size) in orphans {
+        let target = match quarantine {
+            Some(quarantine) => quarantine_path(quarantine, &journal.batch, tp),
+            None => quarantine_path(&root.join(QUARANTINE_DIR_NAME), &journal.batch, tp.strip_prefix(root).unwrap()),
+        };
+
+        match move_dir(tp, &target) {
+            Ok(()) => {
+                journal.entries.push(JournalEntry { original: tp.to_path_buf(), quarantined: target, size });
+                // Sauvegardé à chaque déplacement pour pouvoir restaurer même après une interruption.
+                journal.save(journal_dir)?;
+            }
+            Err(e) => eprintln!("Failed to move {}: {}", tp.display(), e),
+        }
+    }
+
+    if journal.entries.is_empty() {
+        fs::remove_file(Journal::path(journal_dir, &journal.batch))?;
+        println!("Aucun dossier déplacé.");
+        return Ok(());
+    }
+
+    let moved: u64 = journal.entries.iter().map(|e| e.size).sum();
+    println!("📦 {} dossiers ({}) mis en quarantaine, lot {}", journal.entries.len(), format_size(moved), journal.batch);
+    println!("Annuler : missing_trickplay restore {}", journal.batch);
+    Ok(())
+}
+
+/// Remet en place un lot (le plus récent par défaut). Les entrées restaurées sortent du journal.
+pub fn restore(journal_dir: &Path, batch: Option<&str>, dry_run: bool) -> io::Result<()> {
+    let batch = match batch {
+        Some(batch) => batch.to_string(),
+        None => match Journal::list(journal_dir)?.pop() {
+            Some(journal) => journal.batch,
+            None => {
+                println!("Aucun lot à restaurer.");
+                return Ok(());
+            }
+        },
+    };
+
+    let mut journal = Journal::load(journal_dir, &batch)?;
+    let mut remaining = Vec::new();
+
+    for entry in journal.entries.drain(..) {
+        if entry.original.exists() {
+            eprintln!("⚠️  {} existe déjà, ignoré", entry.original.display());
+            remaining.push(entry);
+            continue;
+        }
+
+        println!("  ↩️  {}", entry.original.display());
+        if dry_run {
+            remaining.push(entry);
+            continue;
+        }
+
+        if let Err(e) = move_dir(&entry.quarantined, &entry.original) {
+            eprintln!("Failed to restore {}: {}", entry.original.display(), e);
+            remaining.push(entry);
+        }
+    }
+
+    if dry_run {
+        println!("Dry-run : rien n'a été déplacé.");
+        return Ok(());
+    }
+
+    journal.entries = remaining;
+    if journal.entries.is_empty() {
+        fs::remove_file(Journal::path(journal_dir, &batch))?;
+        println!("Lot {} entièrement restauré.", batch);
+    } else {
+        journal.save(journal_dir)?;
+        println!("Lot {} : {} dossiers non restaurés.", batch, journal.entries.len());
+    }
+    Ok(())
+}
+
+pub fn list(journal_dir: &Path) -> io::Result<()> {
+    let journals = Journal::list(journal_dir)?;
+    if journals.is_empty() {
+        println!("Aucun lot en quarantaine.");
+    }
+
+    for journal in journals {
+        let size: u64 = journal.entries.iter().map(|e| e.size).sum();
+        println!("{} : {} dossiers, {}", journal.batch, journal.entries.len(), format_size(size));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::{Entry, Found, TRICKPLAY_KIND};
+
+    fn stale(root: &Path, video: PathBuf, trickplay: PathBuf) -> Entry {
+        Entry {
+            category: "Films".to_string(),
+            root: root.to_path_buf(),
+            kind: TRICKPLAY_KIND.to_string(),
+            status: Status::Stale,
+            video: Some(video),
+            trickplay: Some(trickplay),
+            sidecar: None,
+            detail: None,
+        }
+    }
+
+    #[test]
+    fn stale_trickplay_of_jellyfin_metadata_is_left_in_place() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("nas/Films");
+        let local = root.join("Alien/Alien.trickplay");
+        // Apparié par library.db, hors de la racine
+        let library = tmp.path().join("data/metadata/library/00/00112233445566778899aabbccddeeff/trickplay");
+        for dir in [&local, &library] {
+            fs::create_dir_all(dir.join("320 - 10x10")).unwrap();
+            fs::write(dir.join("320 - 10x10/0.jpg"), b"jpeg").unwrap();
+        }
+
+        let report = ScanReport {
+            entries: vec![
+                stale(&root, root.join("Alien/Alien.mkv"), local.clone()),
+                stale(&root, root.join("Alien 3/Alien 3.mkv"), library.clone()),
+            ],
+            estimates: Vec::new(),
+            found: Found::default(),
+        };
+        let journal_dir = tmp.path().join("journal");
+        prune(&report, None, &journal_dir, true, false).unwrap();
+
+        assert!(library.join("320 - 10x10/0.jpg").exists());
+        assert!(!local.exists());
+        let journals = Journal::list(&journal_dir).unwrap();
+        assert_eq!(journals.len(), 1);
+        assert_eq!(journals[0].entries.len(), 1);
+        assert_eq!(journals[0].entries[0].original, local);
+        assert!(journals[0].entries[0].quarantined.starts_with(root.join(QUARANTINE_DIR_NAME)));
+    }
+}
@@ -0,0 +1,431 @@
+use crate::config::LibraryConfig;
+use crate::estimate::{format_minutes, Estimate};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Version du schéma JSON/CSV, à incrémenter si un champ change de sens ou disparaît.
+/// v2 : colonnes `kind` et `sidecar`, lignes des règles sidecars.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// `kind` des entrées Trickplay, les autres portent le nom de leur règle sidecar.
+pub const TRICKPLAY_KIND: &str = "trickplay";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Missing,
+    Orphan,
+    Incomplete,
+    Corrupt,
+    /// Trickplay d'une ancienne version de la vidéo
+    Stale,
+    Ambiguous,
+}
+
+impl Status {
+    pub const ALL: [Status; 7] = [
+        Status::Ok,
+        Status::Missing,
+        Status::Orphan,
+        Status::Incomplete,
+        Status::Corrupt,
+        Status::Stale,
+        Status::Ambiguous,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Status::Ok => "ok",
+            Status::Missing => "missing",
+            Status::Orphan => "orphan",
+            Status::Incomplete => "incomplete",
+            Status::Corrupt => "corrupt",
+            Status::Stale => "stale",
+            Status::Ambiguous => "ambiguous",
+        }
+    }
+
+    /// Trickplay présent mais à regénérer.
+    pub fn needs_regeneration(&self) -> bool {
+        matches!(self, Status::Incomplete | Status::Corrupt | Status::Stale)
+    }
+}
+
+/// Une vidéo ou un dossier `.trickplay` (les deux quand ils sont appariés),
+/// ou le résultat d'une règle sidecar pour une vidéo ou un dossier.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub category: String,
+    pub root: PathBuf,
+    pub kind: String,
+    pub status: Status,
+    pub video: Option<PathBuf>,
+    pub trickplay: Option<PathBuf>,
+    /// Sidecar trouvé, ou chemin attendu s'il manque.
+    pub sidecar: Option<PathBuf>,
+    pub detail: Option<String>,
+}
+
+impl Entry {
+    pub fn is_trickplay(&self) -> bool {
+        self.kind == TRICKPLAY_KIND
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RootEstimate {
+    pub category: String,
+    pub root: PathBuf,
+    pub estimate: Estimate,
+}
+
+/// Vidéos et `.trickplay` comptés sur les listings bruts du scan, sans passer par l'appariement :
+/// c'est ce que la vérification du résumé recoupe avec les entrées.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Found {
+    pub videos: usize,
+    pub trickplays: usize,
+    /// Trickplay retrouvés dans le dossier metadata de Jellyfin, hors des listings
+    pub library_trickplays: usize,
+}
+
+impl Found {
+    pub fn merge(&mut self, other: &Found) {
+        self.videos += other.videos;
+        self.trickplays += other.trickplays;
+        self.library_trickplays += other.library_trickplays;
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    pub entries: Vec<Entry>,
+    pub estimates: Vec<RootEstimate>,
+    pub found: Found,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+#[derive(Serialize)]
+struct JsonEstimate<'a> {
+    category: &'a str,
+    root: &'a Path,
+    files: usize,
+    unknown_duration: usize,
+    runtime_secs: u64,
+    estimated_secs: u64,
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    schema_version: u32,
+    totals: BTreeMap<&'static str, usize>,
+    sidecar_totals: BTreeMap<&'a str, BTreeMap<&'static str, usize>>,
+    estimates: Vec<JsonEstimate<'a>>,
+    entries: &'a [Entry],
+}
+
+type Groups<'a> = Vec<(&'a str, Vec<(&'a Path, Vec<&'a Entry>)>)>;
+
+impl ScanReport {
+    /// Nombre d'entrées Trickplay dans ce statut.
+    pub fn count(&self, status: Status) -> usize {
+        self.entries.iter().filter(|e| e.is_trickplay() && e.status == status).count()
+    }
+
+    pub fn count_sidecars(&self, status: Status) -> usize {
+        self.entries.iter().filter(|e| !e.is_trickplay() && e.status == status).count()
+    }
+
+    /// Regroupe par catégorie puis par racine, dans l'ordre du scan.
+    fn grouped(&self, filter: impl Fn(&Entry) -> bool) -> Groups<'_> {
+        let mut groups: Groups = Vec::new();
+
+        for entry in self.entries.iter().filter(|e| filter(e)) {
+            if groups.last().is_none_or(|(category, _)| *category != entry.category) {
+                groups.push((&entry.category, Vec::new()));
+            }
+            let roots = &mut groups.last_mut().unwrap().1;
+            if roots.last().is_none_or(|(root, _)| *root != entry.root) {
+                roots.push((&entry.root, Vec::new()));
+            }
+            roots.last_mut().unwrap().1.push(entry);
+        }
+
+        groups
+    }
+
+    pub fn write(&self, format: OutputFormat, config: &LibraryConfig, out: &mut impl Write) -> io::Result<()> {
+        match format {
+            OutputFormat::Text => self.write_text(config, out),
+            OutputFormat::Json => self.write_json(out),
+            OutputFormat::Csv => self.write_csv(out),
+        }
+    }
+
+    pub fn write_json(&self, out: &mut impl Write) -> io::Result<()> {
+        let report = JsonReport {
+            schema_version: SCHEMA_VERSION,
+            totals: Status::ALL.iter().map(|s| (s.as_str(), self.count(*s))).collect(),
+            sidecar_totals: self.entries.iter().filter(|e| !e.is_trickplay()).fold(BTreeMap::new(), |mut totals, e| {
+                *totals.entry(e.kind.as_str()).or_insert_with(BTreeMap::new).entry(e.status.as_str()).or_insert(0) += 1;
+                totals
+            }),
+            estimates: self
+                .estimates
+                .iter()
+                .map(|e| JsonEstimate {
+                    category: &e.category,
+                    root: &e.root,
+                    files: e.estimate.files,
+                    unknown_duration: e.estimate.unknown,
+                    runtime_secs: e.estimate.runtime.as_secs(),
+                    estimated_secs: e.estimate.time.as_secs(),
+                })
+                .collect(),
+            entries: &self.entries,
+        };
+
+        serde_json::to_writer_pretty(&mut *out, &report)?;
+        writeln!(out)
+    }
+
+    pub fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "category,root,kind,status,video,trickplay,sidecar,detail")?;
+
+        let path = |p: &Option<PathBuf>| p.as_ref().map(|p| p.display().to_string()).unwrap_or_default();
+        for entry in &self.entries {
+            writeln!(
+                out,
+                "{},{},{},{},{},{},{},{}",
+                csv_field(&entry.category),
+                csv_field(&entry.root.display().to_string()),
+                csv_field(&entry.kind),
+                entry.status.as_str(),
+                csv_field(&path(&entry.video)),
+                csv_field(&path(&entry.trickplay)),
+                csv_field(&path(&entry.sidecar)),
+                csv_field(entry.detail.as_deref().unwrap_or_default()),
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn write_text(&self, config: &LibraryConfig, out: &mut impl Write) -> io::Result<()> {
+        let stem = |p: &Option<PathBuf>| p.as_ref().and_then(|p| p.file_stem()).unwrap_or_default().to_string_lossy().into_owned();
+        let name = |p: &Option<PathBuf>| p.as_ref().and_then(|p| p.file_name()).unwrap_or_default().to_string_lossy().into_owned();
+
+        for (category, roots) in self.grouped(|e| e.is_trickplay() && e.status == Status::Missing) {
+            writeln!(out, "\n📂 **{}**", category)?;
+            for (root, entries) in roots {
+                writeln!(out, "  📁 {:?} ({} manquants)", root, entries.len())?;
+                for entry in entries {
+                    writeln!(out, "    ❌ {}", stem(&entry.video))?;
+                }
+            }
+        }
+
+        for (category, roots) in self.grouped(|e| e.is_trickplay() && e.status == Status::Orphan) {
+            writeln!(out, "\n📂 **{}** (Trickplay orphelins)", category)?;
+            for (root, entries) in roots {
+                writeln!(out, "  📁 {:?} ({} orphelins)", root, entries.len())?;
+                for entry in entries {
+                    writeln!(out, "    🚨 {}", name(&entry.trickplay))?;
+                }
+            }
+        }
+
+        for (category, roots) in self.grouped(|e| e.is_trickplay() && e.status.needs_regeneration()) {
+            writeln!(out, "\n📂 **{}** (Trickplay incomplets/corrompus/obsolètes)", category)?;
+            for (root, entries) in roots {
+                writeln!(out, "  📁 {:?} ({} à regénérer)", root, entries.len())?;
+                for entry in entries {
+                    let kind = match entry.status {
+                        Status::Corrupt => "corrompu",
+                        Status::Stale => "obsolète",
+                        _ => "incomplet",
+                    };
+                    writeln!(out, "    ⚠️  {} [{}: {}]", name(&entry.trickplay), kind, entry.detail.as_deref().unwrap_or_default())?;
+                }
+            }
+        }
+
+        for (category, roots) in self.grouped(|e| e.is_trickplay() && e.status == Status::Ambiguous) {
+            writeln!(out, "\n📂 **{}** (Appariements ambigus)", category)?;
+            for (root, entries) in roots {
+                let mut details: Vec<&str> = entries.iter().filter_map(|e| e.detail.as_deref()).collect();
+                details.dedup();
+                writeln!(out, "  📁 {:?} ({} ambigus)", root, details.len())?;
+                for detail in details {
+                    writeln!(out, "    ❓ {}", detail)?;
+                }
+            }
+        }
+
+        self.write_sidecars_text(out)?;
+
+        let trickplay_entries = || self.entries.iter().filter(|e| e.is_trickplay());
+        let total_mkv = self.found.videos;
+        let total_trickplay = self.found.trickplays + self.found.library_trickplays;
+        let total_paired = trickplay_entries().filter(|e| e.video.is_some() && e.trickplay.is_some()).count();
+        let ambiguous_videos = trickplay_entries().filter(|e| e.status == Status::Ambiguous && e.video.is_some()).count();
+        let ambiguous_trickplay = self.count(Status::Ambiguous) - ambiguous_videos;
+        let missing = self.count(Status::Missing);
+        let orphans = self.count(Status::Orphan);
+
+        let computed_mkv = total_paired + missing + ambiguous_videos;
+        let computed_trickplay = total_paired + orphans + ambiguous_trickplay;
+        writeln!(out, "\n--- Résumé ---")?;
+        writeln!(out, "Total .mkv trouvés       : {}", total_mkv)?;
+        if self.found.library_trickplays > 0 {
+            writeln!(out, "Total .trickplay trouvés : {} (dont {} dans metadata)", total_trickplay, self.found.library_trickplays)?;
+        } else {
+            writeln!(out, "Total .trickplay trouvés : {}", total_trickplay)?;
+        }
+        writeln!(out, "Total appariés           : {}", total_paired)?;
+        writeln!(out, "Total manquants          : {}", missing)?;
+        writeln!(out, "Total Trickplay orphelins: {}", orphans)?;
+        writeln!(out, "Total incomplets/corrompus: {}", self.count(Status::Incomplete) + self.count(Status::Corrupt))?;
+        writeln!(out, "Total obsolètes          : {}", self.count(Status::Stale))?;
+        writeln!(out, "Total ambigus            : {} vidéos / {} trickplay", ambiguous_videos, ambiguous_trickplay)?;
+        writeln!(
+            out,
+            "Vérification .mkv       : {} appariés + {} manquants + {} ambigus → {}",
+            total_paired, missing, ambiguous_videos, computed_mkv
+        )?;
+        writeln!(
+            out,
+            "Vérification .trickplay : {} appariés + {} orphelins + {} ambigus → {}",
+            total_paired, orphans, ambiguous_trickplay, computed_trickplay
+        )?;
+
+        if total_mkv != computed_mkv || total_trickplay != computed_trickplay {
+            writeln!(out, "⚠️  Attention : Il y a une différence de {} fichiers non comptabilisés.",
+                     total_mkv.abs_diff(computed_mkv) + total_trickplay.abs_diff(computed_trickplay))?;
+        }
+
+        writeln!(out, "\n--- Trickplay manquants ---")?;
+        for category in &config.categories {
+            let count = |filter: fn(Status) -> bool| {
+                self.entries.iter().filter(|e| e.is_trickplay() && e.category == category.name && filter(e.status)).count()
+            };
+
+            let missing = count(|s| s == Status::Missing);
+            let incomplete = count(|s| s.needs_regeneration());
+            if missing + incomplete > 0 {
+                writeln!(out, "{} : {} fichiers manquants, {} à regénérer", category.name, missing, incomplete)?;
+            }
+        }
+
+        writeln!(out, "\n--- Estimation du temps pour générer les Trickplay ---")?;
+        let mut total_estimate = Estimate::default();
+        for category in &config.categories {
+            let estimates: Vec<&RootEstimate> = self.estimates.iter().filter(|e| e.category == category.name).collect();
+            if estimates.is_empty() {
+                continue;
+            }
+
+            let mut category_estimate = Estimate::default();
+            estimates.iter().for_each(|e| category_estimate.merge(&e.estimate));
+            total_estimate.merge(&category_estimate);
+
+            writeln!(out, "{} (x{} temps réel) : {}", category.name, config.speed_for(category), describe_estimate(&category_estimate))?;
+            for e in estimates {
+                writeln!(out, "  📁 {:?} : {}", e.root, describe_estimate(&e.estimate))?;
+            }
+        }
+
+        writeln!(out, "Temps total estimé : {}", format_minutes(total_estimate.time))
+    }
+}
+
+impl ScanReport {
+    fn write_sidecars_text(&self, out: &mut impl Write) -> io::Result<()> {
+        let name = |p: &Option<PathBuf>| p.as_ref().and_then(|p| p.file_name()).unwrap_or_default().to_string_lossy().into_owned();
+
+        for (category, roots) in self.grouped(|e| !e.is_trickplay() && matches!(e.status, Status::Missing | Status::Orphan)) {
+            writeln!(out, "\n📂 **{}** (Sidecars)", category)?;
+            for (root, entries) in roots {
+                let missing = entries.iter().filter(|e| e.status == Status::Missing).count();
+                writeln!(out, "  📁 {:?} ({} manquants, {} orphelins)", root, missing, entries.len() - missing)?;
+
+                for entry in entries {
+                    match (entry.status, &entry.video) {
+                        (Status::Missing, Some(_)) => writeln!(out, "    ❌ [{}] {}", entry.kind, name(&entry.video))?,
+                        (Status::Missing, None) => {
+                            let expected = entry.sidecar.as_deref().unwrap_or(Path::new(""));
+                            writeln!(out, "    ❌ [{}] {}", entry.kind, expected.strip_prefix(root).unwrap_or(expected).display())?
+                        }
+                        _ => writeln!(out, "    🚨 [{}] {}", entry.kind, name(&entry.sidecar))?,
+                    }
+                }
+            }
+        }
+
+        if self.entries.iter().any(|e| !e.is_trickplay()) {
+            writeln!(out, "\n--- Sidecars ---")?;
+            let mut kinds: Vec<&str> = self.entries.iter().filter(|e| !e.is_trickplay()).map(|e| e.kind.as_str()).collect();
+            kinds.sort();
+            kinds.dedup();
+
+            for kind in kinds {
+                let count = |status| self.entries.iter().filter(|e| e.kind == kind && e.status == status).count();
+                writeln!(
+                    out,
+                    "{} : {} ok, {} manquants, {} orphelins",
+                    kind,
+                    count(Status::Ok),
+                    count(Status::Missing),
+                    count(Status::Orphan)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn describe_estimate(estimate: &Estimate) -> String {
+    let runtime = estimate.runtime.as_secs() / 60;
+    let mut text = format!(
+        "{} pour {} fichiers ({}h{}min de vidéo)",
+        format_minutes(estimate.time),
+        estimate.files,
+        runtime / 60,
+        runtime % 60
+    );
+    if estimate.unknown > 0 {
+        text.push_str(&format!(", {} sans durée lisible", estimate.unknown));
+    }
+    text
+}
+
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Taille en unités décimales (Ko = 1000 octets), comme Finder.
+pub fn format_size(size_in_bytes: u64) -> String {
+    let units = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = size_in_bytes as f64;
+    let mut unit = 0;
+
+    while size >= 1000.0 && unit < units.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+
+    format!("{:.2} {}", size, units[unit])
+}
@@ -0,0 +1,367 @@
+use crate::config::{LibraryConfig, SidecarRule};
+use crate::estimate::Estimate;
+use crate::jellyfin::LibraryIndex;
+use crate::media;
+use crate::pairing;
+use crate::report::{Entry, Found, RootEstimate, ScanReport, Status, TRICKPLAY_KIND};
+use crate::sidecar::{self, Pattern};
+use crate::stale;
+use crate::trickplay;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Intervalle minimal entre deux rafraîchissements de la ligne d'avancement.
+const PROGRESS_REDRAW: Duration = Duration::from_millis(100);
+
+/// Contenu d'un dossier : vidéos, `.trickplay`, et tout le reste (candidats sidecars).
+#[derive(Debug, Clone, Default)]
+pub struct DirListing {
+    pub path: PathBuf,
+    pub videos: Vec<PathBuf>,
+    pub trickplays: Vec<PathBuf>,
+    pub others: Vec<String>,
+}
+
+/// Liste récursivement `folder` : un `DirListing` par dossier, `folder` lui-même en premier.
+pub fn find_videos_and_trickplay(folder: &Path) -> Vec<DirListing> {
+    let mut listings = Vec::new();
+    walk(folder, &mut listings);
+    listings
+}
+
+fn walk(folder: &Path, listings: &mut Vec<DirListing>) {
+    let index = listings.len();
+    listings.push(DirListing { path: folder.to_path_buf(), ..DirListing::default() });
+
+    let Ok(entries) = fs::read_dir(folder) else { return };
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else { continue };
+        let sub_path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+
+        if is_dir(&file_type, &sub_path) {
+            if name.ends_with(".trickplay") {
+                listings[index].trickplays.push(sub_path);
+            } else {
+                listings[index].others.push(name);
+                walk(&sub_path, listings);
+            }
+        } else if let Some(ext) = sub_path.extension()
+            && (ext == "mkv" || ext == "mp4")
+        {
+            listings[index].videos.push(sub_path);
+        } else {
+            listings[index].others.push(name);
+        }
+    }
+}
+
+/// Le type vient de `readdir` (`d_type`), sans `stat` par entrée. Seuls les liens
+/// symboliques sont résolus, pour continuer à suivre ceux qui pointent vers un dossier.
+fn is_dir(file_type: &fs::FileType, path: &Path) -> bool {
+    if file_type.is_symlink() { path.is_dir() } else { file_type.is_dir() }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+/// Dossiers d'éléments (films, séries) d'une racine, `None` si elle est illisible.
+/// Dossiers cachés ignorés comme dans Jellyfin (dont la quarantaine de `prune`).
+pub fn item_folders(root: &Path) -> Option<Vec<PathBuf>> {
+    let entries = fs::read_dir(root).ok()?;
+
+    Some(
+        entries
+            .flatten()
+            .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+            .filter_map(|entry| {
+                let path = entry.path();
+                is_dir(&entry.file_type().ok()?, &path).then_some(path)
+            })
+            .collect(),
+    )
+}
+
+/// Entrées d'un dossier d'élément : Trickplay, puis sidecars, et le temps pour générer ce qui manque.
+#[derive(Debug, Clone, Default)]
+pub struct ItemScan {
+    pub entries: Vec<Entry>,
+    pub sidecars: Vec<Entry>,
+    pub estimate: Estimate,
+    pub found: Found,
+}
+
+/// Une racine scannée : ses dossiers d'éléments dans l'ordre du listing, vide si elle est illisible.
+pub struct RootScan {
+    pub category: usize,
+    pub root: PathBuf,
+    pub items: Vec<(PathBuf, ItemScan)>,
+}
+
+/// Ce qu'il faut pour scanner un dossier d'élément, préparé une fois pour toute la bibliothèque.
+pub struct Scanner<'a> {
+    config: &'a LibraryConfig,
+    library: Option<&'a LibraryIndex>,
+    validate: bool,
+    /// Règles sidecars de chaque catégorie avec leur motif et leurs noms ignorés, dans l'ordre de `config.categories`
+    rules: Vec<Vec<(&'a SidecarRule, Pattern, Pattern)>>,
+    progress: bool,
+}
+
+impl<'a> Scanner<'a> {
+    /// Avec `library`, une vidéo sans `.trickplay` à côté est cherchée dans le dossier metadata de Jellyfin.
+    pub fn new(config: &'a LibraryConfig, library: Option<&'a LibraryIndex>, validate: bool) -> Self {
+        let rules = config
+            .categories
+            .iter()
+            .map(|category| {
+                config
+                    .sidecar_rules_for(category)
+                    .into_iter()
+                    .map(|rule| (rule, Pattern::parse(&rule.pattern), Pattern::parse(rule.ignore())))
+                    .collect()
+            })
+            .collect();
+
+        Scanner { config, library, validate, rules, progress: io::stderr().is_terminal() }
+    }
+
+    /// Liste les racines puis scanne tous leurs dossiers d'éléments avec `scan_threads` workers.
+    /// Une seule file pour toutes les racines : un disque lent n'attend pas la fin d'un autre.
+    pub fn scan_roots(&self) -> Vec<RootScan> {
+        let threads = self.config.scan_threads;
+        let roots: Vec<(usize, &PathBuf)> = self
+            .config
+            .categories
+            .iter()
+            .enumerate()
+            .flat_map(|(index, category)| category.roots.iter().map(move |root| (index, root)))
+            .collect();
+
+        let folders = parallel_map(&roots, threads, |(_, root)| item_folders(root).unwrap_or_default());
+        let work: Vec<(usize, &PathBuf)> = folders
+            .iter()
+            .enumerate()
+            .flat_map(|(root_index, folders)| folders.iter().map(move |folder| (root_index, folder)))
+            .collect();
+
+        let progress = Progress::new(self.progress, roots.iter().zip(&folders).map(|((_, root), folders)| (*root, folders.len())));
+        let scans = parallel_map(&work, threads, |&(root_index, folder)| {
+            let (category, root) = roots[root_index];
+            let item = self.scan_item(category, root, folder);
+            progress.tick(root_index);
+            item
+        });
+
+        let mut result: Vec<RootScan> = roots
+            .iter()
+            .map(|&(category, root)| RootScan { category, root: root.clone(), items: Vec::new() })
+            .collect();
+        for ((root_index, folder), item) in work.into_iter().zip(scans) {
+            result[root_index].items.push((folder.clone(), item));
+        }
+        result
+    }
+
+    /// Classe les vidéos et `.trickplay` de `folder`, sous `root` dans la catégorie `category_index`.
+    pub fn scan_item(&self, category_index: usize, root: &Path, folder: &Path) -> ItemScan {
+        let category = &self.config.categories[category_index];
+        let speed = self.config.speed_for(category);
+        let interval = Duration::from_millis(self.config.trickplay_interval_ms);
+        let mut item = ItemScan::default();
+        let entry = |kind: &str, status, video: Option<&PathBuf>, trickplay: Option<&PathBuf>, detail: Option<String>| Entry {
+            category: category.name.clone(),
+            root: root.to_path_buf(),
+            kind: kind.to_string(),
+            status,
+            video: video.cloned(),
+            trickplay: trickplay.cloned(),
+            sidecar: None,
+            detail,
+        };
+        let mut push = |status, video: Option<&PathBuf>, trickplay: Option<&PathBuf>, detail: Option<String>| {
+            item.entries.push(entry(TRICKPLAY_KIND, status, video, trickplay, detail));
+        };
+
+        let listings = find_videos_and_trickplay(folder);
+        let videos: Vec<PathBuf> = listings.iter().flat_map(|l| l.videos.iter().cloned()).collect();
+        let trickplays: Vec<PathBuf> = listings.iter().flat_map(|l| l.trickplays.iter().cloned()).collect();
+        item.found.videos = videos.len();
+        item.found.trickplays = trickplays.len();
+        let mut pairing = pairing::pair(&videos, &trickplays);
+        if let Some(library) = self.library {
+            pairing.missing.retain(|video| match library.trickplay_for(video) {
+                Some(tp) => {
+                    pairing.paired.push((video.clone(), tp));
+                    item.found.library_trickplays += 1;
+                    false
+                }
+                None => true,
+            });
+        }
+
+        for video in &pairing.missing {
+            item.estimate.add(&media::probe(video), speed, category.minutes_per_file);
+            push(Status::Missing, Some(video), None, None);
+        }
+
+        for tp in &pairing.orphans {
+            push(Status::Orphan, None, Some(tp), None);
+        }
+
+        // Vérifie le contenu des Trickplay appariés
+        for (video, tp) in &pairing.paired {
+            if !self.validate {
+                push(Status::Ok, Some(video), Some(tp), None);
+                continue;
+            }
+
+            let info = media::probe(video);
+            // Vidéo remplacée : le nombre de tuiles ne dit plus rien, inutile de les lire.
+            if let Some(reason) = stale::check(video, tp, &info) {
+                item.estimate.add(&info, speed, category.minutes_per_file);
+                push(Status::Stale, Some(video), Some(tp), Some(reason));
+                continue;
+            }
+
+            match trickplay::validate(tp, info.duration, interval) {
+                Ok(()) => push(Status::Ok, Some(video), Some(tp), None),
+                Err(issue) => {
+                    let status = if issue.is_corrupt() { Status::Corrupt } else { Status::Incomplete };
+                    item.estimate.add(&info, speed, category.minutes_per_file);
+                    push(status, Some(video), Some(tp), Some(issue.to_string()));
+                }
+            }
+        }
+
+        // Plusieurs candidats pour le même nom : une entrée par fichier concerné
+        for ambiguity in &pairing.ambiguous {
+            let videos: Vec<String> = ambiguity.videos.iter().map(|v| file_name(v)).collect();
+            let trickplays: Vec<String> = ambiguity.trickplays.iter().map(|tp| file_name(tp)).collect();
+            let detail = format!("{} ↔ {}", videos.join(", "), trickplays.join(", "));
+
+            for video in &ambiguity.videos {
+                push(Status::Ambiguous, Some(video), None, Some(detail.clone()));
+            }
+            for tp in &ambiguity.trickplays {
+                push(Status::Ambiguous, None, Some(tp), Some(detail.clone()));
+            }
+        }
+
+        // Sidecars sur le même parcours, affichés après les Trickplay
+        for (rule, pattern, ignore) in &self.rules[category_index] {
+            for (status, video, path) in sidecar::audit(rule, pattern, ignore, &listings) {
+                item.sidecars.push(Entry { sidecar: Some(path), ..entry(&rule.name, status, video.as_ref(), None, None) });
+            }
+        }
+
+        item
+    }
+}
+
+/// Ajoute au rapport les éléments d'une racine : tous les Trickplay, puis tous les sidecars.
+pub fn append_root<'i>(report: &mut ScanReport, category: &str, root: &Path, items: impl IntoIterator<Item = &'i ItemScan> + Clone) {
+    let mut estimate = Estimate::default();
+    for item in items.clone() {
+        report.entries.extend(item.entries.iter().cloned());
+        report.found.merge(&item.found);
+        estimate.merge(&item.estimate);
+    }
+    for item in items {
+        report.entries.extend(item.sidecars.iter().cloned());
+    }
+
+    if estimate.files > 0 {
+        report.estimates.push(RootEstimate { category: category.to_string(), root: root.to_path_buf(), estimate });
+    }
+}
+
+/// Applique `f` à chaque élément avec au plus `threads` workers, résultats dans l'ordre de `items`.
+fn parallel_map<T: Sync, R: Send>(items: &[T], threads: usize, f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Option<R>> = items.iter().map(|_| None).collect();
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads.clamp(1, items.len().max(1)))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(index) else { return done };
+                        done.push((index, f(item)));
+                    }
+                })
+            })
+            .collect();
+
+        for worker in workers {
+            for (index, result) in worker.join().unwrap() {
+                results[index] = Some(result);
+            }
+        }
+    });
+
+    results.into_iter().map(Option::unwrap).collect()
+}
+
+/// Ligne d'avancement sur stderr : `fait/total` de chaque racine en cours, une ligne par racine terminée.
+struct Progress {
+    enabled: bool,
+    roots: Vec<(String, usize, AtomicUsize)>,
+    last_draw: Mutex<Instant>,
+}
+
+impl Progress {
+    fn new<'p>(enabled: bool, roots: impl Iterator<Item = (&'p PathBuf, usize)>) -> Self {
+        Progress {
+            enabled,
+            roots: roots.map(|(root, total)| (root.display().to_string(), total, AtomicUsize::new(0))).collect(),
+            last_draw: Mutex::new(Instant::now()),
+        }
+    }
+
+    fn tick(&self, root_index: usize) {
+        if !self.enabled {
+            return;
+        }
+
+        let (name, total, done) = &self.roots[root_index];
+        let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut last_draw = self.last_draw.lock().unwrap();
+        if done == *total {
+            eprintln!("\r\x1b[K📁 {} : {} dossiers", name, total);
+        } else if last_draw.elapsed() >= PROGRESS_REDRAW {
+            let running: Vec<String> = self
+                .roots
+                .iter()
+                .map(|(name, total, done)| (name, total, done.load(Ordering::Relaxed)))
+                .filter(|(_, total, done)| *done > 0 && done < total)
+                .map(|(name, total, done)| format!("{} {}/{}", name, done, total))
+                .collect();
+            eprint!("\r\x1b[K⏳ {}", running.join(" · "));
+        } else {
+            return;
+        }
+        *last_draw = Instant::now();
+    }
+}
+
+/// Parcourt chaque racine de chaque catégorie et classe toutes les vidéos et tous les `.trickplay`.
+pub fn scan_library(config: &LibraryConfig, library: Option<&LibraryIndex>, validate: bool) -> ScanReport {
+    let scanner = Scanner::new(config, library, validate);
+    let mut report = ScanReport::default();
+
+    for root in scanner.scan_roots() {
+        let category = &config.categories[root.category].name;
+        append_root(&mut report, category, &root.root, root.items.iter().map(|(_, item)| item));
+    }
+
+    report
+}
@@ -0,0 +1,194 @@
+use crate::config::{SidecarRule, SidecarScope};
+use crate::report::Status;
+use crate::scan::DirListing;
+use std::path::{Path, PathBuf};
+use unicode_normalization::UnicodeNormalization;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(char),
+    /// `*` : n'importe quelle suite, vide comprise
+    Any,
+    /// `?` : un caractère
+    One,
+    /// `{stem}` : le nom de la vidéo sans extension
+    Stem,
+}
+
+/// Un motif de règle : alternatives séparées par `|`, avec `*`, `?` et `{stem}`.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    alternatives: Vec<Vec<Token>>,
+}
+
+impl Pattern {
+    pub fn parse(pattern: &str) -> Self {
+        let alternatives = pattern
+            .split('|')
+            .map(|alternative| {
+                let mut tokens = Vec::new();
+                let mut rest = alternative.trim();
+                while !rest.is_empty() {
+                    if let Some(after) = rest.strip_prefix("{stem}") {
+                        tokens.push(Token::Stem);
+                        rest = after;
+                        continue;
+                    }
+                    let c = rest.chars().next().unwrap();
+                    tokens.push(match c {
+                        '*' => Token::Any,
+                        '?' => Token::One,
+                        c => Token::Literal(c),
+                    });
+                    rest = &rest[c.len_utf8()..];
+                }
+                tokens
+            })
+            .filter(|tokens| !tokens.is_empty())
+            .collect();
+
+        Pattern { alternatives }
+    }
+
+    pub fn uses_stem(&self) -> bool {
+        self.alternatives.iter().any(|alternative| alternative.contains(&Token::Stem))
+    }
+
+    /// Avec `stem = None`, `{stem}` accepte n'importe quel nom non vide.
+    pub fn matches(&self, name: &str, stem: Option<&str>) -> bool {
+        let name: Vec<char> = name.nfc().collect();
+        let stem: Option<Vec<char>> = stem.map(|stem| stem.nfc().collect());
+
+        self.alternatives.iter().any(|alternative| {
+            let tokens: Vec<Token> = match &stem {
+                Some(stem) => alternative
+                    .iter()
+                    .flat_map(|token| match token {
+                        Token::Stem => stem.iter().map(|c| Token::Literal(*c)).collect(),
+                        token => vec![token.clone()],
+                    })
+                    .collect(),
+                None => alternative.clone(),
+            };
+            glob_match(&tokens, &name)
+        })
+    }
+
+    /// Nom attendu, pour l'afficher quand le sidecar manque (première alternative).
+    pub fn expected_name(&self, stem: Option<&str>) -> String {
+        let Some(alternative) = self.alternatives.first() else { return String::new() };
+
+        alternative
+            .iter()
+            .map(|token| match token {
+                Token::Literal(c) => c.to_string(),
+                Token::Any => "*".to_string(),
+                Token::One => "?".to_string(),
+                Token::Stem => stem.unwrap_or("{stem}").to_string(),
+            })
+            .collect()
+    }
+}
+
+fn glob_match(tokens: &[Token], name: &[char]) -> bool {
+    match tokens.split_first() {
+        None => name.is_empty(),
+        Some((Token::Literal(c), rest)) => name.first() == Some(c) && glob_match(rest, &name[1..]),
+        Some((Token::One, rest)) => !name.is_empty() && glob_match(rest, &name[1..]),
+        Some((Token::Any, rest)) => (0..=name.len()).any(|skip| glob_match(rest, &name[skip..])),
+        Some((Token::Stem, rest)) => (1..=name.len()).any(|skip| glob_match(rest, &name[skip..])),
+    }
+}
+
+/// Résultat d'une règle : `(statut, vidéo concernée, chemin du sidecar trouvé ou attendu)`.
+pub type Finding = (Status, Option<PathBuf>, PathBuf);
+
+fn stem_of(video: &Path) -> String {
+    video.file_stem().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+/// Applique une règle aux dossiers d'un élément (`listings[0]` est le dossier de l'élément).
+/// Les noms qui correspondent à `ignore` ne sont jamais signalés orphelins.
+pub fn audit(rule: &SidecarRule, pattern: &Pattern, ignore: &Pattern, listings: &[DirListing]) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let scope = rule.scope.unwrap_or(if pattern.uses_stem() { SidecarScope::Video } else { SidecarScope::Folder });
+
+    let find = |listing: &DirListing, stem: Option<&str>| {
+        listing.others.iter().find(|name| pattern.matches(name, stem)).map(|name| listing.path.join(name))
+    };
+
+    match scope {
+        SidecarScope::Video => {
+            // Un dossier sans vidéo (saison vide, extras) n'a pas de sidecar orphelin, seulement des métadonnées
+            for listing in listings.iter().filter(|listing| !listing.videos.is_empty()) {
+                let stems: Vec<String> = listing.videos.iter().map(|v| stem_of(v)).collect();
+
+                for (video, stem) in listing.videos.iter().zip(&stems) {
+                    match find(listing, Some(stem)) {
+                        Some(path) => findings.push((Status::Ok, Some(video.clone()), path)),
+                        None => findings.push((Status::Missing, Some(video.clone()), listing.path.join(pattern.expected_name(Some(stem))))),
+                    }
+                }
+
+                // Un sidecar nommé d'après une vidéo qui n'existe pas dans ce dossier
+                for name in &listing.others {
+                    if pattern.matches(name, None)
+                        && !ignore.matches(name, None)
+                        && !stems.iter().any(|stem| pattern.matches(name, Some(stem)))
+                    {
+                        findings.push((Status::Orphan, None, listing.path.join(name)));
+                    }
+                }
+            }
+        }
+        SidecarScope::Folder => {
+            for listing in listings.iter().filter(|listing| !listing.videos.is_empty()) {
+                match find(listing, None) {
+                    Some(path) => findings.push((Status::Ok, None, path)),
+                    None => findings.push((Status::Missing, None, listing.path.join(pattern.expected_name(None)))),
+                }
+            }
+        }
+        SidecarScope::Item => {
+            let Some(item) = listings.first() else { return findings };
+            if listings.iter().all(|listing| listing.videos.is_empty()) {
+                return findings;
+            }
+
+            match find(item, None) {
+                Some(path) => findings.push((Status::Ok, None, path)),
+                None => findings.push((Status::Missing, None, item.path.join(pattern.expected_name(None)))),
+            }
+        }
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listing(path: &str, videos: &[&str], others: &[&str]) -> DirListing {
+        DirListing {
+            path: PathBuf::from(path),
+            videos: videos.iter().map(|video| Path::new(path).join(video)).collect(),
+            trickplays: Vec::new(),
+            others: others.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn stem_rule_ignores_jellyfin_metadata_and_folders_without_videos() {
+        let rule = SidecarRule { name: "nfo".to_string(), pattern: "{stem}.nfo".to_string(), scope: None, ignore: None };
+        let listings = [
+            listing("/tv/Show", &[], &["tvshow.nfo", "Season 1", "notes.nfo"]),
+            listing("/tv/Show/Season 1", &["S01E01.mkv"], &["S01E01.nfo", "season.nfo", "S01E02.nfo"]),
+        ];
+
+        let findings = audit(&rule, &Pattern::parse(&rule.pattern), &Pattern::parse(rule.ignore()), &listings);
+        let orphans: Vec<&PathBuf> = findings.iter().filter(|(status, ..)| *status == Status::Orphan).map(|(.., path)| path).collect();
+        assert_eq!(orphans, [Path::new("/tv/Show/Season 1/S01E02.nfo")]);
+        assert!(findings.iter().any(|(status, _, path)| *status == Status::Ok && path.ends_with("S01E01.nfo")));
+    }
+}
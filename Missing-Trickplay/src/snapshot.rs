@@ -0,0 +1,486 @@
+use crate::config::LibraryConfig;
+use crate::report::{csv_field, Entry, OutputFormat, ScanReport, Status, SCHEMA_VERSION};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Un scan enregistré dans `<data_dir>/snapshots/<horodatage>.json`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Nom du fichier : l'horodatage, suivi de `-2`, `-3`… si plusieurs scans ont fini dans la même seconde
+    #[serde(skip)]
+    id: String,
+    pub schema_version: u32,
+    pub created_at: u64,
+    /// Tuiles lues (`false` avec `--skip-validation`)
+    pub validated: bool,
+    /// Catégories scannées, pour la couverture.
+    pub categories: Vec<String>,
+    /// Racines scannées par catégorie (après `-r`) : seules les racines communes à deux snapshots
+    /// sont comparées. Absent des anciens snapshots, où on le retrouve par les entrées.
+    #[serde(default)]
+    pub roots: BTreeMap<String, Vec<PathBuf>>,
+    pub entries: Vec<Entry>,
+}
+
+impl Snapshot {
+    pub fn new(report: &ScanReport, config: &LibraryConfig, validated: bool) -> Self {
+        let created_at = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        Snapshot {
+            id: created_at.to_string(),
+            schema_version: SCHEMA_VERSION,
+            created_at,
+            validated,
+            categories: config.categories.iter().map(|c| c.name.clone()).collect(),
+            roots: config.categories.iter().map(|c| (c.name.clone(), c.roots.clone())).collect(),
+            entries: report.entries.clone(),
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn path(dir: &Path, id: &str) -> PathBuf {
+        dir.join(format!("{}.json", id))
+    }
+
+    /// Réserve `<horodatage>.json`, ou `<horodatage>-2.json`… si un autre scan a fini dans la même
+    /// seconde (cron et lancement manuel) : deux snapshots ne s'écrasent jamais.
+    pub fn save(&mut self, dir: &Path) -> io::Result<PathBuf> {
+        fs::create_dir_all(dir)?;
+        let mut attempt = 1;
+        let path = loop {
+            self.id = if attempt == 1 { self.created_at.to_string() } else { format!("{}-{}", self.created_at, attempt) };
+            let path = Self::path(dir, &self.id);
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => break path,
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => attempt += 1,
+                Err(e) => return Err(e),
+            }
+        };
+
+        let tmp = path.with_extension("json.tmp");
+        let written = File::create(&tmp)
+            .and_then(|file| serde_json::to_writer(file, self).map_err(io::Error::other))
+            .and_then(|()| fs::rename(&tmp, &path));
+        if let Err(e) = written {
+            // Un fichier réservé mais vide ferait échouer `diff --history`
+            let _ = fs::remove_file(&tmp);
+            let _ = fs::remove_file(&path);
+            return Err(e);
+        }
+        Ok(path)
+    }
+
+    pub fn load(dir: &Path, id: &str) -> io::Result<Self> {
+        let file = File::open(Self::path(dir, id)).map_err(|e| io::Error::new(e.kind(), format!("snapshot {} : {}", id, e)))?;
+        let mut snapshot: Snapshot = serde_json::from_reader(io::BufReader::new(file)).map_err(io::Error::other)?;
+        snapshot.id = id.to_string();
+        Ok(snapshot)
+    }
+
+    /// Identifiants des snapshots, du plus ancien au plus récent.
+    pub fn list(dir: &Path) -> Vec<String> {
+        let Ok(entries) = fs::read_dir(dir) else { return Vec::new() };
+
+        let mut ids: Vec<(u64, u32, String)> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| {
+                let id = path.file_stem()?.to_str()?;
+                let (created_at, attempt) = id.split_once('-').unwrap_or((id, "1"));
+                Some((created_at.parse().ok()?, attempt.parse().ok()?, id.to_string()))
+            })
+            .collect();
+        ids.sort_unstable();
+        ids.into_iter().map(|(_, _, id)| id).collect()
+    }
+
+    /// Supprime les plus anciens snapshots pour n'en garder que `keep` (au moins le dernier),
+    /// et renvoie le nombre de snapshots supprimés.
+    pub fn remove_oldest(dir: &Path, keep: usize) -> io::Result<usize> {
+        let ids = Self::list(dir);
+        let excess = ids.len().saturating_sub(keep.max(1));
+        for id in &ids[..excess] {
+            fs::remove_file(Self::path(dir, id))?;
+        }
+        Ok(excess)
+    }
+
+    /// `(catégorie, racine)` scannées.
+    fn scanned_roots(&self) -> HashSet<(&str, &Path)> {
+        if self.roots.is_empty() {
+            return self.entries.iter().map(|e| (e.category.as_str(), e.root.as_path())).collect();
+        }
+        self.roots
+            .iter()
+            .flat_map(|(category, roots)| roots.iter().map(move |root| (category.as_str(), root.as_path())))
+            .collect()
+    }
+
+    /// `(catégorie, vidéos avec un Trickplay valide, vidéos)`, dans l'ordre de la config.
+    pub fn coverage(&self) -> Vec<(&str, usize, usize)> {
+        self.categories
+            .iter()
+            .map(|category| {
+                let videos = || self.entries.iter().filter(|e| e.is_trickplay() && &e.category == category && e.video.is_some());
+                let covered = videos().filter(|e| e.status == Status::Ok).count();
+                (category.as_str(), covered, videos().count())
+            })
+            .collect()
+    }
+}
+
+fn percent(covered: usize, total: usize) -> f64 {
+    if total == 0 { 100.0 } else { covered as f64 * 100.0 / total as f64 }
+}
+
+/// `1760767200` → `2025-10-18 06:00 UTC`
+pub fn format_timestamp(secs: u64) -> String {
+    // civil_from_days de Howard Hinnant
+    let z = (secs / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02} {:02}:{:02} UTC", year, month, day, secs % 86400 / 3600, secs % 3600 / 60)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Change {
+    #[serde(rename = "newly_missing")]
+    Missing,
+    #[serde(rename = "newly_generated")]
+    Generated,
+    #[serde(rename = "newly_orphaned")]
+    Orphaned,
+}
+
+impl Change {
+    pub const ALL: [Change; 3] = [Change::Missing, Change::Generated, Change::Orphaned];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Change::Missing => "newly_missing",
+            Change::Generated => "newly_generated",
+            Change::Orphaned => "newly_orphaned",
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            Change::Missing => "❌ Nouveaux manquants",
+            Change::Generated => "✅ Nouveaux générés",
+            Change::Orphaned => "🚨 Nouveaux orphelins",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Changed<'a> {
+    pub change: Change,
+    pub category: &'a str,
+    pub video: Option<&'a Path>,
+    pub trickplay: Option<&'a Path>,
+}
+
+#[derive(Serialize)]
+struct JsonCoverage<'a> {
+    category: &'a str,
+    covered: usize,
+    total: usize,
+    percent: f64,
+}
+
+#[derive(Serialize)]
+struct JsonDiff<'a> {
+    schema_version: u32,
+    from: u64,
+    to: u64,
+    totals: BTreeMap<&'static str, usize>,
+    skipped_roots: usize,
+    coverage_before: Vec<JsonCoverage<'a>>,
+    coverage_after: Vec<JsonCoverage<'a>>,
+    changes: &'a [Changed<'a>],
+}
+
+fn json_coverage(coverage: Vec<(&str, usize, usize)>) -> Vec<JsonCoverage<'_>> {
+    coverage
+        .into_iter()
+        .map(|(category, covered, total)| JsonCoverage { category, covered, total, percent: percent(covered, total) })
+        .collect()
+}
+
+/// Changements Trickplay de `from` à `to`, sur les racines scannées dans les deux.
+pub struct SnapshotDiff<'a> {
+    pub from: &'a Snapshot,
+    pub to: &'a Snapshot,
+    pub changes: Vec<Changed<'a>>,
+    /// Racines scannées dans un seul des deux snapshots, donc ignorées
+    pub skipped_roots: usize,
+}
+
+impl<'a> SnapshotDiff<'a> {
+    pub fn new(from: &'a Snapshot, to: &'a Snapshot) -> Self {
+        let (roots_before, roots_after) = (from.scanned_roots(), to.scanned_roots());
+        let common: HashSet<(&str, &Path)> = roots_before.intersection(&roots_after).copied().collect();
+        let skipped_roots = roots_before.symmetric_difference(&roots_after).count();
+        let shared = |e: &&Entry| e.is_trickplay() && common.contains(&(e.category.as_str(), e.root.as_path()));
+
+        let before: HashMap<&Path, Status> =
+            from.entries.iter().filter(shared).filter_map(|e| Some((e.video.as_deref()?, e.status))).collect();
+        let orphans_before: HashSet<&Path> = from
+            .entries
+            .iter()
+            .filter(shared)
+            .filter(|e| e.status == Status::Orphan)
+            .filter_map(|e| e.trickplay.as_deref())
+            .collect();
+
+        let mut changes = Vec::new();
+        for entry in to.entries.iter().filter(shared) {
+            let previous = entry.video.as_deref().and_then(|video| before.get(video));
+            let change = match entry.status {
+                // Vidéo ajoutée sans Trickplay, ou dont le Trickplay a disparu
+                Status::Missing if previous != Some(&Status::Missing) => Change::Missing,
+                Status::Ok if previous.is_some_and(|s| *s == Status::Missing || s.needs_regeneration()) => Change::Generated,
+                Status::Orphan if entry.trickplay.as_deref().is_some_and(|tp| !orphans_before.contains(tp)) => Change::Orphaned,
+                _ => continue,
+            };
+            changes.push(Changed {
+                change,
+                category: &entry.category,
+                video: entry.video.as_deref(),
+                trickplay: entry.trickplay.as_deref(),
+            });
+        }
+
+        SnapshotDiff { from, to, changes, skipped_roots }
+    }
+
+    pub fn count(&self, change: Change) -> usize {
+        self.changes.iter().filter(|c| c.change == change).count()
+    }
+
+    pub fn write(&self, format: OutputFormat, out: &mut impl Write) -> io::Result<()> {
+        match format {
+            OutputFormat::Text => self.write_text(out),
+            OutputFormat::Json => self.write_json(out),
+            OutputFormat::Csv => self.write_csv(out),
+        }
+    }
+
+    fn write_json(&self, out: &mut impl Write) -> io::Result<()> {
+        let diff = JsonDiff {
+            schema_version: SCHEMA_VERSION,
+            from: self.from.created_at,
+            to: self.to.created_at,
+            totals: Change::ALL.iter().map(|c| (c.as_str(), self.count(*c))).collect(),
+            skipped_roots: self.skipped_roots,
+            coverage_before: json_coverage(self.from.coverage()),
+            coverage_after: json_coverage(self.to.coverage()),
+            changes: &self.changes,
+        };
+
+        serde_json::to_writer_pretty(&mut *out, &diff)?;
+        writeln!(out)
+    }
+
+    fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "change,category,video,trickplay")?;
+
+        let path = |p: Option<&Path>| p.map(|p| p.display().to_string()).unwrap_or_default();
+        for changed in &self.changes {
+            writeln!(
+                out,
+                "{},{},{},{}",
+                changed.change.as_str(),
+                csv_field(changed.category),
+                csv_field(&path(changed.video)),
+                csv_field(&path(changed.trickplay)),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn write_text(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "📸 {} ({}) → {} ({})",
+            self.from.id(),
+            format_timestamp(self.from.created_at),
+            self.to.id(),
+            format_timestamp(self.to.created_at)
+        )?;
+        if self.from.validated != self.to.validated {
+            writeln!(out, "⚠️  Un seul des deux scans a vérifié les tuiles, les incomplets peuvent apparaître comme générés.")?;
+        }
+        if self.skipped_roots > 0 {
+            writeln!(out, "⚠️  {} racines scannées dans un seul des deux snapshots (-r ou config modifiée), ignorées.", self.skipped_roots)?;
+        }
+
+        for change in Change::ALL {
+            let changes: Vec<&Changed> = self.changes.iter().filter(|c| c.change == change).collect();
+            if changes.is_empty() {
+                continue;
+            }
+
+            writeln!(out, "\n{} ({})", change.title(), changes.len())?;
+            for changed in changes {
+                let path = changed.video.or(changed.trickplay).unwrap_or(Path::new(""));
+                writeln!(out, "  {} : {}", changed.category, path.display())?;
+            }
+        }
+
+        writeln!(out, "\n--- Couverture ---")?;
+        let before = self.from.coverage();
+        for (category, covered, total) in self.to.coverage() {
+            let after = percent(covered, total);
+            match before.iter().find(|(name, _, _)| *name == category) {
+                Some((_, covered_before, total_before)) => {
+                    let before = percent(*covered_before, *total_before);
+                    writeln!(out, "{} : {:.1}% → {:.1}% ({:+.1}), {}/{} vidéos", category, before, after, after - before, covered, total)?
+                }
+                None => writeln!(out, "{} : {:.1}%, {}/{} vidéos (absente du snapshot précédent)", category, after, covered, total)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Couverture par catégorie de chaque snapshot, du plus ancien au plus récent.
+pub fn write_history(snapshots: &[Snapshot], format: OutputFormat, out: &mut impl Write) -> io::Result<()> {
+    match format {
+        OutputFormat::Json => {
+            let history: Vec<_> = snapshots
+                .iter()
+                .map(|s| serde_json::json!({ "snapshot": s.created_at, "coverage": json_coverage(s.coverage()) }))
+                .collect();
+            serde_json::to_writer_pretty(&mut *out, &history)?;
+            writeln!(out)
+        }
+        OutputFormat::Csv => {
+            writeln!(out, "snapshot,category,covered,total,percent")?;
+            for snapshot in snapshots {
+                for (category, covered, total) in snapshot.coverage() {
+                    writeln!(out, "{},{},{},{},{:.1}", snapshot.created_at, csv_field(category), covered, total, percent(covered, total))?;
+                }
+            }
+            Ok(())
+        }
+        OutputFormat::Text => {
+            for snapshot in snapshots {
+                let coverage: Vec<String> = snapshot
+                    .coverage()
+                    .into_iter()
+                    .map(|(category, covered, total)| format!("{} {:.1}%", category, percent(covered, total)))
+                    .collect();
+                writeln!(out, "{}  {}  {}", snapshot.id(), format_timestamp(snapshot.created_at), coverage.join(" | "))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::TRICKPLAY_KIND;
+
+    fn entry(root: &str, video: &str, status: Status) -> Entry {
+        Entry {
+            category: "Films".to_string(),
+            root: PathBuf::from(root),
+            kind: TRICKPLAY_KIND.to_string(),
+            status,
+            video: Some(Path::new(root).join(video)),
+            trickplay: None,
+            sidecar: None,
+            detail: None,
+        }
+    }
+
+    fn snapshot(roots: &[&str], entries: Vec<Entry>) -> Snapshot {
+        Snapshot {
+            id: String::new(),
+            schema_version: SCHEMA_VERSION,
+            created_at: 0,
+            validated: true,
+            categories: vec!["Films".to_string()],
+            roots: BTreeMap::from([("Films".to_string(), roots.iter().map(PathBuf::from).collect())]),
+            entries,
+        }
+    }
+
+    #[test]
+    fn only_roots_scanned_in_both_are_compared() {
+        let from = snapshot(&["/a", "/b"], vec![entry("/a", "A.mkv", Status::Missing), entry("/b", "B.mkv", Status::Ok)]);
+        // `-r /a -r /c` : /b n'a pas été scannée, /c ne l'était pas avant
+        let to = snapshot(&["/a", "/c"], vec![entry("/a", "A.mkv", Status::Ok), entry("/c", "C.mkv", Status::Missing)]);
+
+        let diff = SnapshotDiff::new(&from, &to);
+        assert_eq!(diff.count(Change::Generated), 1);
+        assert_eq!(diff.count(Change::Missing), 0);
+        assert_eq!(diff.skipped_roots, 2);
+    }
+
+    #[test]
+    fn old_snapshots_fall_back_to_entry_roots() {
+        let mut from = snapshot(&[], vec![entry("/a", "A.mkv", Status::Ok)]);
+        from.roots.clear();
+        let to = snapshot(&["/a", "/c"], vec![entry("/a", "A.mkv", Status::Missing), entry("/c", "C.mkv", Status::Missing)]);
+
+        let diff = SnapshotDiff::new(&from, &to);
+        assert_eq!(diff.changes.len(), 1);
+        assert_eq!(diff.changes[0].video, Some(Path::new("/a/A.mkv")));
+    }
+
+    #[test]
+    fn snapshots_of_the_same_second_get_their_own_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut first = snapshot(&["/a"], vec![entry("/a", "A.mkv", Status::Missing)]);
+        let mut second = snapshot(&["/a"], vec![entry("/a", "A.mkv", Status::Ok)]);
+        first.created_at = 1_760_767_200;
+        second.created_at = 1_760_767_200;
+        let mut older = snapshot(&["/a"], vec![]);
+        older.created_at = 1_760_767_199;
+
+        for snapshot in [&mut first, &mut second, &mut older] {
+            snapshot.save(tmp.path()).unwrap();
+        }
+        assert_eq!((first.id(), second.id()), ("1760767200", "1760767200-2"));
+        assert_eq!(Snapshot::list(tmp.path()), ["1760767199", "1760767200", "1760767200-2"]);
+
+        let loaded = Snapshot::load(tmp.path(), "1760767200-2").unwrap();
+        assert_eq!(loaded.id(), "1760767200-2");
+        assert_eq!(loaded.entries[0].status, Status::Ok);
+        assert_eq!(Snapshot::load(tmp.path(), "1760767200").unwrap().entries[0].status, Status::Missing);
+    }
+
+    #[test]
+    fn remove_oldest_keeps_the_latest_snapshots() {
+        let tmp = tempfile::tempdir().unwrap();
+        for created_at in [30, 10, 20, 20] {
+            let mut snapshot = snapshot(&["/a"], vec![]);
+            snapshot.created_at = created_at;
+            snapshot.save(tmp.path()).unwrap();
+        }
+
+        assert_eq!(Snapshot::remove_oldest(tmp.path(), 2).unwrap(), 2);
+        assert_eq!(Snapshot::list(tmp.path()), ["20-2", "30"]);
+        assert_eq!(Snapshot::remove_oldest(tmp.path(), 5).unwrap(), 0);
+        // `keep_snapshots = 0` garde quand même le scan qui vient d'être enregistré
+        assert_eq!(Snapshot::remove_oldest(tmp.path(), 0).unwrap(), 1);
+        assert_eq!(Snapshot::list(tmp.path()), ["30"]);
+    }
+}
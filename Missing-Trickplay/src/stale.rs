@@ -0,0 +1,87 @@
+use crate::media::MediaInfo;
+use crate::snapshot::format_timestamp;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Fichier écrit par `generate` dans le dossier `.trickplay`, ignoré par Jellyfin.
+pub const SOURCE_FILE_NAME: &str = ".missing_trickplay.json";
+
+/// Vidéo modifiée moins d'une heure après le Trickplay : copie ou remux en cours, pas un remplacement.
+const MTIME_TOLERANCE: Duration = Duration::from_secs(3600);
+const DURATION_TOLERANCE: Duration = Duration::from_secs(2);
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// La vidéo telle qu'elle était quand son Trickplay a été généré.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceInfo {
+    pub size: u64,
+    pub modified: u64,
+    pub duration_ms: Option<u64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+impl SourceInfo {
+    pub fn read(video: &Path, info: &MediaInfo) -> io::Result<Self> {
+        let metadata = fs::metadata(video)?;
+        Ok(SourceInfo {
+            size: metadata.len(),
+            modified: metadata.modified().map_or(0, unix_secs),
+            duration_ms: info.duration.map(|d| d.as_millis() as u64),
+            width: info.width,
+            height: info.height,
+        })
+    }
+
+    pub fn load(trickplay: &Path) -> Option<Self> {
+        let file = File::open(trickplay.join(SOURCE_FILE_NAME)).ok()?;
+        serde_json::from_reader(file).ok()
+    }
+
+    pub fn save(&self, trickplay: &Path) -> io::Result<()> {
+        let file = File::create(trickplay.join(SOURCE_FILE_NAME))?;
+        serde_json::to_writer_pretty(file, self).map_err(io::Error::other)
+    }
+}
+
+/// Raison pour laquelle le Trickplay ne correspond plus à la vidéo, `None` s'il est à jour.
+/// Compare à la source enregistrée par `generate` si elle existe, sinon aux dates.
+pub fn check(video: &Path, trickplay: &Path, info: &MediaInfo) -> Option<String> {
+    let current = SourceInfo::read(video, info).ok()?;
+
+    if let Some(recorded) = SourceInfo::load(trickplay) {
+        if let (Some(before), Some(after)) = (recorded.width.zip(recorded.height), current.width.zip(current.height))
+            && before != after
+        {
+            return Some(format!("résolution {}x{} → {}x{}", before.0, before.1, after.0, after.1));
+        }
+        if let (Some(before), Some(after)) = (recorded.duration_ms, current.duration_ms)
+            && before.abs_diff(after) > DURATION_TOLERANCE.as_millis() as u64
+        {
+            let minutes = |ms: u64| format!("{}min{:02}", ms / 60_000, ms / 1000 % 60);
+            return Some(format!("durée {} → {}", minutes(before), minutes(after)));
+        }
+        if recorded.size != current.size {
+            return Some(format!("vidéo remplacée ({} → {} octets)", recorded.size, current.size));
+        }
+        return None;
+    }
+
+    // Sans source enregistrée (Trickplay généré par Jellyfin) : la date de création du dossier,
+    // ou sa date de modification si le système de fichiers ne la fournit pas.
+    let metadata = fs::metadata(trickplay).ok()?;
+    let generated = metadata.created().or_else(|_| metadata.modified()).map_or(0, unix_secs);
+    (current.modified > generated + MTIME_TOLERANCE.as_secs()).then(|| {
+        format!(
+            "vidéo modifiée le {}, Trickplay du {}",
+            format_timestamp(current.modified),
+            format_timestamp(generated)
+        )
+    })
+}
@@ -0,0 +1,136 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Un sous-dossier `<largeur> - <colonnes>x<lignes>` d'un dossier `.trickplay`.
+#[derive(Debug, Clone)]
+pub struct TileSet {
+    pub path: PathBuf,
+    pub width: u32,
+    pub cols: u32,
+    pub rows: u32,
+    pub tiles: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum TrickplayIssue {
+    Empty,
+    NoTiles(String),
+    MissingTile { resolution: String, index: usize },
+    CorruptTile(PathBuf),
+    TileCount { resolution: String, expected: usize, found: usize },
+}
+
+impl TrickplayIssue {
+    pub fn is_corrupt(&self) -> bool {
+        matches!(self, TrickplayIssue::CorruptTile(_))
+    }
+}
+
+impl fmt::Display for TrickplayIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrickplayIssue::Empty => write!(f, "dossier vide"),
+            TrickplayIssue::NoTiles(resolution) => write!(f, "aucune tuile dans '{}'", resolution),
+            TrickplayIssue::MissingTile { resolution, index } => {
+                write!(f, "tuile {}.jpg manquante dans '{}'", index, resolution)
+            }
+            TrickplayIssue::CorruptTile(path) => write!(f, "JPEG invalide : {}", path.display()),
+            TrickplayIssue::TileCount { resolution, expected, found } => {
+                write!(f, "{} tuiles dans '{}', ~{} attendues", found, resolution, expected)
+            }
+        }
+    }
+}
+
+/// Parse un nom `320 - 10x10` en `(320, 10, 10)`.
+fn parse_resolution_dir(name: &str) -> Option<(u32, u32, u32)> {
+    let (width, grid) = name.split_once(" - ")?;
+    let (cols, rows) = grid.split_once('x')?;
+    Some((width.trim().parse().ok()?, cols.trim().parse().ok()?, rows.trim().parse().ok()?))
+}
+
+/// Vérifie les marqueurs SOI (`FF D8 FF`) et EOI (`FF D9`) : une tuile tronquée n'a pas d'EOI.
+fn is_valid_jpeg(path: &Path) -> bool {
+    let Ok(mut file) = File::open(path) else { return false };
+
+    let mut start = [0u8; 3];
+    if file.read_exact(&mut start).is_err() || start != [0xFF, 0xD8, 0xFF] {
+        return false;
+    }
+
+    let mut end = [0u8; 2];
+    file.seek(SeekFrom::End(-2)).is_ok() && file.read_exact(&mut end).is_ok() && end == [0xFF, 0xD9]
+}
+
+fn scan_tile_set(path: &Path, name: &str, (width, cols, rows): (u32, u32, u32)) -> Result<TileSet, TrickplayIssue> {
+    let mut indices: Vec<usize> = fs::read_dir(path)
+        .map_err(|_| TrickplayIssue::NoTiles(name.to_string()))?
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name();
+            file_name.to_str()?.strip_suffix(".jpg")?.parse().ok()
+        })
+        .collect();
+
+    if indices.is_empty() {
+        return Err(TrickplayIssue::NoTiles(name.to_string()));
+    }
+
+    indices.sort_unstable();
+    if let Some(index) = indices.iter().enumerate().find(|(expected, index)| expected != *index).map(|(expected, _)| expected) {
+        return Err(TrickplayIssue::MissingTile { resolution: name.to_string(), index });
+    }
+
+    for index in &indices {
+        let tile = path.join(format!("{}.jpg", index));
+        if !is_valid_jpeg(&tile) {
+            return Err(TrickplayIssue::CorruptTile(tile));
+        }
+    }
+
+    Ok(TileSet { path: path.to_path_buf(), width, cols, rows, tiles: indices.len() })
+}
+
+/// Liste les jeux de tuiles d'un dossier `.trickplay` en validant chaque tuile.
+pub fn read_tile_sets(folder: &Path) -> Result<Vec<TileSet>, TrickplayIssue> {
+    let mut tile_sets = Vec::new();
+
+    for entry in fs::read_dir(folder).map_err(|_| TrickplayIssue::Empty)?.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Some(resolution) = parse_resolution_dir(&name) else { continue };
+        if !entry.file_type().is_ok_and(|t| t.is_dir()) {
+            continue;
+        }
+
+        tile_sets.push(scan_tile_set(&entry.path(), &name, resolution)?);
+    }
+
+    if tile_sets.is_empty() {
+        return Err(TrickplayIssue::Empty);
+    }
+    Ok(tile_sets)
+}
+
+/// Valide un dossier `.trickplay`. Si la durée de la vidéo est connue, le nombre de tuiles
+/// doit correspondre (à une tuile près) à `durée / intervalle` vignettes.
+pub fn validate(folder: &Path, duration: Option<Duration>, interval: Duration) -> Result<(), TrickplayIssue> {
+    let tile_sets = read_tile_sets(folder)?;
+
+    let Some(duration) = duration.filter(|_| !interval.is_zero()) else { return Ok(()) };
+    let thumbnails = duration.as_millis().div_ceil(interval.as_millis()) as usize;
+
+    for set in &tile_sets {
+        let per_tile = (set.cols * set.rows).max(1) as usize;
+        let expected = thumbnails.div_ceil(per_tile);
+
+        if set.tiles.abs_diff(expected) > 1 {
+            let resolution = set.path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            return Err(TrickplayIssue::TileCount { resolution, expected, found: set.tiles });
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,245 @@
+use crate::config::LibraryConfig;
+use crate::report::{ScanReport, Status, SCHEMA_VERSION};
+use crate::scan::{self, ItemScan, Scanner};
+use crate::snapshot::Snapshot;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Cursor};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// Attente après le dernier évènement avant de rescanner : la copie d'un film en produit des milliers.
+const DEBOUNCE: Duration = Duration::from_secs(2);
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+struct WatchedRoot {
+    category: usize,
+    root: PathBuf,
+    items: BTreeMap<PathBuf, ItemScan>,
+}
+
+/// État de chaque dossier d'élément, tenu à jour au fil des évènements du système de fichiers.
+pub struct CoverageIndex {
+    roots: Vec<WatchedRoot>,
+    updated_at: u64,
+    rescans: u64,
+}
+
+impl CoverageIndex {
+    pub fn build(scanner: &Scanner) -> Self {
+        let roots = scanner
+            .scan_roots()
+            .into_iter()
+            .map(|scan| WatchedRoot { category: scan.category, root: scan.root, items: scan.items.into_iter().collect() })
+            .collect();
+
+        CoverageIndex { roots, updated_at: now(), rescans: 0 }
+    }
+
+    fn rescan_root(&mut self, scanner: &Scanner, root_index: usize) {
+        let watched = &mut self.roots[root_index];
+        watched.items = scan::item_folders(&watched.root)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|folder| {
+                let item = scanner.scan_item(watched.category, &watched.root, &folder);
+                (folder, item)
+            })
+            .collect();
+    }
+
+    /// Rescanne un dossier d'élément, ou l'oublie s'il a disparu.
+    fn rescan_item(&mut self, scanner: &Scanner, root_index: usize, folder: &Path) -> Option<&ItemScan> {
+        let watched = &mut self.roots[root_index];
+        if !folder.is_dir() {
+            watched.items.remove(folder);
+            return None;
+        }
+
+        let item = scanner.scan_item(watched.category, &watched.root, folder);
+        Some(watched.items.entry(folder.to_path_buf()).insert_entry(item).into_mut())
+    }
+
+    /// Racine et dossier d'élément touchés par un évènement. `None` comme dossier : la racine
+    /// elle-même a changé. Tout ce qui passe par un nom caché est ignoré, à n'importe quelle
+    /// profondeur : quarantaine, `.<nom>.trickplay.partial` que `generate` remplit à côté de la vidéo.
+    fn locate(&self, path: &Path) -> Option<(usize, Option<PathBuf>)> {
+        self.roots.iter().enumerate().find_map(|(root_index, watched)| {
+            let relative = path.strip_prefix(&watched.root).ok()?;
+            let hidden = |component: Component| matches!(component, Component::Normal(name) if name.to_string_lossy().starts_with('.'));
+            if relative.components().any(hidden) {
+                return None;
+            }
+            match relative.components().next() {
+                None => Some((root_index, None)),
+                Some(Component::Normal(name)) => Some((root_index, Some(watched.root.join(name)))),
+                _ => None,
+            }
+        })
+    }
+
+    pub fn report(&self, config: &LibraryConfig) -> ScanReport {
+        let mut report = ScanReport::default();
+        for watched in &self.roots {
+            scan::append_root(&mut report, &config.categories[watched.category].name, &watched.root, watched.items.values());
+        }
+        report
+    }
+}
+
+fn json_response(body: Vec<u8>, content_type: &str, status: u16) -> Response<Cursor<Vec<u8>>> {
+    let header = Header::from_bytes("Content-Type", content_type).unwrap();
+    Response::from_data(body).with_status_code(status).with_header(header)
+}
+
+/// `GET /` : totaux et couverture, `GET /report` : rapport complet (`-f json`), `GET /report.csv`.
+fn handle(request: &Request, index: &Mutex<CoverageIndex>, config: &LibraryConfig, validate: bool) -> Response<Cursor<Vec<u8>>> {
+    if request.method() != &Method::Get {
+        return json_response(br#"{"error":"GET uniquement"}"#.to_vec(), "application/json", 405);
+    }
+
+    let (report, updated_at, rescans) = {
+        let index = index.lock().unwrap();
+        (index.report(config), index.updated_at, index.rescans)
+    };
+    let path = request.url().split('?').next().unwrap_or_default();
+    let mut body = Vec::new();
+
+    let written = match path {
+        "/" | "/status" => {
+            let snapshot = Snapshot::new(&report, config, validate);
+            let coverage: Vec<_> = snapshot
+                .coverage()
+                .into_iter()
+                .map(|(category, covered, total)| serde_json::json!({ "category": category, "covered": covered, "total": total }))
+                .collect();
+            let status = serde_json::json!({
+                "schema_version": SCHEMA_VERSION,
+                "updated_at": updated_at,
+                "rescans": rescans,
+                "totals": Status::ALL.iter().map(|s| (s.as_str(), report.count(*s))).collect::<BTreeMap<_, _>>(),
+                "coverage": coverage,
+            });
+            serde_json::to_writer_pretty(&mut body, &status).map_err(io::Error::other).map(|()| body.push(b'\n'))
+        }
+        "/report" => report.write_json(&mut body),
+        "/report.csv" => return json_response(report_csv(&report), "text/csv; charset=utf-8", 200),
+        _ => return json_response(br#"{"error":"introuvable, essayer /, /report ou /report.csv"}"#.to_vec(), "application/json", 404),
+    };
+
+    match written {
+        Ok(()) => json_response(body, "application/json", 200),
+        Err(e) => json_response(serde_json::json!({ "error": e.to_string() }).to_string().into_bytes(), "application/json", 500),
+    }
+}
+
+fn report_csv(report: &ScanReport) -> Vec<u8> {
+    let mut body = Vec::new();
+    let _ = report.write_csv(&mut body);
+    body
+}
+
+/// Surveille les racines et sert l'état courant en HTTP sur `listen`, jusqu'à l'arrêt du processus.
+pub fn run(config: &LibraryConfig, scanner: &Scanner, validate: bool, listen: &str) -> io::Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).map_err(io::Error::other)?;
+    for root in config.categories.iter().flat_map(|c| &c.roots) {
+        // Sous Linux, une erreur ici vient souvent de fs.inotify.max_user_watches trop bas.
+        if let Err(e) = watcher.watch(root, RecursiveMode::Recursive) {
+            eprintln!("⚠️  {} non surveillé : {}", root.display(), e);
+        }
+    }
+
+    println!("🔎 Scan initial...");
+    let index = Mutex::new(CoverageIndex::build(scanner));
+    let server = Server::http(listen).map_err(io::Error::other)?;
+    {
+        let index = index.lock().unwrap();
+        let report = index.report(config);
+        println!("👀 {} racines surveillées, {} manquants, http://{}/", index.roots.len(), report.count(Status::Missing), listen);
+    }
+
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            for request in server.incoming_requests() {
+                let response = handle(&request, &index, config, validate);
+                let _ = request.respond(response);
+            }
+        });
+
+        let mut dirty: BTreeSet<(usize, Option<PathBuf>)> = BTreeSet::new();
+        loop {
+            let event = if dirty.is_empty() { rx.recv().map_err(|_| mpsc::RecvTimeoutError::Disconnected) } else { rx.recv_timeout(DEBOUNCE) };
+
+            match event {
+                Ok(Ok(event)) if !matches!(event.kind, EventKind::Access(_)) => {
+                    let index = index.lock().unwrap();
+                    dirty.extend(event.paths.iter().filter_map(|path| index.locate(path)));
+                }
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => eprintln!("Watch error: {}", e),
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    let mut index = index.lock().unwrap();
+                    for (root_index, folder) in std::mem::take(&mut dirty) {
+                        match folder {
+                            None => index.rescan_root(scanner, root_index),
+                            Some(folder) => {
+                                let missing = index.rescan_item(scanner, root_index, &folder).map(|item| {
+                                    item.entries.iter().filter(|e| e.status == Status::Missing).count()
+                                });
+                                match missing {
+                                    Some(missing) => println!("🔄 {} ({} manquants)", folder.display(), missing),
+                                    None => println!("🗑️  {}", folder.display()),
+                                }
+                            }
+                        }
+                        index.rescans += 1;
+                    }
+                    index.updated_at = now();
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        server.unblock();
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(roots: &[&str]) -> CoverageIndex {
+        let roots = roots
+            .iter()
+            .map(|root| WatchedRoot { category: 0, root: PathBuf::from(root), items: BTreeMap::new() })
+            .collect();
+        CoverageIndex { roots, updated_at: 0, rescans: 0 }
+    }
+
+    #[test]
+    fn locate_ignores_hidden_names_at_any_depth() {
+        let index = index(&["/films", "/series"]);
+        let item = |root: usize, folder: &str| Some((root, Some(PathBuf::from(folder))));
+
+        assert_eq!(index.locate(Path::new("/films")), Some((0, None)));
+        assert_eq!(index.locate(Path::new("/films/Alien (1979)/Alien.mkv")), item(0, "/films/Alien (1979)"));
+        assert_eq!(index.locate(Path::new("/films/Alien (1979)/Alien.trickplay/320 - 10x10/0.jpg")), item(0, "/films/Alien (1979)"));
+        assert_eq!(index.locate(Path::new("/series/Dark/Saison 1/Dark S01E01.mkv")), item(1, "/series/Dark"));
+        assert_eq!(index.locate(Path::new("/musique/album.flac")), None);
+
+        // Dossier caché directement sous la racine (quarantaine)
+        assert_eq!(index.locate(Path::new("/films/.quarantine/Alien.trickplay")), None);
+        // `.partial` de `generate`, à côté de la vidéo : seul le renommage final compte
+        assert_eq!(index.locate(Path::new("/films/Alien (1979)/.Alien.trickplay.partial/320 - 10x10/0.jpg")), None);
+        assert_eq!(index.locate(Path::new("/series/Dark/Saison 1/.Dark S01E01.trickplay.partial")), None);
+    }
+}
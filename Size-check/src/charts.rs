@@ -0,0 +1,334 @@
+use std::collections::HashSet;
+use std::f32::consts::TAU;
+use eframe::egui::{self, Align2, Color32, FontId, Painter, Pos2, Rect, Shape, Stroke, StrokeKind};
+use eframe::egui::ecolor::Hsva;
+use crate::{format_size, FileNode, FolderNode, SizeUnit};
+
+/// Profondeur maximale dessinée sous le dossier affiché.
+const MAX_DEPTH: usize = 4;
+/// Hauteur du bandeau portant le nom d'un dossier dans la treemap.
+const HEADER_HEIGHT: f32 = 14.0;
+const SELECTED: Color32 = Color32::YELLOW;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorBy {
+    Extension,
+    Category,
+}
+
+/// Ce que l'utilisateur a fait sur le graphique pendant cette frame.
+pub enum ChartEvent {
+    /// Clic gauche sur un dossier : l'afficher seul
+    ZoomInto(String),
+    /// Clic droit : cocher ou décocher, comme dans l'arbre
+    ToggleSelection(String),
+}
+
+const CATEGORIES: [(&str, &[&str], Color32); 5] = [
+    ("Vidéo", &["mkv", "mp4", "avi", "m2ts", "ts", "mov", "wmv", "webm"], Color32::from_rgb(70, 120, 200)),
+    ("Sous-titres", &["srt", "ass", "ssa", "sub", "idx", "sup", "vtt"], Color32::from_rgb(90, 170, 90)),
+    ("Image", &["jpg", "jpeg", "png", "webp", "bmp", "gif"], Color32::from_rgb(220, 140, 50)),
+    ("Texte", &["nfo", "txt", "xml", "json", "md"], Color32::from_rgb(150, 150, 150)),
+    ("Audio", &["mp3", "flac", "m4a", "aac", "ogg", "opus", "wav"], Color32::from_rgb(180, 90, 180)),
+];
+const OTHER: Color32 = Color32::from_rgb(120, 100, 80);
+
+fn extension(name: &str) -> String {
+    name.rsplit_once('.').map(|(_, ext)| ext.to_lowercase()).unwrap_or_default()
+}
+
+/// Couleur d'un fichier : teinte stable par extension, ou couleur de sa catégorie.
+pub fn file_color(name: &str, color_by: ColorBy) -> Color32 {
+    let ext = extension(name);
+    match color_by {
+        ColorBy::Category => CATEGORIES.iter().find(|(_, exts, _)| exts.contains(&ext.as_str())).map_or(OTHER, |(_, _, color)| *color),
+        ColorBy::Extension => {
+            // FNV-1a : la même extension garde la même couleur d'une session à l'autre
+            let hash = ext.bytes().fold(0x811c9dc5u32, |h, b| (h ^ b as u32).wrapping_mul(0x01000193));
+            Hsva::new((hash % 360) as f32 / 360.0, 0.55, 0.75, 1.0).into()
+        }
+    }
+}
+
+fn folder_color(depth: usize) -> Color32 {
+    Color32::from_gray(35 + 12 * depth.min(8) as u8)
+}
+
+pub fn legend(ui: &mut egui::Ui, color_by: ColorBy) {
+    if color_by != ColorBy::Category {
+        return;
+    }
+    ui.horizontal(|ui| {
+        for (name, _, color) in CATEGORIES.iter().chain([("Autre", &[][..], OTHER)].iter()) {
+            let (rect, _) = ui.allocate_exact_size(egui::vec2(12.0, 12.0), egui::Sense::hover());
+            ui.painter().rect_filled(rect, 2.0, *color);
+            ui.label(*name);
+        }
+    });
+}
+
+/// Fichier ou sous-dossier, pour les traiter ensemble dans les dispositions.
+#[derive(Clone, Copy)]
+enum Node<'a> {
+    Folder(&'a FolderNode),
+    File(&'a FileNode),
+}
+
+impl<'a> Node<'a> {
+    fn size(&self) -> u64 {
+        match self {
+            Node::Folder(folder) => folder.size,
+            Node::File(file) => file.size,
+        }
+    }
+
+    fn path(&self) -> &'a str {
+        match self {
+            Node::Folder(folder) => &folder.path,
+            Node::File(file) => &file.path,
+        }
+    }
+
+    fn name(&self) -> &'a str {
+        match self {
+            Node::Folder(folder) => &folder.name,
+            Node::File(file) => &file.name,
+        }
+    }
+
+    fn color(&self, depth: usize, color_by: ColorBy) -> Color32 {
+        match self {
+            Node::Folder(_) => folder_color(depth),
+            Node::File(file) => file_color(&file.name, color_by),
+        }
+    }
+}
+
+/// Enfants non vides d'un dossier, du plus gros au plus petit.
+fn children(folder: &FolderNode) -> Vec<Node<'_>> {
+    let mut nodes: Vec<Node> = folder.subfolders.iter().map(Node::Folder).chain(folder.files.iter().map(Node::File)).filter(|n| n.size() > 0).collect();
+    nodes.sort_by_key(|node| std::cmp::Reverse(node.size()));
+    nodes
+}
+
+/// Pire rapport largeur/hauteur d'une rangée d'aires posée sur un côté de longueur `side`.
+fn worst_ratio(row: &[f32], side: f32) -> f32 {
+    let sum: f32 = row.iter().sum();
+    row.iter()
+        .map(|&area| (side * side * area / (sum * sum)).max(sum * sum / (side * side * area)))
+        .fold(0.0, f32::max)
+}
+
+/// Treemap « squarified » (Bruls, Huizing, van Wijk) : `sizes` triées par ordre décroissant,
+/// un rectangle par taille dans le même ordre, aussi proches que possible du carré.
+pub fn squarify(sizes: &[u64], rect: Rect) -> Vec<Rect> {
+    let total: u64 = sizes.iter().sum();
+    if total == 0 || rect.area() <= 0.0 {
+        return vec![Rect::NOTHING; sizes.len()];
+    }
+
+    let scale = rect.area() / total as f32;
+    let areas: Vec<f32> = sizes.iter().map(|&size| size as f32 * scale).collect();
+    let mut rects = Vec::with_capacity(sizes.len());
+    let mut remaining = rect;
+    let mut start = 0;
+
+    while start < areas.len() {
+        let side = remaining.width().min(remaining.height());
+        let mut end = start + 1;
+        while end < areas.len() && worst_ratio(&areas[start..=end], side) <= worst_ratio(&areas[start..end], side) {
+            end += 1;
+        }
+
+        // La rangée occupe toute la longueur du côté le plus court
+        let row = &areas[start..end];
+        let sum: f32 = row.iter().sum();
+        if remaining.width() >= remaining.height() {
+            let thickness = sum / remaining.height();
+            let mut y = remaining.top();
+            for area in row {
+                let height = area / thickness;
+                rects.push(Rect::from_min_size(Pos2::new(remaining.left(), y), egui::vec2(thickness, height)));
+                y += height;
+            }
+            remaining.min.x += thickness;
+        } else {
+            let thickness = sum / remaining.width();
+            let mut x = remaining.left();
+            for area in row {
+                let width = area / thickness;
+                rects.push(Rect::from_min_size(Pos2::new(x, remaining.top()), egui::vec2(width, thickness)));
+                x += width;
+            }
+            remaining.min.y += thickness;
+        }
+        start = end;
+    }
+    rects
+}
+
+/// Zone cliquable d'un graphique, la plus profonde en dernier.
+struct Hit<'a> {
+    node: Node<'a>,
+    depth: usize,
+}
+
+fn tooltip(ui: &mut egui::Ui, node: Node) {
+    ui.strong(node.name());
+    ui.label(format_size(node.size(), 2, SizeUnit::Decimal, None));
+    if let Node::Folder(folder) = node {
+        ui.label(format!("{} fichiers, {} dossiers", folder.files.len(), folder.subfolders.len()));
+    }
+    ui.weak(node.path());
+    ui.weak("Clic : zoomer · clic droit : sélectionner");
+}
+
+/// Traduit le clic de la frame en évènement, et affiche l'infobulle de l'élément survolé.
+fn interact(response: egui::Response, hovered: Option<Node>, folder_under: Option<&FolderNode>) -> Option<ChartEvent> {
+    let event = if response.clicked() {
+        folder_under.map(|folder| ChartEvent::ZoomInto(folder.path.clone()))
+    } else if response.secondary_clicked() {
+        hovered.map(|node| ChartEvent::ToggleSelection(node.path().to_string()))
+    } else {
+        None
+    };
+
+    if let Some(node) = hovered {
+        response.on_hover_ui_at_pointer(|ui| tooltip(ui, node));
+    }
+    event
+}
+
+fn draw_tiles<'a>(painter: &Painter, rect: Rect, folder: &'a FolderNode, depth: usize, color_by: ColorBy, selection: &HashSet<String>, hits: &mut Vec<(Rect, Hit<'a>)>) {
+    let nodes = children(folder);
+    let sizes: Vec<u64> = nodes.iter().map(Node::size).collect();
+
+    for (node, tile) in nodes.into_iter().zip(squarify(&sizes, rect)) {
+        if tile.width() < 1.0 || tile.height() < 1.0 {
+            continue;
+        }
+        painter.rect_filled(tile, 0.0, node.color(depth, color_by));
+        painter.rect_stroke(tile, 0.0, Stroke::new(1.0, Color32::from_black_alpha(160)), StrokeKind::Inside);
+        hits.push((tile, Hit { node, depth }));
+
+        let show_name = tile.width() > 40.0 && tile.height() > HEADER_HEIGHT;
+        if show_name {
+            painter.with_clip_rect(tile.shrink(1.0)).text(
+                tile.left_top() + egui::vec2(3.0, 1.0),
+                Align2::LEFT_TOP,
+                node.name(),
+                FontId::proportional(11.0),
+                Color32::WHITE,
+            );
+        }
+        if let Node::Folder(sub) = node
+            && depth + 1 < MAX_DEPTH
+            && tile.height() > 2.0 * HEADER_HEIGHT
+            && tile.width() > 2.0 * HEADER_HEIGHT
+        {
+            let inner = Rect::from_min_max(tile.left_top() + egui::vec2(2.0, HEADER_HEIGHT), tile.right_bottom() - egui::vec2(2.0, 2.0));
+            draw_tiles(painter, inner, sub, depth + 1, color_by, selection, hits);
+        }
+        if selection.contains(node.path()) {
+            painter.rect_stroke(tile, 0.0, Stroke::new(2.0, SELECTED), StrokeKind::Inside);
+        }
+    }
+}
+
+/// Treemap du dossier affiché : les sous-dossiers contiennent leurs propres tuiles jusqu'à `MAX_DEPTH`.
+pub fn treemap(ui: &mut egui::Ui, folder: &FolderNode, color_by: ColorBy, selection: &HashSet<String>) -> Option<ChartEvent> {
+    let (response, painter) = ui.allocate_painter(ui.available_size(), egui::Sense::click());
+    let mut hits = Vec::new();
+    draw_tiles(&painter, response.rect, folder, 0, color_by, selection, &mut hits);
+
+    let pointer = response.hover_pos();
+    let under: Vec<&Hit> = hits.iter().filter(|(rect, _)| pointer.is_some_and(|p| rect.contains(p))).map(|(_, hit)| hit).collect();
+    let hovered = under.iter().max_by_key(|hit| hit.depth).map(|hit| hit.node);
+    let folder_under = under
+        .iter()
+        .filter_map(|hit| match hit.node {
+            Node::Folder(folder) => Some((hit.depth, folder)),
+            Node::File(_) => None,
+        })
+        .max_by_key(|(depth, _)| *depth)
+        .map(|(_, folder)| folder);
+    interact(response, hovered, folder_under)
+}
+
+/// Arc d'anneau du sunburst, angles en radians depuis midi dans le sens horaire.
+struct Arc<'a> {
+    node: Node<'a>,
+    ring: usize,
+    start: f32,
+    end: f32,
+}
+
+fn collect_arcs<'a>(folder: &'a FolderNode, ring: usize, start: f32, span: f32, arcs: &mut Vec<Arc<'a>>) {
+    if ring > MAX_DEPTH || folder.size == 0 {
+        return;
+    }
+    let mut angle = start;
+    for node in children(folder) {
+        let node_span = span * node.size() as f32 / folder.size as f32;
+        // Trop fin pour être vu ou survolé
+        if node_span >= 0.004 {
+            arcs.push(Arc { node, ring, start: angle, end: angle + node_span });
+            if let Node::Folder(sub) = node {
+                collect_arcs(sub, ring + 1, angle, node_span, arcs);
+            }
+        }
+        angle += node_span;
+    }
+}
+
+fn polar(center: Pos2, radius: f32, angle: f32) -> Pos2 {
+    center + radius * egui::vec2(angle.sin(), -angle.cos())
+}
+
+/// Sunburst du dossier affiché : le disque central le représente, chaque anneau un niveau de plus.
+pub fn sunburst(ui: &mut egui::Ui, folder: &FolderNode, color_by: ColorBy, selection: &HashSet<String>) -> Option<ChartEvent> {
+    let (response, painter) = ui.allocate_painter(ui.available_size(), egui::Sense::click());
+    let center = response.rect.center();
+    let outer = response.rect.width().min(response.rect.height()) / 2.0 - 4.0;
+    let ring_width = outer / (MAX_DEPTH + 1) as f32;
+
+    painter.circle_filled(center, ring_width, folder_color(0));
+    painter.text(center, Align2::CENTER_CENTER, format_size(folder.size, 1, SizeUnit::Decimal, None), FontId::proportional(12.0), Color32::WHITE);
+
+    let mut arcs = Vec::new();
+    collect_arcs(folder, 1, 0.0, TAU, &mut arcs);
+    for arc in &arcs {
+        let (inner, outer) = (arc.ring as f32 * ring_width, (arc.ring + 1) as f32 * ring_width - 1.0);
+        let steps = ((arc.end - arc.start) / 0.05).ceil().max(1.0) as usize;
+        let step = (arc.end - arc.start) / steps as f32;
+        let color = arc.node.color(arc.ring, color_by);
+        // Un arc n'est pas convexe : découpé en quadrilatères
+        for i in 0..steps {
+            let (a0, a1) = (arc.start + i as f32 * step, arc.start + (i + 1) as f32 * step);
+            let quad = vec![polar(center, inner, a0), polar(center, outer, a0), polar(center, outer, a1), polar(center, inner, a1)];
+            painter.add(Shape::convex_polygon(quad, color, Stroke::NONE));
+        }
+        painter.line_segment([polar(center, inner, arc.start), polar(center, outer, arc.start)], Stroke::new(1.0, Color32::from_black_alpha(160)));
+
+        if selection.contains(arc.node.path()) {
+            let mut outline: Vec<Pos2> = (0..=steps).map(|i| polar(center, outer, arc.start + i as f32 * step)).collect();
+            outline.extend((0..=steps).rev().map(|i| polar(center, inner, arc.start + i as f32 * step)));
+            outline.push(outline[0]);
+            painter.add(Shape::line(outline, Stroke::new(2.0, SELECTED)));
+        }
+    }
+
+    let hovered = response.hover_pos().and_then(|pointer| {
+        let offset = pointer - center;
+        let ring = (offset.length() / ring_width) as usize;
+        let angle = offset.x.atan2(-offset.y).rem_euclid(TAU);
+        arcs.iter().find(|arc| arc.ring == ring && (arc.start..arc.end).contains(&angle)).map(|arc| arc.node)
+    });
+    let in_center = response.hover_pos().is_some_and(|pointer| (pointer - center).length() < ring_width);
+    let hovered = if in_center { Some(Node::Folder(folder)) } else { hovered };
+    let folder_under = match hovered {
+        Some(Node::Folder(folder)) => Some(folder),
+        _ => None,
+    };
+    interact(response, hovered, folder_under)
+}
@@ -0,0 +1,291 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+use serde::Serialize;
+use crate::{format_size, FolderNode, SizeUnit};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Change {
+    Added,
+    Removed,
+    Grown,
+    Shrunk,
+}
+
+impl Change {
+    pub const ALL: [Change; 4] = [Change::Added, Change::Removed, Change::Grown, Change::Shrunk];
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Change::Added => "+",
+            Change::Removed => "-",
+            Change::Grown => "▲",
+            Change::Shrunk => "▼",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Change::Added => "added",
+            Change::Removed => "removed",
+            Change::Grown => "grown",
+            Change::Shrunk => "shrunk",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffEntry {
+    /// Chemin relatif à la racine, identique d'un scan à l'autre
+    pub relative: String,
+    /// Chemin dans le nouveau scan, ou dans l'ancien pour un élément supprimé
+    pub path: String,
+    pub folder: bool,
+    pub change: Change,
+    pub old_size: Option<u64>,
+    pub new_size: Option<u64>,
+    pub delta: i64,
+}
+
+/// Taille signée, `+1.20 GB` ou `-350.00 MB`.
+pub fn format_delta(delta: i64) -> String {
+    let sign = if delta < 0 { "-" } else { "+" };
+    format!("{}{}", sign, format_size(delta.unsigned_abs(), 2, SizeUnit::Decimal, None))
+}
+
+struct Flat {
+    path: String,
+    size: u64,
+    folder: bool,
+}
+
+/// Indexe l'arborescence par chemin relatif construit avec les noms, pour comparer deux scans
+/// même si la racine a changé de point de montage (ou si `path` manque dans un vieux JSON).
+fn flatten(folder: &FolderNode, relative: &str, out: &mut BTreeMap<String, Flat>) {
+    for file in &folder.files {
+        out.insert(format!("{}{}", relative, file.name), Flat { path: file.path.clone(), size: file.size, folder: false });
+    }
+    for sub in &folder.subfolders {
+        let key = format!("{}{}/", relative, sub.name);
+        out.insert(key.clone(), Flat { path: sub.path.clone(), size: sub.size, folder: true });
+        flatten(sub, &key, out);
+    }
+}
+
+/// Différences entre deux scans. Un dossier ajouté ou supprimé n'est listé qu'une fois,
+/// sans son contenu ; un dossier modifié est listé avec ses enfants modifiés.
+#[derive(Debug, Clone)]
+pub struct ScanDiff {
+    pub old_total: u64,
+    pub new_total: u64,
+    pub entries: Vec<DiffEntry>,
+}
+
+#[derive(Serialize)]
+struct JsonDiff<'a> {
+    old_total: u64,
+    new_total: u64,
+    delta: i64,
+    totals: BTreeMap<&'static str, usize>,
+    changes: &'a [DiffEntry],
+}
+
+impl ScanDiff {
+    pub fn new(old: &FolderNode, new: &FolderNode) -> Self {
+        let (mut before, mut after) = (BTreeMap::new(), BTreeMap::new());
+        flatten(old, "", &mut before);
+        flatten(new, "", &mut after);
+
+        let mut entries = Vec::new();
+        let mut last_added_or_removed: Option<String> = None;
+        let keys: BTreeMap<&String, ()> = before.keys().chain(after.keys()).map(|k| (k, ())).collect();
+        // Ordre des clés : un dossier vient juste avant son contenu
+        for key in keys.into_keys() {
+            if last_added_or_removed.as_deref().is_some_and(|parent| key.starts_with(parent)) {
+                continue;
+            }
+
+            let (old, new) = (before.get(key), after.get(key));
+            let change = match (old, new) {
+                (None, Some(_)) => Change::Added,
+                (Some(_), None) => Change::Removed,
+                (Some(old), Some(new)) if new.size > old.size => Change::Grown,
+                (Some(old), Some(new)) if new.size < old.size => Change::Shrunk,
+                _ => continue,
+            };
+            let entry = new.or(old).unwrap();
+            if entry.folder && matches!(change, Change::Added | Change::Removed) {
+                last_added_or_removed = Some(key.clone());
+            }
+
+            let (old_size, new_size) = (old.map(|o| o.size), new.map(|n| n.size));
+            entries.push(DiffEntry {
+                relative: key.clone(),
+                path: entry.path.clone(),
+                folder: entry.folder,
+                change,
+                old_size,
+                new_size,
+                delta: new_size.unwrap_or(0) as i64 - old_size.unwrap_or(0) as i64,
+            });
+        }
+
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.delta.unsigned_abs()));
+        ScanDiff { old_total: old.size, new_total: new.size, entries }
+    }
+
+    pub fn count(&self, change: Change) -> usize {
+        self.entries.iter().filter(|entry| entry.change == change).count()
+    }
+
+    pub fn delta(&self) -> i64 {
+        self.new_total as i64 - self.old_total as i64
+    }
+
+    /// Changement de chaque chemin du nouveau scan, pour le surligner dans l'arbre.
+    pub fn changes_by_path(&self) -> HashMap<String, (Change, i64)> {
+        self.entries.iter().filter(|e| e.change != Change::Removed).map(|e| (e.path.clone(), (e.change, e.delta))).collect()
+    }
+
+    pub fn write_json(&self, output: &mut impl Write) -> io::Result<()> {
+        let diff = JsonDiff {
+            old_total: self.old_total,
+            new_total: self.new_total,
+            delta: self.delta(),
+            totals: Change::ALL.iter().map(|c| (c.label(), self.count(*c))).collect(),
+            changes: &self.entries,
+        };
+        serde_json::to_writer_pretty(&mut *output, &diff)?;
+        writeln!(output)
+    }
+
+    pub fn write_text(&self, output: &mut impl Write) -> io::Result<()> {
+        for entry in &self.entries {
+            let sizes = match (entry.old_size, entry.new_size) {
+                (Some(old), Some(new)) => format!(
+                    " ({} → {})",
+                    format_size(old, 2, SizeUnit::Decimal, None),
+                    format_size(new, 2, SizeUnit::Decimal, None)
+                ),
+                _ => String::new(),
+            };
+            writeln!(output, "{} {:>12}  {}{}", entry.change.symbol(), format_delta(entry.delta), entry.relative, sizes)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_summary(&self, output: &mut impl Write) -> io::Result<()> {
+        writeln!(output, "Diff summary:\n- Total size: {} → {} ({})\n- Added: {}\n- Removed: {}\n- Grown: {}\n- Shrunk: {}",
+                 format_size(self.old_total, 2, SizeUnit::Decimal, None),
+                 format_size(self.new_total, 2, SizeUnit::Decimal, None),
+                 format_delta(self.delta()),
+                 self.count(Change::Added),
+                 self.count(Change::Removed),
+                 self.count(Change::Grown),
+                 self.count(Change::Shrunk)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileNode;
+    use std::path::Path;
+
+    fn file(folder: &str, name: &str, size: u64) -> FileNode {
+        FileNode {
+            path: format!("{}/{}", folder, name),
+            name: name.to_string(),
+            size,
+            allocated: size,
+            links: 1,
+            inode: None,
+            modified: 0,
+            media: None,
+        }
+    }
+
+    fn folder(path: &str, files: Vec<FileNode>, subfolders: Vec<FolderNode>) -> FolderNode {
+        let mut folder = FolderNode::new(Path::new(path));
+        folder.size = files.iter().map(|f| f.size).sum::<u64>() + subfolders.iter().map(|s| s.size).sum::<u64>();
+        folder.files = files;
+        folder.subfolders = subfolders;
+        folder
+    }
+
+    fn change_of<'a>(diff: &'a ScanDiff, relative: &str) -> Option<&'a DiffEntry> {
+        diff.entries.iter().find(|entry| entry.relative == relative)
+    }
+
+    #[test]
+    fn lists_added_removed_grown_and_shrunk_entries() {
+        let old = folder("/old", vec![file("/old", "same.txt", 10), file("/old", "gone.txt", 50), file("/old", "big.mkv", 100)], vec![
+            folder("/old/docs", vec![file("/old/docs", "a.pdf", 300)], vec![]),
+        ]);
+        // Racine montée ailleurs : seuls les chemins relatifs comptent
+        let new = folder("/new", vec![file("/new", "same.txt", 10), file("/new", "big.mkv", 400), file("/new", "new.txt", 5)], vec![
+            folder("/new/docs", vec![file("/new/docs", "a.pdf", 200)], vec![]),
+        ]);
+        let diff = ScanDiff::new(&old, &new);
+
+        assert_eq!((diff.old_total, diff.new_total, diff.delta()), (460, 615, 155));
+        assert!(change_of(&diff, "same.txt").is_none());
+
+        let gone = change_of(&diff, "gone.txt").unwrap();
+        assert_eq!((gone.change, gone.old_size, gone.new_size, gone.delta), (Change::Removed, Some(50), None, -50));
+        assert_eq!(gone.path, "/old/gone.txt");
+        let added = change_of(&diff, "new.txt").unwrap();
+        assert_eq!((added.change, added.old_size, added.new_size, added.delta), (Change::Added, None, Some(5), 5));
+        assert_eq!(added.path, "/new/new.txt");
+        let grown = change_of(&diff, "big.mkv").unwrap();
+        assert_eq!((grown.change, grown.delta), (Change::Grown, 300));
+        let docs = change_of(&diff, "docs/").unwrap();
+        assert_eq!((docs.change, docs.folder, docs.delta), (Change::Shrunk, true, -100));
+        // Un dossier modifié garde ses enfants modifiés
+        assert_eq!(change_of(&diff, "docs/a.pdf").unwrap().change, Change::Shrunk);
+
+        assert_eq!(Change::ALL.map(|c| diff.count(c)), [1, 1, 1, 2]);
+        // Triés par variation absolue décroissante
+        let deltas: Vec<u64> = diff.entries.iter().map(|e| e.delta.unsigned_abs()).collect();
+        assert_eq!(deltas, [300, 100, 100, 50, 5]);
+    }
+
+    #[test]
+    fn nested_added_folders_are_listed_once() {
+        let old = folder("/m", vec![], vec![folder("/m/films", vec![file("/m/films", "a.mkv", 10)], vec![])]);
+        let new = folder("/m", vec![], vec![
+            folder("/m/films", vec![file("/m/films", "a.mkv", 10)], vec![
+                folder("/m/films/saga", vec![file("/m/films/saga", "1.mkv", 100)], vec![
+                    folder("/m/films/saga/bonus", vec![file("/m/films/saga/bonus", "making-of.mkv", 20)], vec![]),
+                ]),
+                // Nom qui commence comme le dossier ajouté, mais qui n'est pas dedans
+                folder("/m/films/sagas", vec![file("/m/films/sagas", "b.mkv", 1)], vec![]),
+            ]),
+        ]);
+        let diff = ScanDiff::new(&old, &new);
+
+        let relatives: Vec<&str> = diff.entries.iter().map(|e| e.relative.as_str()).collect();
+        assert_eq!(relatives, ["films/", "films/saga/", "films/sagas/"]);
+        let saga = change_of(&diff, "films/saga/").unwrap();
+        assert_eq!((saga.change, saga.folder, saga.delta), (Change::Added, true, 120));
+        assert_eq!(change_of(&diff, "films/").unwrap().change, Change::Grown);
+
+        // Et dans l'autre sens, un seul dossier supprimé
+        let diff = ScanDiff::new(&new, &old);
+        let removed: Vec<&str> = diff.entries.iter().filter(|e| e.change == Change::Removed).map(|e| e.relative.as_str()).collect();
+        assert_eq!(removed, ["films/saga/", "films/sagas/"]);
+    }
+
+    #[test]
+    fn summary_goes_to_the_given_writer() {
+        let old = folder("/m", vec![file("/m", "a", 1000)], vec![]);
+        let new = folder("/m", vec![file("/m", "a", 3000)], vec![]);
+        let mut output = Vec::new();
+        ScanDiff::new(&old, &new).write_summary(&mut output).unwrap();
+
+        let summary = String::from_utf8(output).unwrap();
+        assert!(summary.starts_with("Diff summary:\n- Total size: 1.00 KB → 3.00 KB (+2.00 KB)\n"), "{}", summary);
+        assert!(summary.ends_with("- Grown: 1\n- Shrunk: 0\n"), "{}", summary);
+    }
+}
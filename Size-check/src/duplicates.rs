@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use xxhash_rust::xxh3::Xxh3;
+use crate::{disk_usage, FileNode, FolderNode};
+
+/// Octets hachés au début et à la fin de chaque candidat avant de tout relire.
+const PARTIAL_CHUNK: u64 = 64 * 1024;
+const BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Fichiers au contenu identique. Chaque copie est un inode distinct, avec tous ses chemins
+/// (hardlinks) : supprimer un seul chemin d'une copie ne libère rien.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DuplicateGroup {
+    pub size: u64,
+    /// xxh3 128 bits du contenu complet, en hexadécimal
+    pub hash: String,
+    pub copies: Vec<Vec<String>>,
+    /// Pour chaque copie, ses liens hors de l'arborescence analysée : une telle copie reste sur
+    /// le disque même si tous ses chemins visibles sont supprimés.
+    #[serde(default)]
+    pub outside_links: Vec<u64>,
+}
+
+impl DuplicateGroup {
+    pub fn is_pinned(&self, copy: usize) -> bool {
+        self.outside_links.get(copy).is_some_and(|&links| links > 0)
+    }
+
+    /// Place libérée en ne gardant qu'une copie, de préférence une copie retenue par des liens
+    /// extérieurs puisqu'elle ne peut pas être libérée.
+    pub fn reclaimable(&self) -> u64 {
+        let pinned = (0..self.copies.len()).filter(|&copy| self.is_pinned(copy)).count();
+        let freeable = (self.copies.len() - pinned) as u64;
+        self.size * if pinned > 0 { freeable } else { freeable.saturating_sub(1) }
+    }
+
+    /// Retire les chemins supprimés, puis les copies qui n'en ont plus.
+    pub fn remove_paths(&mut self, removed: impl Fn(&str) -> bool) {
+        let outside_links = std::mem::take(&mut self.outside_links);
+        let mut copies = Vec::new();
+        for (index, mut copy) in std::mem::take(&mut self.copies).into_iter().enumerate() {
+            copy.retain(|path| !removed(path));
+            if !copy.is_empty() {
+                copies.push(copy);
+                self.outside_links.push(outside_links.get(index).copied().unwrap_or(0));
+            }
+        }
+        self.copies = copies;
+    }
+}
+
+/// Une copie candidate : les chemins d'un même inode, dans l'ordre du parcours.
+struct Candidate {
+    size: u64,
+    links: u64,
+    paths: Vec<String>,
+}
+
+fn collect_files<'a>(folder: &'a FolderNode, files: &mut Vec<&'a FileNode>) {
+    files.extend(&folder.files);
+    for subfolder in &folder.subfolders {
+        collect_files(subfolder, files);
+    }
+}
+
+/// Réunit les hardlinks d'un même inode. Les fichiers à un seul lien ne sont pas relus.
+fn into_copies(files: Vec<&FileNode>) -> Vec<Candidate> {
+    let mut copies: Vec<Candidate> = Vec::new();
+    let mut by_inode: HashMap<(u64, u64), usize> = HashMap::new();
+
+    for file in files {
+        let inode = if file.is_hardlink() { fs::metadata(&file.path).ok().and_then(|m| disk_usage(&m).0) } else { None };
+        match inode.and_then(|inode| by_inode.get(&inode)) {
+            Some(&index) => copies[index].paths.push(file.path.clone()),
+            None => {
+                if let Some(inode) = inode {
+                    by_inode.insert(inode, copies.len());
+                }
+                copies.push(Candidate { size: file.size, links: file.links, paths: vec![file.path.clone()] });
+            }
+        }
+    }
+    copies
+}
+
+/// Début et fin du fichier, ou tout le fichier s'il tient dans deux blocs.
+fn partial_hash(path: &str, size: u64) -> io::Result<u128> {
+    let mut file = File::open(path)?;
+    let mut hasher = Xxh3::new();
+    let mut buffer = vec![0; PARTIAL_CHUNK.min(size) as usize];
+
+    file.read_exact(&mut buffer)?;
+    hasher.update(&buffer);
+    if size > 2 * PARTIAL_CHUNK {
+        file.seek(SeekFrom::End(-(PARTIAL_CHUNK as i64)))?;
+        file.read_exact(&mut buffer)?;
+        hasher.update(&buffer);
+    } else if size > PARTIAL_CHUNK {
+        let mut rest = Vec::new();
+        file.read_to_end(&mut rest)?;
+        hasher.update(&rest);
+    }
+    Ok(hasher.digest128())
+}
+
+fn full_hash(path: &str, pb: &ProgressBar) -> io::Result<u128> {
+    let mut file = File::open(path)?;
+    let mut hasher = Xxh3::new();
+    let mut buffer = vec![0; BUFFER_SIZE];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(hasher.digest128());
+        }
+        hasher.update(&buffer[..read]);
+        pb.inc(read as u64);
+    }
+}
+
+/// Regroupe les copies par `key`, en ne gardant que les groupes d'au moins deux copies lisibles.
+fn regroup<K: std::hash::Hash + Eq>(
+    copies: Vec<Candidate>,
+    debug: bool,
+    mut key: impl FnMut(&Candidate) -> io::Result<K>,
+) -> Vec<(K, Vec<Candidate>)> {
+    let mut groups: HashMap<K, Vec<Candidate>> = HashMap::new();
+    for copy in copies {
+        match key(&copy) {
+            Ok(key) => groups.entry(key).or_default().push(copy),
+            Err(e) => {
+                if debug {
+                    eprintln!("Failed to hash {}: {}", copy.paths[0], e);
+                }
+            }
+        }
+    }
+    groups.into_iter().filter(|(_, group)| group.len() > 1).collect()
+}
+
+/// Cherche les fichiers identiques de l'arborescence : même taille, puis même hash partiel,
+/// puis même hash complet, avec une barre de progression par étape.
+pub fn find_duplicates(root: &FolderNode, debug: bool) -> Vec<DuplicateGroup> {
+    let mut files = Vec::new();
+    collect_files(root, &mut files);
+
+    let mut by_size: HashMap<u64, Vec<&FileNode>> = HashMap::new();
+    for file in files.into_iter().filter(|file| file.size > 0) {
+        by_size.entry(file.size).or_default().push(file);
+    }
+    let candidates: Vec<Vec<Candidate>> = by_size
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .map(into_copies)
+        .filter(|copies| copies.len() > 1)
+        .collect();
+
+    let pb = ProgressBar::new(candidates.iter().map(Vec::len).sum::<usize>() as u64);
+    pb.set_style(ProgressStyle::default_bar()
+        .template("{spinner:.green} {msg} [{bar:40.cyan/blue}] {pos}/{len}")
+        .unwrap()
+        .progress_chars("#>-"));
+    pb.set_message("Partial hash");
+
+    let mut partial = Vec::new();
+    for group in candidates {
+        partial.extend(regroup(group, debug, |copy| {
+            pb.inc(1);
+            partial_hash(&copy.paths[0], copy.size)
+        }));
+    }
+
+    // Le hash partiel couvre déjà tout le contenu des petits fichiers
+    let to_read = partial.iter().flat_map(|(_, group)| group).filter(|copy| copy.size > 2 * PARTIAL_CHUNK);
+    pb.set_length(to_read.map(|copy| copy.size).sum());
+    pb.set_position(0);
+    pb.set_style(ProgressStyle::default_bar()
+        .template("{spinner:.green} {msg} [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
+        .unwrap()
+        .progress_chars("#>-"));
+    pb.set_message("Full hash");
+
+    let mut duplicates = Vec::new();
+    for (hash, group) in partial {
+        let size = group[0].size;
+        let confirmed = if size <= 2 * PARTIAL_CHUNK {
+            vec![(hash, group)]
+        } else {
+            regroup(group, debug, |copy| full_hash(&copy.paths[0], &pb))
+        };
+
+        for (hash, group) in confirmed {
+            duplicates.push(DuplicateGroup {
+                size,
+                hash: format!("{:032x}", hash),
+                outside_links: group.iter().map(|copy| copy.links.saturating_sub(copy.paths.len() as u64)).collect(),
+                copies: group.into_iter().map(|copy| copy.paths).collect(),
+            });
+        }
+    }
+    pb.finish_with_message("Duplicates done.");
+
+    duplicates.sort_by(|a, b| b.reclaimable().cmp(&a.reclaimable()).then_with(|| a.copies.cmp(&b.copies)));
+    duplicates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::scan_blocking;
+    use crate::Summary;
+    use std::path::Path;
+
+    fn group(copies: &[&[&str]], outside_links: &[u64]) -> DuplicateGroup {
+        DuplicateGroup {
+            size: 100,
+            hash: String::new(),
+            copies: copies.iter().map(|copy| copy.iter().map(|path| path.to_string()).collect()).collect(),
+            outside_links: outside_links.to_vec(),
+        }
+    }
+
+    #[test]
+    fn reclaimable_keeps_one_copy_preferably_a_pinned_one() {
+        assert_eq!(group(&[&["a"], &["b"], &["c"]], &[0, 0, 0]).reclaimable(), 200);
+        // Ancien JSON sans `outside_links` : rien n'est retenu
+        assert_eq!(group(&[&["a"], &["b"]], &[]).reclaimable(), 100);
+        // Les chemins d'une même copie ne comptent qu'une fois
+        assert_eq!(group(&[&["a", "a2"], &["b"]], &[0, 0]).reclaimable(), 100);
+        // La copie retenue ailleurs est celle qu'on garde
+        assert_eq!(group(&[&["a"], &["b"], &["c"]], &[1, 0, 0]).reclaimable(), 200);
+        assert_eq!(group(&[&["a"], &["b"], &["c"]], &[1, 0, 2]).reclaimable(), 100);
+        assert_eq!(group(&[&["a"], &["b"]], &[1, 1]).reclaimable(), 0);
+        assert_eq!(group(&[&["a"]], &[0]).reclaimable(), 0);
+    }
+
+    #[test]
+    fn remove_paths_drops_emptied_copies_with_their_outside_links() {
+        let mut duplicates = group(&[&["a", "a2"], &["b"], &["c"]], &[0, 3, 1]);
+        duplicates.remove_paths(|path| path == "a2" || path == "b");
+        assert_eq!(duplicates.copies, [vec!["a".to_string()], vec!["c".to_string()]]);
+        assert_eq!(duplicates.outside_links, [0, 1]);
+        assert!(duplicates.is_pinned(1));
+        assert_eq!(duplicates.reclaimable(), 100);
+
+        duplicates.remove_paths(|path| path == "c");
+        assert_eq!((duplicates.copies.len(), duplicates.outside_links.len()), (1, 1));
+    }
+
+    /// Contenu pseudo-aléatoire, différent selon `seed`.
+    fn content(size: usize, seed: u8) -> Vec<u8> {
+        (0..size).map(|index| (index % 251) as u8 ^ seed).collect()
+    }
+
+    fn write(path: &Path, bytes: &[u8]) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn groups_by_size_then_partial_then_full_hash() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let big = 3 * PARTIAL_CHUNK as usize;
+
+        // Même contenu, dont un hardlink qui reste dans la même copie
+        write(&root.join("films/alien.mkv"), &content(big, 1));
+        write(&root.join("backup/alien.mkv"), &content(big, 1));
+        fs::hard_link(root.join("films/alien.mkv"), root.join("films/alien-link.mkv")).unwrap();
+        // Même début et même fin : seul le hash complet les sépare
+        let mut middle = content(big, 1);
+        middle[big / 2] ^= 0xff;
+        write(&root.join("films/alien-fixed.mkv"), &middle);
+        // Même taille, début différent : écarté dès le hash partiel
+        write(&root.join("films/other.mkv"), &content(big, 2));
+        // Petits fichiers lus en entier par le hash partiel
+        for name in ["notes.txt", "docs/notes.txt", "docs/old/notes.txt"] {
+            write(&root.join(name), b"liste de courses");
+        }
+        write(&root.join("docs/autre.txt"), b"liste de course!");
+        write(&root.join("unique.bin"), &content(10, 3));
+        write(&root.join("vide-1"), b"");
+        write(&root.join("vide-2"), b"");
+
+        let tree = scan_blocking(root, true, false, &mut Summary::default()).unwrap();
+        let duplicates = find_duplicates(&tree, false);
+        assert_eq!(duplicates.len(), 2, "{:?}", duplicates);
+
+        let path = |name: &str| root.join(name).display().to_string();
+        let mut films = duplicates[0].copies.clone();
+        films.iter_mut().for_each(|copy| copy.sort());
+        films.sort();
+        assert_eq!(films, [vec![path("backup/alien.mkv")], vec![path("films/alien-link.mkv"), path("films/alien.mkv")]]);
+        assert_eq!((duplicates[0].size, duplicates[0].reclaimable()), (big as u64, big as u64));
+        assert_eq!(duplicates[0].outside_links, [0, 0]);
+        assert_eq!(duplicates[0].hash.len(), 32);
+
+        let mut notes: Vec<String> = duplicates[1].copies.iter().flatten().cloned().collect();
+        notes.sort();
+        assert_eq!(notes, [path("docs/notes.txt"), path("docs/old/notes.txt"), path("notes.txt")]);
+        assert_eq!(duplicates[1].reclaimable(), 2 * 16);
+    }
+}
@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+use clap::ValueEnum;
+use serde_json::json;
+use crate::duplicates::DuplicateGroup;
+use crate::{format_size, hardlink_mark, FileNode, FolderNode, SizeUnit, Summary};
+
+/// Format de `--output-type`. Seuls `txt` et `json` servent aussi pour `--diff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputType {
+    Txt,
+    Json,
+    Ncdu,
+    Csv,
+    Html,
+}
+
+impl OutputType {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputType::Txt => "txt",
+            OutputType::Json => "json",
+            OutputType::Ncdu => "ncdu.json",
+            OutputType::Csv => "csv",
+            OutputType::Html => "html",
+        }
+    }
+}
+
+/// Export au format JSON de ncdu (version 1.2). Les tailles de dossier ne sont pas écrites, ncdu
+/// les recalcule. Chaque chemin d'un hardlink porte `ino`, `nlink` et `hlnkc` avec la taille réelle
+/// de l'inode : c'est ncdu qui le compte une seule fois et qui affiche ses liens.
+pub fn write_ncdu(root: &FolderNode, output: &mut impl Write) -> io::Result<()> {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |now| now.as_secs());
+    let metadata = json!({ "progname": env!("CARGO_PKG_NAME"), "progver": env!("CARGO_PKG_VERSION"), "timestamp": timestamp });
+    let mut inodes = HashMap::new();
+    collect_inode_sizes(root, &mut inodes);
+    write!(output, "[1,2,{},", metadata)?;
+    // ncdu attend le chemin complet pour la racine, le nom seul ensuite
+    write_ncdu_folder(root, &root.path, &inodes, output)?;
+    writeln!(output, "]")
+}
+
+/// Place allouée de chaque inode hardlinké, que l'analyse n'a laissée que sur un de ses chemins.
+fn collect_inode_sizes(folder: &FolderNode, inodes: &mut HashMap<u64, u64>) {
+    for file in folder.files.iter().filter(|file| file.is_hardlink()) {
+        if let Some(inode) = file.inode {
+            let allocated = inodes.entry(inode).or_default();
+            *allocated = (*allocated).max(file.allocated);
+        }
+    }
+    for subfolder in &folder.subfolders {
+        collect_inode_sizes(subfolder, inodes);
+    }
+}
+
+fn write_ncdu_folder(folder: &FolderNode, name: &str, inodes: &HashMap<u64, u64>, output: &mut impl Write) -> io::Result<()> {
+    write!(output, "[{}", json!({ "name": name }))?;
+    for file in &folder.files {
+        let mut entry = json!({ "name": file.name, "asize": file.size, "dsize": file.allocated });
+        if file.modified > 0 {
+            entry["mtime"] = file.modified.into();
+        }
+        if let Some(inode) = file.inode.filter(|_| file.is_hardlink()) {
+            entry["dsize"] = inodes.get(&inode).copied().unwrap_or(file.allocated).into();
+            entry["ino"] = inode.into();
+            entry["nlink"] = file.links.into();
+            entry["hlnkc"] = true.into();
+        }
+        write!(output, ",\n{}", entry)?;
+    }
+    for subfolder in &folder.subfolders {
+        writeln!(output, ",")?;
+        write_ncdu_folder(subfolder, &subfolder.name, inodes, output)?;
+    }
+    write!(output, "]")
+}
+
+const CSV_HEADER: &str = "kind,path,size,allocated,links,modified,video_codec,width,height,hdr,duration,bitrate,audio_languages";
+
+/// Guillemets autour des champs qui contiennent une virgule, un guillemet ou un retour à la ligne.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+/// Liste à plat, dossiers compris (avec leur taille totale), dans l'ordre de l'arborescence triée.
+/// Les colonnes vidéo ne sont remplies qu'avec `--media`.
+pub fn write_csv(root: &FolderNode, output: &mut impl Write) -> io::Result<()> {
+    writeln!(output, "{}", CSV_HEADER)?;
+    write_csv_folder(root, output)
+}
+
+fn write_csv_folder(folder: &FolderNode, output: &mut impl Write) -> io::Result<()> {
+    writeln!(output, "folder,{},{},{},,{},,,,,,,", csv_field(&folder.path), folder.size, folder.allocated, folder.modified)?;
+    for file in &folder.files {
+        write_csv_file(file, output)?;
+    }
+    for subfolder in &folder.subfolders {
+        write_csv_folder(subfolder, output)?;
+    }
+    Ok(())
+}
+
+fn write_csv_file(file: &FileNode, output: &mut impl Write) -> io::Result<()> {
+    write!(output, "file,{},{},{},{},{}", csv_field(&file.path), file.size, file.allocated, file.links, file.modified)?;
+    match &file.media {
+        Some(media) => writeln!(
+            output,
+            ",{},{},{},{},{},{},{}",
+            csv_field(media.video_codec.as_deref().unwrap_or_default()),
+            optional(media.width),
+            optional(media.height),
+            media.hdr,
+            optional(media.duration.map(|duration| format!("{:.3}", duration))),
+            optional(media.bitrate),
+            csv_field(&media.audio_languages.join(" "))
+        ),
+        None => writeln!(output, ",,,,,,,"),
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn size(bytes: u64) -> String {
+    format_size(bytes, 2, SizeUnit::Decimal, None)
+}
+
+/// Part de `size` dans `parent`, en pourcentage pour la largeur des barres.
+fn share(size: u64, parent: u64) -> f64 {
+    if parent == 0 { 0.0 } else { size as f64 * 100.0 / parent as f64 }
+}
+
+const HTML_STYLE: &str = "\
+body { font: 14px/1.4 system-ui, sans-serif; margin: 2em; color: #222; }
+h1 { font-size: 1.4em; word-break: break-all; }
+.totals { color: #555; }
+details { margin-left: 1.2em; }
+details.root { margin-left: 0; }
+summary { cursor: pointer; }
+summary, .file { display: flex; align-items: center; gap: 0.6em; padding: 1px 0; }
+.file { margin-left: 2.4em; }
+.bar { flex: none; width: 120px; height: 10px; background: #eee; border-radius: 2px; overflow: hidden; }
+.bar span { display: block; height: 100%; background: #4a90d9; }
+.file .bar span { background: #8cb8e6; }
+.size { flex: none; width: 7em; text-align: right; font-variant-numeric: tabular-nums; }
+.name { word-break: break-all; }
+.info { color: #888; }
+.duplicates li { margin: 0.3em 0; }
+";
+
+/// Rapport HTML sans dépendance externe : chaque dossier est un `<details>` replié (sauf la racine),
+/// avec une barre proportionnelle à sa part dans le dossier parent.
+pub fn write_html(root: &FolderNode, duplicates: &[DuplicateGroup], summary: &Summary, output: &mut impl Write) -> io::Result<()> {
+    writeln!(output, "<!DOCTYPE html>\n<html lang=\"fr\">\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(output, "<title>Analyse de {}</title>\n<style>\n{}</style>\n</head>\n<body>", escape_html(&root.path), HTML_STYLE)?;
+    writeln!(output, "<h1>📂 {}</h1>", escape_html(&root.path))?;
+    writeln!(
+        output,
+        "<p class=\"totals\">{} · {} sur le disque · {} fichiers · {} dossiers</p>",
+        size(summary.total_size),
+        size(summary.total_allocated),
+        summary.total_files,
+        summary.total_folders
+    )?;
+
+    write_html_folder(root, root.size, true, output)?;
+
+    if !duplicates.is_empty() {
+        writeln!(
+            output,
+            "<h2>👯 Doublons ({} groupes, {} récupérables)</h2>\n<ul class=\"duplicates\">",
+            duplicates.len(),
+            size(summary.duplicate_reclaimable)
+        )?;
+        for group in duplicates {
+            writeln!(output, "<li>{} × {} <span class=\"info\">({} récupérables)</span><ul>", size(group.size), group.copies.len(), size(group.reclaimable()))?;
+            for (index, copy) in group.copies.iter().enumerate() {
+                let pinned = if group.is_pinned(index) {
+                    format!(" <span class=\"info\">(📌 +{} liens hors de l'arborescence)</span>", group.outside_links[index])
+                } else {
+                    String::new()
+                };
+                writeln!(output, "<li>{}{}</li>", escape_html(&copy.join(" 🔗 ")), pinned)?;
+            }
+            writeln!(output, "</ul></li>")?;
+        }
+        writeln!(output, "</ul>")?;
+    }
+    writeln!(output, "</body>\n</html>")
+}
+
+fn write_html_folder(folder: &FolderNode, parent_size: u64, root: bool, output: &mut impl Write) -> io::Result<()> {
+    writeln!(
+        output,
+        "<details{}><summary><span class=\"bar\"><span style=\"width:{:.1}%\"></span></span><span class=\"size\">{}</span><span class=\"name\">{}/</span></summary>",
+        if root { " class=\"root\" open" } else { "" },
+        share(folder.size, parent_size),
+        size(folder.size),
+        escape_html(&folder.name)
+    )?;
+    for file in &folder.files {
+        let info = file.media.as_ref().map(|media| media.describe()).unwrap_or_default();
+        writeln!(
+            output,
+            "<div class=\"file\"><span class=\"bar\"><span style=\"width:{:.1}%\"></span></span><span class=\"size\">{}</span><span class=\"name\">{}{}</span><span class=\"info\">{}</span></div>",
+            share(file.size, folder.size),
+            size(file.size),
+            escape_html(&file.name),
+            escape_html(&hardlink_mark(file)),
+            escape_html(&info)
+        )?;
+    }
+    for subfolder in &folder.subfolders {
+        write_html_folder(subfolder, folder.size, false, output)?;
+    }
+    writeln!(output, "</details>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::MediaInfo;
+    use serde_json::Value;
+    use std::path::Path;
+
+    fn file(folder: &str, name: &str, size: u64, allocated: u64, links: u64) -> FileNode {
+        FileNode {
+            path: format!("{}/{}", folder, name),
+            name: name.to_string(),
+            size,
+            allocated,
+            links,
+            inode: (links > 1).then_some(42),
+            modified: 1_700_000_000,
+            media: None,
+        }
+    }
+
+    /// `/films` : un hardlink compté dans `a`, son autre chemin dans `b, c` (nom à virgule).
+    fn tree() -> FolderNode {
+        let mut a = FolderNode::new(Path::new("/films/a"));
+        a.files.push(file("/films/a", "Alien.mkv", 1000, 4096, 2));
+        let mut b = FolderNode::new(Path::new("/films/b, c"));
+        let mut linked = file("/films/b, c", "Alien \"director's cut\".mkv", 1000, 0, 2);
+        linked.media = Some(MediaInfo { video_codec: Some("HEVC".to_string()), width: Some(3840), height: Some(2160), audio_languages: vec!["fre".to_string(), "eng".to_string()], ..MediaInfo::default() });
+        b.files.push(linked);
+        let mut root = FolderNode::new(Path::new("/films"));
+        root.files.push(file("/films", "notes.txt", 10, 4096, 1));
+        root.subfolders = vec![a, b];
+        root
+    }
+
+    #[test]
+    fn ncdu_export_has_the_expected_shape() {
+        let mut output = Vec::new();
+        write_ncdu(&tree(), &mut output).unwrap();
+        let export: Value = serde_json::from_slice(&output).unwrap();
+
+        let export = export.as_array().unwrap();
+        assert_eq!((export[0].as_u64(), export[1].as_u64()), (Some(1), Some(2)));
+        assert_eq!(export[2]["progname"], "size_check");
+        let root = export[3].as_array().unwrap();
+        assert_eq!(root[0]["name"], "/films");
+        assert_eq!(root[1]["name"], "notes.txt");
+        assert_eq!(root[1]["dsize"], 4096);
+        assert!(root[1].get("ino").is_none());
+
+        // Sous-dossiers : tableaux nommés par leur seul nom, chaque lien avec la taille de l'inode
+        for (index, name) in [(2, "a"), (3, "b, c")] {
+            let folder = root[index].as_array().unwrap();
+            assert_eq!(folder[0]["name"], name);
+            assert_eq!(folder[1]["dsize"], 4096);
+            assert_eq!((&folder[1]["ino"], &folder[1]["nlink"], &folder[1]["hlnkc"]), (&Value::from(42), &Value::from(2), &Value::from(true)));
+            assert_eq!(folder[1]["mtime"], 1_700_000_000);
+        }
+    }
+
+    /// Découpe une ligne CSV en champs, guillemets doublés compris.
+    fn csv_fields(line: &str) -> Vec<String> {
+        let (mut fields, mut field, mut quoted) = (Vec::new(), String::new(), false);
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => quoted = !quoted,
+                ',' if !quoted => fields.push(std::mem::take(&mut field)),
+                _ => field.push(c),
+            }
+        }
+        fields.push(field);
+        fields
+    }
+
+    #[test]
+    fn csv_export_quotes_fields_and_keeps_column_count() {
+        let mut output = Vec::new();
+        write_csv(&tree(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        let columns = CSV_HEADER.split(',').count();
+        let rows: Vec<Vec<String>> = output.lines().map(csv_fields).collect();
+        assert!(rows.iter().all(|row| row.len() == columns), "{}", output);
+        assert_eq!(rows.len(), 7);
+
+        assert_eq!(rows[5][..2], ["folder", "/films/b, c"]);
+        let linked = &rows[6];
+        assert_eq!(linked[1], "/films/b, c/Alien \"director's cut\".mkv");
+        assert_eq!(linked[2..5], ["1000", "0", "2"]);
+        assert_eq!(linked[6..9], ["HEVC", "3840", "2160"]);
+        assert_eq!(linked[12], "fre eng");
+        assert!(output.contains("\"/films/b, c/Alien \"\"director's cut\"\".mkv\""));
+    }
+
+    #[test]
+    fn html_export_escapes_names() {
+        let mut output = Vec::new();
+        write_html(&tree(), &[], &Summary::default(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Alien &quot;director&#39;s cut&quot;.mkv"));
+        assert!(!output.contains("director's"));
+        assert_eq!(output.matches("<details").count(), 3);
+    }
+}
@@ -0,0 +1,215 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use regex::{Regex, RegexBuilder};
+use crate::{FileNode, FolderNode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Size,
+    Name,
+    Modified,
+    Count,
+    /// Octets par minute de film, d'après l'en-tête lu avec `--media`
+    BytesPerMinute,
+}
+
+impl SortKey {
+    pub const ALL: [SortKey; 5] = [SortKey::Size, SortKey::Name, SortKey::Modified, SortKey::Count, SortKey::BytesPerMinute];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SortKey::Size => "taille",
+            SortKey::Name => "nom",
+            SortKey::Modified => "date",
+            SortKey::Count => "nb de fichiers",
+            SortKey::BytesPerMinute => "Mo par minute",
+        }
+    }
+
+    /// Sens proposé en choisissant la clé : alphabétique pour le nom, le plus gros ou le plus récent d'abord sinon.
+    pub fn default_descending(&self) -> bool {
+        *self != SortKey::Name
+    }
+}
+
+fn compare_names(a: &str, b: &str) -> Ordering {
+    a.to_lowercase().cmp(&b.to_lowercase()).then_with(|| a.cmp(b))
+}
+
+/// Les fichiers sans durée connue passent après tous les autres en ordre décroissant.
+fn compare_rates(a: Option<f64>, b: Option<f64>) -> Ordering {
+    a.unwrap_or(-1.0).total_cmp(&b.unwrap_or(-1.0))
+}
+
+fn compare_files(a: &FileNode, b: &FileNode, key: SortKey) -> Ordering {
+    let rate = |file: &FileNode| file.media.as_ref().and_then(|media| media.bytes_per_minute(file.size));
+    match key {
+        SortKey::Name => compare_names(&a.name, &b.name),
+        SortKey::Modified => a.modified.cmp(&b.modified),
+        SortKey::BytesPerMinute => compare_rates(rate(a), rate(b)),
+        // Un fichier compte pour un : à nombre égal, on retombe sur la taille
+        SortKey::Size | SortKey::Count => a.size.cmp(&b.size),
+    }
+}
+
+/// Ce qu'il faut connaître d'un sous-arbre pour le trier, calculé en remontant.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TreeStats {
+    pub files: usize,
+    /// Taille et durée cumulées des vidéos dont la durée est connue
+    media_bytes: u64,
+    media_seconds: f64,
+}
+
+impl TreeStats {
+    fn bytes_per_minute(&self) -> Option<f64> {
+        (self.media_seconds > 0.0).then(|| self.media_bytes as f64 * 60.0 / self.media_seconds)
+    }
+}
+
+/// Trie les fichiers et les sous-dossiers de tout l'arbre, sans rescanner. Les statistiques de
+/// `folder` sont calculées au passage pour les tris par nombre de fichiers et par Mo par minute.
+pub fn sort_tree(folder: &mut FolderNode, key: SortKey, descending: bool) -> TreeStats {
+    let directed = |ordering: Ordering| if descending { ordering.reverse() } else { ordering };
+
+    folder.files.sort_by(|a, b| directed(compare_files(a, b, key)));
+
+    let mut subfolders: Vec<(TreeStats, FolderNode)> = std::mem::take(&mut folder.subfolders)
+        .into_iter()
+        .map(|mut sub| (sort_tree(&mut sub, key, descending), sub))
+        .collect();
+    subfolders.sort_by(|(stats_a, a), (stats_b, b)| {
+        directed(match key {
+            SortKey::Size => a.size.cmp(&b.size),
+            SortKey::Name => compare_names(&a.name, &b.name),
+            SortKey::Modified => a.modified.cmp(&b.modified),
+            SortKey::Count => stats_a.files.cmp(&stats_b.files),
+            SortKey::BytesPerMinute => compare_rates(stats_a.bytes_per_minute(), stats_b.bytes_per_minute()),
+        })
+    });
+
+    let mut stats = TreeStats { files: folder.files.len(), ..TreeStats::default() };
+    for file in &folder.files {
+        if let Some(duration) = file.media.as_ref().and_then(|media| media.duration) {
+            stats.media_bytes += file.size;
+            stats.media_seconds += duration;
+        }
+    }
+    for (sub_stats, _) in &subfolders {
+        stats.files += sub_stats.files;
+        stats.media_bytes += sub_stats.media_bytes;
+        stats.media_seconds += sub_stats.media_seconds;
+    }
+    folder.subfolders = subfolders.into_iter().map(|(_, sub)| sub).collect();
+    stats
+}
+
+/// Recherche et filtres de la barre d'outils de l'arbre.
+#[derive(Debug, Clone, Default)]
+pub struct TreeFilter {
+    pub query: String,
+    pub regex: bool,
+    /// Taille minimale en Mo des fichiers et dossiers affichés, 0 pour tout afficher
+    pub min_size_mb: f64,
+    /// Extensions à garder, séparées par des virgules ou des espaces, avec ou sans le point
+    pub extensions: String,
+}
+
+/// Ce que le filtre laisse voir : les chemins affichés, et les dossiers à déplier pour montrer les résultats.
+#[derive(Debug, Default)]
+pub struct Visible {
+    pub paths: HashSet<String>,
+    pub expand: HashSet<String>,
+    pub files: usize,
+}
+
+enum Matcher {
+    None,
+    Text(String),
+    Regex(Regex),
+}
+
+impl Matcher {
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            Matcher::None => true,
+            Matcher::Text(text) => name.to_lowercase().contains(text),
+            Matcher::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+struct Compiled {
+    matcher: Matcher,
+    min_size: u64,
+    extensions: Vec<String>,
+}
+
+impl Compiled {
+    fn keeps_file(&self, file: &FileNode) -> bool {
+        file.size >= self.min_size
+            && (self.extensions.is_empty()
+                || file.name.rsplit_once('.').is_some_and(|(_, ext)| self.extensions.contains(&ext.to_lowercase())))
+    }
+
+    /// Remplit `visible` et renvoie `true` si quelque chose de `folder` doit rester affiché.
+    /// Un dossier dont le nom correspond à la recherche montre tout son contenu filtré.
+    fn visit(&self, folder: &FolderNode, parent_matches: bool, visible: &mut Visible) -> bool {
+        let searching = !matches!(self.matcher, Matcher::None);
+        let folder_matches = parent_matches || (searching && self.matcher.matches(&folder.name));
+        let mut shown = false;
+
+        for file in &folder.files {
+            if self.keeps_file(file) && (folder_matches || self.matcher.matches(&file.name)) {
+                visible.paths.insert(file.path.clone());
+                visible.files += 1;
+                shown = true;
+            }
+        }
+        for sub in &folder.subfolders {
+            if sub.size >= self.min_size && self.visit(sub, folder_matches, visible) {
+                visible.paths.insert(sub.path.clone());
+                shown = true;
+            }
+        }
+
+        if shown && searching {
+            visible.expand.insert(folder.path.clone());
+        }
+        shown || (folder_matches && self.extensions.is_empty())
+    }
+}
+
+impl TreeFilter {
+    pub fn is_active(&self) -> bool {
+        !self.query.is_empty() || self.min_size_mb > 0.0 || !self.extensions.trim().is_empty()
+    }
+
+    /// `None` sans filtre actif, une erreur si la regex ne compile pas.
+    pub fn apply(&self, root: &FolderNode) -> Result<Option<Visible>, String> {
+        if !self.is_active() {
+            return Ok(None);
+        }
+
+        let matcher = match (self.query.is_empty(), self.regex) {
+            (true, _) => Matcher::None,
+            (false, false) => Matcher::Text(self.query.to_lowercase()),
+            (false, true) => Matcher::Regex(RegexBuilder::new(&self.query).case_insensitive(true).build().map_err(|e| e.to_string())?),
+        };
+        let compiled = Compiled {
+            matcher,
+            min_size: (self.min_size_mb * 1_000_000.0) as u64,
+            extensions: self
+                .extensions
+                .split([',', ' '])
+                .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+                .filter(|ext| !ext.is_empty())
+                .collect(),
+        };
+
+        let mut visible = Visible::default();
+        compiled.visit(root, false, &mut visible);
+        visible.paths.insert(root.path.clone());
+        Ok(Some(visible))
+    }
+}
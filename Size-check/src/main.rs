@@ -1,10 +1,35 @@
+mod charts;
+mod diff;
+mod duplicates;
+mod export;
+mod filter;
+mod media;
+mod scan;
+mod trash;
+mod volume;
+
 use std::{fs::{self, File}, io::{self, Write}, path::{Path, PathBuf}};
-use std::collections::HashSet;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
 use clap::{Parser, ArgAction};
 use eframe::egui;
 use eframe::egui::{Id, StrokeKind};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use indicatif::{ProgressBar, ProgressStyle};
+use charts::{ChartEvent, ColorBy};
+use diff::{Change, ScanDiff};
+use duplicates::DuplicateGroup;
+use export::OutputType;
+use filter::{SortKey, TreeFilter, Visible};
+use media::MediaInfo;
+use scan::{Received, Scan};
+use trash::TrashedItem;
+use volume::{VolumeReport, VolumeSample};
 
 #[derive(Parser, Debug)]
 #[command(author = "CatAnnaDev", version, about = "Directory Analyzer with Sorting", long_about = None)]
@@ -24,8 +49,8 @@ struct ClapArgs {
     #[arg(short = 'i', long, help = "include all files or just movies, true = all files", action = ArgAction::SetTrue)]
     include_all: bool,
 
-    #[arg(short = 't', long, help = "Output file type for the result, txt or json", default_value = "txt")]
-    output_type: String,
+    #[arg(short = 't', long, value_enum, help = "Output file type: txt tree, json (reloadable), ncdu (for ncdu -f), csv flat listing or self-contained html report", default_value_t = OutputType::Txt)]
+    output_type: OutputType,
 
     #[arg(long, help = "Simulate the run without writing any file", action = ArgAction::SetTrue)]
     dry_run: bool,
@@ -33,6 +58,21 @@ struct ClapArgs {
     #[arg(long, help = "Show result in ui", action = ArgAction::SetTrue)]
     ui: bool,
 
+    #[arg(long, help = "Find duplicate files (same size, then same partial and full content hash)", action = ArgAction::SetTrue)]
+    duplicates: bool,
+
+    #[arg(long, value_name = "FILE", help = "Open a saved JSON scan in the ui instead of scanning", conflicts_with = "diff")]
+    load: Option<PathBuf>,
+
+    #[arg(long, num_args = 2, value_names = ["OLD", "NEW"], help = "Compare two saved JSON scans: added, removed, grown and shrunk files and folders")]
+    diff: Vec<PathBuf>,
+
+    #[arg(long, help = "Read codec, resolution, HDR, duration, bitrate and audio languages from mkv/mp4/avi headers", action = ArgAction::SetTrue)]
+    media: bool,
+
+    #[arg(long, help = "Scan threads, 0 = one per CPU", default_value_t = 0)]
+    threads: usize,
+
 }
 
 #[derive(Default, Debug)]
@@ -40,122 +80,250 @@ struct Summary {
     total_files: u64,
     total_folders: u64,
     total_size: u64,
+    /// Blocs réellement alloués, chaque inode compté une seule fois
+    total_allocated: u64,
+    hardlinked_files: u64,
+    /// Taille apparente des chemins dont l'inode était déjà compté
+    hardlink_savings: u64,
+    /// Fichiers à plusieurs liens déjà rencontrés, par `(dev, inode)`
+    hardlinks: HashMap<(u64, u64), HardlinkCharge>,
+    duplicate_groups: u64,
+    duplicate_reclaimable: u64,
+    /// Remplissage du volume de la racine, et sa tendance d'après les analyses précédentes
+    volume: Option<VolumeReport>,
+}
+
+/// Chemins d'un inode à plusieurs liens : celui qui porte `allocated` pendant le parcours (le
+/// premier lu, qui dépend des threads) et le plus petit, qui le porte une fois l'analyse finie.
+#[derive(Debug)]
+struct HardlinkCharge {
+    charged: String,
+    smallest: String,
+    allocated: u64,
+}
+
+impl Summary {
+    fn print(&self) {
+        println!("Summary:\n- Total folders: {}\n- Total files: {}\n- Total size: {}\n- Allocated on disk: {}",
+                 self.total_folders,
+                 self.total_files,
+                 format_size(self.total_size, 2, SizeUnit::Decimal, None),
+                 format_size(self.total_allocated, 2, SizeUnit::Decimal, None)
+        );
+        if self.duplicate_groups > 0 {
+            println!("- Duplicate groups: {} ({} reclaimable)",
+                     self.duplicate_groups,
+                     format_size(self.duplicate_reclaimable, 2, SizeUnit::Decimal, None)
+            );
+        }
+        if self.hardlinked_files > 0 {
+            println!("- Hardlinked files: {} ({} counted once)",
+                     self.hardlinked_files,
+                     format_size(self.hardlink_savings, 2, SizeUnit::Decimal, None)
+            );
+        }
+        if let Some(volume) = &self.volume {
+            let sample = &volume.sample;
+            println!("- Volume {}: {} used of {} ({:.1}%), {} free",
+                     sample.mount,
+                     format_size(sample.used, 2, SizeUnit::Decimal, None),
+                     format_size(sample.total, 2, SizeUnit::Decimal, None),
+                     sample.fill_percent(),
+                     format_size(sample.available, 2, SizeUnit::Decimal, None)
+            );
+            match (volume.growth, volume.days_until_full()) {
+                (Some(growth), Some(days)) => println!("- Growth: {}/day over {:.0} days, full in ~{:.0} days",
+                                                       diff::format_delta(growth.bytes_per_day as i64), growth.over_days, days),
+                (Some(growth), None) => println!("- Growth: {}/day over {:.0} days, not filling up",
+                                                 diff::format_delta(growth.bytes_per_day as i64), growth.over_days),
+                (None, _) => println!("- Growth: not enough history yet (needs scans at least a day apart)"),
+            }
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Clone)]
+// Les valeurs par défaut permettent de relire les JSON des versions précédentes.
+#[derive(Debug, Serialize, Deserialize, Clone)]
 struct FileNode {
+    #[serde(default)]
     path: String,
     name: String,
+    /// Taille apparente (`metadata.len()`)
     size: u64,
+    /// Place sur le disque (`st_blocks * 512`), 0 si l'inode est déjà compté par un autre lien
+    #[serde(default)]
+    allocated: u64,
+    /// Nombre de liens physiques, plus de 1 pour un hardlink
+    #[serde(default = "default_links")]
+    links: u64,
+    /// Numéro d'inode d'un hardlink, que l'export ncdu utilise pour le compter une seule fois
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    inode: Option<u64>,
+    /// Date de modification, en secondes depuis l'epoch Unix
+    #[serde(default)]
+    modified: u64,
+    /// Avec `--media`, pour les vidéos dont l'en-tête a pu être lu
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    media: Option<MediaInfo>,
 }
 
-#[derive(Debug, Serialize, Clone)]
+fn default_links() -> u64 {
+    1
+}
+
+impl FileNode {
+    fn is_hardlink(&self) -> bool {
+        self.links > 1
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 struct FolderNode {
+    #[serde(default)]
     path: String,
     name: String,
     size: u64,
+    #[serde(default)]
+    allocated: u64,
+    /// Modification la plus récente d'un fichier du dossier ou de ses sous-dossiers
+    #[serde(default)]
+    modified: u64,
     files: Vec<FileNode>,
     subfolders: Vec<FolderNode>,
 }
 
-fn count_entries(dir: &Path, include_all: bool) -> u64 {
-    let mut count = 0;
-
-    if let Ok(entries) = fs::read_dir(dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-
-            let is_trickplay = path.file_name()
-                .map(|n| n.to_string_lossy().contains(".trickplay"))
-                .unwrap_or(false);
-
-            if !include_all && is_trickplay {
-                continue;
-            }
-
-            count += 1;
-            if path.is_dir() {
-                count += count_entries(&path, include_all);
-            }
+impl FolderNode {
+    /// Dossier encore vide, nommé d'après `dir` (le chemin complet pour une racine sans nom).
+    fn new(dir: &Path) -> Self {
+        FolderNode {
+            path: dir.display().to_string(),
+            name: dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| dir.display().to_string()),
+            size: 0,
+            allocated: 0,
+            modified: 0,
+            files: vec![],
+            subfolders: vec![],
         }
     }
-
-    count
 }
 
-fn visit_dirs(
-    dir: &Path,
-    debug: bool,
-    include_all: bool,
-    summary: &mut Summary,
-    pb: &ProgressBar,
-) -> io::Result<FolderNode> {
-    summary.total_folders += 1;
-    let mut folder = FolderNode {
-        path: dir.display().to_string(),
-        name: dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| dir.display().to_string()),
-        size: 0,
-        files: vec![],
-        subfolders: vec![],
-    };
+/// Contenu du JSON : l'arborescence, plus les doublons avec `--duplicates`.
+#[derive(Debug, Serialize)]
+struct ScanOutput<'a> {
+    #[serde(flatten)]
+    root: &'a FolderNode,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    duplicates: &'a [DuplicateGroup],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    volume: Option<&'a VolumeSample>,
+}
 
-    for entry in fs::read_dir(dir)? {
-        let entry = entry?;
-        let path = entry.path();
+/// Un JSON écrit par `--output-type json`, relu par `--load` et `--diff`.
+#[derive(Debug, Deserialize)]
+struct SavedScan {
+    #[serde(flatten)]
+    root: FolderNode,
+    #[serde(default)]
+    duplicates: Vec<DuplicateGroup>,
+    /// Remplissage du volume au moment de l'analyse
+    #[serde(default)]
+    volume: Option<VolumeSample>,
+}
 
-        if path.is_dir() {
-            let name = path.file_name().unwrap_or_default().to_string_lossy();
+impl SavedScan {
+    fn load(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut scan: SavedScan = serde_json::from_reader(io::BufReader::new(file)).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let root = if scan.root.path.is_empty() { PathBuf::from(&scan.root.name) } else { PathBuf::from(&scan.root.path) };
+        fill_missing_paths(&mut scan.root, &root);
+        Ok(scan)
+    }
+}
 
-            if name.starts_with('.') {
-                continue;
-            }
+/// Les anciens JSON n'ont pas de `path` : on le reconstruit avec les noms, la sélection en a besoin.
+fn fill_missing_paths(folder: &mut FolderNode, path: &Path) {
+    if folder.path.is_empty() {
+        folder.path = path.to_string_lossy().into_owned();
+    }
+    for file in &mut folder.files {
+        if file.path.is_empty() {
+            file.path = path.join(&file.name).to_string_lossy().into_owned();
+        }
+    }
+    for subfolder in &mut folder.subfolders {
+        let sub_path = path.join(&subfolder.name);
+        fill_missing_paths(subfolder, &sub_path);
+    }
+}
 
-            if !include_all && name.contains(".trickplay") {
-                continue;
-            }
+/// Identifiant `(dev, inode)`, nombre de liens et place réellement allouée.
+#[cfg(unix)]
+fn disk_usage(metadata: &fs::Metadata) -> (Option<(u64, u64)>, u64, u64) {
+    (Some((metadata.dev(), metadata.ino())), metadata.nlink(), metadata.blocks() * 512)
+}
 
-            pb.inc(1);
+#[cfg(not(unix))]
+fn disk_usage(metadata: &fs::Metadata) -> (Option<(u64, u64)>, u64, u64) {
+    (None, 1, metadata.len())
+}
 
-            match visit_dirs(&path, debug, include_all, summary, pb) {
-                Ok(subfolder) => {
-                    folder.path = path.display().to_string();
-                    folder.size += subfolder.size;
-                    folder.subfolders.push(subfolder);
-                }
-                Err(e) => {
-                    if debug {
-                        eprintln!("Failed to read directory {}: {}", path.display(), e);
+fn file_node(path: &Path, name: String, metadata: &fs::Metadata, summary: &mut Summary) -> FileNode {
+    let size = metadata.len();
+    let (inode, links, mut allocated) = disk_usage(metadata);
+    let path = path.display().to_string();
+    // Comme du : un inode à plusieurs liens n'est compté qu'une fois, d'abord au premier chemin
+    // rencontré, puis au plus petit quand `Scan` a tout reçu
+    if links > 1 {
+        summary.hardlinked_files += 1;
+        if let Some(inode) = inode {
+            match summary.hardlinks.entry(inode) {
+                // Même chemin relu après une restauration : il garde sa charge
+                Entry::Occupied(charge) if charge.get().charged == path => {}
+                Entry::Occupied(mut charge) => {
+                    allocated = 0;
+                    summary.hardlink_savings += size;
+                    if path < charge.get().smallest {
+                        charge.get_mut().smallest = path.clone();
                     }
                 }
-            }
-        } else {
-            let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
-            if file_name == ".DS_Store" {
-                continue;
-            }
-
-            if !include_all && !file_name.ends_with(".mp4") && !file_name.ends_with(".mkv") && !file_name.ends_with(".avi") {
-                continue;
-            }
-
-            match path.metadata() {
-                Ok(metadata) => {
-                    let size = metadata.len();
-                    folder.size += size;
-                    folder.files.push(FileNode { path: path.display().to_string(), name: file_name, size });
-                    summary.total_files += 1;
-                    summary.total_size += size;
-                }
-                Err(e) => {
-                    if debug {
-                        eprintln!("Failed to get metadata for {}: {}", path.display(), e);
-                    }
+                Entry::Vacant(charge) => {
+                    charge.insert(HardlinkCharge { charged: path.clone(), smallest: path.clone(), allocated });
                 }
             }
         }
     }
+    summary.total_files += 1;
+    summary.total_size += size;
+    summary.total_allocated += allocated;
+    let modified = metadata.modified().ok().and_then(|time| time.duration_since(UNIX_EPOCH).ok()).map_or(0, |age| age.as_secs());
+    let inode = inode.filter(|_| links > 1).map(|(_, ino)| ino);
+    FileNode { path, name, size, allocated, links, inode, modified, media: None }
+}
+
+/// Analyse `base_path` en un seul parcours ; la barre suit les dossiers trouvés jusqu'ici.
+fn scan_with_progress(base_path: &Path, args: &ClapArgs) -> io::Result<(FolderNode, Summary)> {
+    let mut scan = Scan::start(base_path, args.include_all, args.media, args.debug, args.threads);
+    let mut folder_structure = FolderNode::new(base_path);
+
+    let pb = ProgressBar::new(1);
+    pb.set_style(ProgressStyle::default_bar()
+        .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} folders, {msg}")
+        .unwrap()
+        .progress_chars("#>-"));
 
-    Ok(folder)
+    while scan.receive(&mut folder_structure, Duration::from_millis(100)).is_running() {
+        pb.set_length(scan.discovered);
+        pb.set_position(scan.scanned);
+        pb.set_message(format_size(folder_structure.size, 2, SizeUnit::Decimal, None));
+    }
+    if let Some(e) = scan.take_error() {
+        pb.abandon();
+        return Err(e);
+    }
+    pb.set_length(scan.discovered);
+    pb.set_position(scan.scanned);
+    pb.finish_with_message("Analyse terminée.");
+    Ok((folder_structure, scan.summary()))
 }
 
 pub enum SizeUnit {
@@ -188,10 +356,10 @@ pub fn format_size(size_in_bytes: u64, decimals: usize, unit_type: SizeUnit, for
 fn sort_folder(folder: &mut FolderNode, sort_by: &str) {
     match sort_by {
         "folder" => {
-            folder.subfolders.sort_by(|a, b| b.size.cmp(&a.size));
+            folder.subfolders.sort_by_key(|f| std::cmp::Reverse(f.size));
         }
         _ => {
-            folder.files.sort_by(|a, b| b.size.cmp(&a.size));
+            folder.files.sort_by_key(|f| std::cmp::Reverse(f.size));
         }
     }
 
@@ -202,77 +370,140 @@ fn sort_folder(folder: &mut FolderNode, sort_by: &str) {
 
 fn write_tree(folder: &FolderNode, output: &mut File, indent: usize) -> io::Result<()> {
     let prefix = "│   ".repeat(indent);
-    writeln!(output, "{}├── {} ({})", prefix, folder.name, format_size(folder.size, 2, SizeUnit::Decimal, None))?;
+    writeln!(
+        output,
+        "{}├── {} ({}, {} on disk)",
+        prefix,
+        folder.name,
+        format_size(folder.size, 2, SizeUnit::Decimal, None),
+        format_size(folder.allocated, 2, SizeUnit::Decimal, None)
+    )?;
 
     for file in &folder.files {
         writeln!(
             output,
-            "{}│   ├── {} ({})",
+            "{}│   ├── {} ({}){}{}",
             prefix,
             file.name,
-            format_size(file.size, 2, SizeUnit::Decimal, None)
+            format_size(file.size, 2, SizeUnit::Decimal, None),
+            hardlink_mark(file),
+            file.media.as_ref().map(|media| format!(" · {}", media.describe())).unwrap_or_default()
         )?;
     }
 
-    for (_, subfolder) in folder.subfolders.iter().enumerate() {
+    for subfolder in &folder.subfolders {
         write_tree(subfolder, output, indent + 1)?;
     }
     Ok(())
 }
 
-fn main() {
-    let args = ClapArgs::parse();
+fn write_duplicates(duplicates: &[DuplicateGroup], output: &mut File) -> io::Result<()> {
+    writeln!(output, "\nDuplicates ({} groups):", duplicates.len())?;
+    for group in duplicates {
+        writeln!(
+            output,
+            "├── {} × {} ({} reclaimable, xxh3 {})",
+            format_size(group.size, 2, SizeUnit::Decimal, None),
+            group.copies.len(),
+            format_size(group.reclaimable(), 2, SizeUnit::Decimal, None),
+            group.hash
+        )?;
+        for (index, copy) in group.copies.iter().enumerate() {
+            let pinned = if group.is_pinned(index) { format!(" (+{} links outside the tree)", group.outside_links[index]) } else { String::new() };
+            writeln!(output, "│   ├── {}{}", copy.join(" 🔗 "), pinned)?;
+        }
+    }
+    Ok(())
+}
 
-    let base_path = PathBuf::from(&args.path);
-    let mut output_path = PathBuf::from(&args.output);
-    let mut summary = Summary::default();
+/// ` 🔗 ×N` pour un hardlink, avec ` (counted elsewhere)` si son inode est compté par un autre chemin.
+fn hardlink_mark(file: &FileNode) -> String {
+    match (file.is_hardlink(), file.allocated) {
+        (false, _) => String::new(),
+        (true, 0) if file.size > 0 => format!(" 🔗 ×{} (counted elsewhere)", file.links),
+        (true, _) => format!(" 🔗 ×{}", file.links),
+    }
+}
 
-    let total = count_entries(&base_path, args.include_all);
-    let pb = ProgressBar::new(total);
-    pb.set_style(ProgressStyle::default_bar()
-        .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({percent}%)")
-        .unwrap()
-        .progress_chars("#>-"));
+fn main() {
+    let args = ClapArgs::parse();
 
+    if let Some(load) = &args.load {
+        match SavedScan::load(load) {
+            Ok(scan) => {
+                let mut app = JsonViewerApp::new(scan.root, scan.duplicates, args.include_all);
+                app.volume = scan.volume.map(VolumeReport::new);
+                show_ui(app);
+            }
+            Err(e) => eprintln!("Error: {}", e),
+        }
+        return;
+    }
+    if let [old, new] = args.diff.as_slice() {
+        run_diff(&args, old, new);
+        return;
+    }
 
-    output_path.set_extension(match args.output_type.as_str() {
-        "json" => "json",
-        _ => "txt",
-    });
+    let base_path = PathBuf::from(&args.path);
+    if args.ui {
+        // L'interface s'ouvre tout de suite et se remplit pendant l'analyse
+        show_ui(JsonViewerApp::scanning(&base_path, &args));
+        return;
+    }
 
+    let mut output_path = PathBuf::from(&args.output);
+    output_path.set_extension(args.output_type.extension());
 
-    match visit_dirs(&base_path, args.debug, args.include_all, &mut summary,  &pb) {
-        Ok(mut folder_structure) => {
-            pb.finish_with_message("Analyse terminée.");
+    match scan_with_progress(&base_path, &args) {
+        Ok((mut folder_structure, mut summary)) => {
             sort_folder(&mut folder_structure, &args.sort);
 
-
-            if args.ui {
-                show_ui(folder_structure);
-                return;
+            let duplicates = if args.duplicates { duplicates::find_duplicates(&folder_structure, args.debug) } else { Vec::new() };
+            summary.duplicate_groups = duplicates.len() as u64;
+            summary.duplicate_reclaimable = duplicates.iter().map(DuplicateGroup::reclaimable).sum();
+            // Un dry-run ne laisse pas de trace, pas même dans l'historique du volume
+            match VolumeReport::measure(&base_path, !args.dry_run) {
+                Ok(volume) => summary.volume = Some(volume),
+                Err(e) if args.debug => eprintln!("Failed to read volume stats: {}", e),
+                Err(_) => {}
             }
 
             if args.dry_run {
                 println!("Dry-run mode: no output file written.");
-                println!("Summary:\n- Total folders: {}\n- Total files: {}\n- Total size: {}",
-                         summary.total_folders,
-                         summary.total_files,
-                         format_size(summary.total_size, 2, SizeUnit::Decimal, None)
-                );
+                summary.print();
                 return;
             }
 
             match File::create(&output_path) {
                 Ok(mut output_file) => {
-                    match args.output_type.as_str() {
-                        "json" => {
-                            match serde_json::to_writer_pretty(&mut output_file, &folder_structure) {
+                    match args.output_type {
+                        OutputType::Json => {
+                            let output = ScanOutput {
+                                root: &folder_structure,
+                                duplicates: &duplicates,
+                                volume: summary.volume.as_ref().map(|volume| &volume.sample),
+                            };
+                            match serde_json::to_writer_pretty(&mut output_file, &output) {
                                 Ok(_) => println!("JSON saved to {}", output_path.display()),
                                 Err(e) => eprintln!("Failed to write JSON: {}", e),
                             }
                         }
-                        _ => {
-                            if let Err(e) = write_tree(&folder_structure, &mut output_file, 0) {
+                        OutputType::Ncdu | OutputType::Csv | OutputType::Html => {
+                            let mut output = io::BufWriter::new(output_file);
+                            let written = match args.output_type {
+                                OutputType::Ncdu => export::write_ncdu(&folder_structure, &mut output),
+                                OutputType::Csv => export::write_csv(&folder_structure, &mut output),
+                                _ => export::write_html(&folder_structure, &duplicates, &summary, &mut output),
+                            };
+                            match written.and_then(|()| output.flush()) {
+                                Ok(()) => println!("Report saved to {}", output_path.display()),
+                                Err(e) => eprintln!("Failed to write to output file: {}", e),
+                            }
+                        }
+                        OutputType::Txt => {
+                            let written = write_tree(&folder_structure, &mut output_file, 0)
+                                .and_then(|()| if duplicates.is_empty() { Ok(()) } else { write_duplicates(&duplicates, &mut output_file) });
+                            if let Err(e) = written {
                                 eprintln!("Failed to write to output file: {}", e);
                             } else {
                                 println!("Analysis saved to {}", output_path.display());
@@ -285,11 +516,7 @@ fn main() {
                 }
             }
 
-            println!("Summary:\n- Total folders: {}\n- Total files: {}\n- Total size: {}",
-                     summary.total_folders,
-                     summary.total_files,
-                     format_size(summary.total_size, 2, SizeUnit::Decimal, None)
-            );
+            summary.print();
         }
         Err(e) => {
             eprintln!("Error: {}", e);
@@ -297,23 +524,347 @@ fn main() {
     }
 }
 
-fn show_ui(json_data: FolderNode) {
+fn run_diff(args: &ClapArgs, old: &Path, new: &Path) {
+    let (old, new) = match (SavedScan::load(old), SavedScan::load(new)) {
+        (Ok(old), Ok(new)) => (old, new),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("Error: {}", e);
+            return;
+        }
+    };
+    let diff = ScanDiff::new(&old.root, &new.root);
+
+    if args.ui {
+        let mut app = JsonViewerApp::new(new.root, new.duplicates, args.include_all);
+        app.changes = diff.changes_by_path();
+        app.diff = Some(diff);
+        show_ui(app);
+        return;
+    }
+    if args.dry_run {
+        println!("Dry-run mode: no output file written.");
+        let _ = diff.write_summary(&mut io::stdout());
+        return;
+    }
+
+    let format = match args.output_type {
+        OutputType::Txt | OutputType::Json => args.output_type,
+        _ => {
+            eprintln!("Error: --diff only writes txt or json");
+            return;
+        }
+    };
+    let mut output_path = PathBuf::from(&args.output);
+    output_path.set_extension(format.extension());
+    let written = File::create(&output_path).and_then(|mut file| match format {
+        OutputType::Json => diff.write_json(&mut file),
+        _ => diff.write_text(&mut file),
+    });
+    match written {
+        Ok(()) => println!("Diff saved to {}", output_path.display()),
+        Err(e) => eprintln!("Failed to write diff: {}", e),
+    }
+    let _ = diff.write_summary(&mut io::stdout());
+}
+
+fn show_ui(app: JsonViewerApp) {
     let _ = eframe::run_native(
         "Résultat Analyse Dossier",
         eframe::NativeOptions::default(),
-        Box::new(|_cc| Ok(Box::new(JsonViewerApp { root_folder: json_data, pending_deletions: Vec::new(), selected_files: HashSet::new(), confirm_deletion: false }))),
+        Box::new(move |_cc| Ok(Box::new(app))),
     );
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum View {
+    Tree,
+    Treemap,
+    Sunburst,
+}
+
+/// Analyse lancée par `--ui`, et ce qu'il reste à faire quand elle se termine.
+#[derive(Debug)]
+struct BackgroundScan {
+    scan: Scan,
+    sort: String,
+    duplicates: bool,
+    debug: bool,
+}
+
+#[derive(Debug)]
 struct JsonViewerApp {
     root_folder: FolderNode,
+    duplicates: Vec<DuplicateGroup>,
     pending_deletions: Vec<String>,
     selected_files: HashSet<String>,
     confirm_deletion: bool,
+    /// Éléments venant de la racine analysée et encore dans la corbeille, les plus récents d'abord
+    trashed: Vec<TrashedItem>,
+    confirm_empty_trash: bool,
+    /// Résultat de la dernière action (corbeille, restauration, erreur)
+    status: Option<String>,
+    include_all: bool,
+    /// Relire l'en-tête des vidéos restaurées, comme l'analyse
+    media: bool,
+    /// Inodes comptés par l'analyse : un hardlink restauré n'est pas compté une seconde fois
+    summary: Summary,
+    view: View,
+    color_by: ColorBy,
+    /// Dossier affiché par la treemap et le sunburst, la racine si `None`
+    focus: Option<String>,
+    /// Avec `--diff` : changement et écart de taille de chaque chemin du nouveau scan
+    changes: HashMap<String, (Change, i64)>,
+    diff: Option<ScanDiff>,
+    /// Analyse en cours : l'arbre se remplit à chaque frame, la suppression attend la fin
+    scan: Option<BackgroundScan>,
+    /// Recherche de doublons lancée en fin d'analyse, dans son propre thread
+    pending_duplicates: Option<Receiver<Vec<DuplicateGroup>>>,
+    filter: TreeFilter,
+    /// Résultat du filtre, `None` quand tout est affiché
+    visible: Option<Visible>,
+    filter_error: Option<String>,
+    /// Déplie une fois les dossiers qui contiennent des résultats, après un changement de recherche
+    expand_matches: bool,
+    /// Tri choisi dans la barre d'outils, `None` pour garder celui de `--sort`
+    sort_key: Option<SortKey>,
+    sort_descending: bool,
+    /// Bandeau du haut : remplissage du volume et projection
+    volume: Option<VolumeReport>,
+}
+
+fn change_color(change: Change) -> egui::Color32 {
+    match change {
+        Change::Added => egui::Color32::from_rgb(110, 200, 110),
+        Change::Removed => egui::Color32::from_rgb(220, 90, 90),
+        Change::Grown => egui::Color32::from_rgb(230, 160, 60),
+        Change::Shrunk => egui::Color32::from_rgb(100, 160, 230),
+    }
+}
+
+fn find_folder<'a>(folder: &'a FolderNode, path: &str) -> Option<&'a FolderNode> {
+    if folder.path == path {
+        return Some(folder);
+    }
+    folder.subfolders.iter().filter(|sub| Path::new(path).starts_with(&sub.path)).find_map(|sub| find_folder(sub, path))
 }
 
 impl JsonViewerApp {
+    fn new(root_folder: FolderNode, duplicates: Vec<DuplicateGroup>, include_all: bool) -> Self {
+        let trashed = trash::list_under(Path::new(&root_folder.path));
+        JsonViewerApp {
+            root_folder,
+            duplicates,
+            pending_deletions: Vec::new(),
+            selected_files: HashSet::new(),
+            confirm_deletion: false,
+            trashed,
+            confirm_empty_trash: false,
+            status: None,
+            include_all,
+            media: false,
+            summary: Summary::default(),
+            view: View::Tree,
+            color_by: ColorBy::Category,
+            focus: None,
+            changes: HashMap::new(),
+            diff: None,
+            scan: None,
+            pending_duplicates: None,
+            filter: TreeFilter::default(),
+            visible: None,
+            filter_error: None,
+            expand_matches: false,
+            sort_key: None,
+            sort_descending: true,
+            volume: None,
+        }
+    }
+
+    fn scanning(root: &Path, args: &ClapArgs) -> Self {
+        let mut app = Self::new(FolderNode::new(root), Vec::new(), args.include_all);
+        app.media = args.media;
+        app.volume = VolumeReport::measure(root, true).ok();
+        app.scan = Some(BackgroundScan {
+            scan: Scan::start(root, args.include_all, args.media, args.debug, args.threads),
+            sort: args.sort.clone(),
+            duplicates: args.duplicates,
+            debug: args.debug,
+        });
+        app
+    }
+
+    /// Range dans l'arbre ce que l'analyse a trouvé depuis la dernière frame.
+    fn receive_scan(&mut self, ctx: &egui::Context) {
+        if let Some(background) = &mut self.scan {
+            let received = background.scan.receive(&mut self.root_folder, Duration::ZERO);
+            if received != Received::Nothing && self.filter.is_active() {
+                self.refresh_filter();
+            }
+            if received.is_running() {
+                ctx.request_repaint_after(Duration::from_millis(100));
+                return;
+            }
+
+            let Some(mut background) = self.scan.take() else { return };
+            if let Some(e) = background.scan.take_error() {
+                self.status = Some(format!("{} : {}", self.root_folder.path, e));
+                return;
+            }
+            match self.sort_key {
+                Some(key) => {
+                    filter::sort_tree(&mut self.root_folder, key, self.sort_descending);
+                }
+                None => sort_folder(&mut self.root_folder, &background.sort),
+            }
+            self.summary = background.scan.summary();
+            if background.scan.is_cancelled() {
+                self.status = Some("Analyse annulée : l'arborescence est incomplète.".to_string());
+                return;
+            }
+
+            self.status = Some(format!("Analyse terminée : {} dossiers, {} fichiers.", self.summary.total_folders, self.summary.total_files));
+            if background.duplicates {
+                let (sender, receiver) = mpsc::channel();
+                let (root, debug) = (self.root_folder.clone(), background.debug);
+                thread::spawn(move || {
+                    let _ = sender.send(duplicates::find_duplicates(&root, debug));
+                });
+                self.pending_duplicates = Some(receiver);
+            }
+        }
+
+        if let Some(receiver) = &self.pending_duplicates {
+            match receiver.try_recv() {
+                Ok(duplicates) => {
+                    self.duplicates = duplicates;
+                    self.pending_duplicates = None;
+                }
+                Err(mpsc::TryRecvError::Empty) => ctx.request_repaint_after(Duration::from_millis(200)),
+                Err(mpsc::TryRecvError::Disconnected) => self.pending_duplicates = None,
+            }
+        }
+    }
+
+    fn refresh_filter(&mut self) {
+        match self.filter.apply(&self.root_folder) {
+            Ok(visible) => {
+                self.visible = visible;
+                self.filter_error = None;
+            }
+            // Regex en cours de saisie : on garde le dernier résultat valide
+            Err(e) => self.filter_error = Some(e),
+        }
+    }
+
+    fn is_visible(&self, path: &str) -> bool {
+        self.visible.as_ref().is_none_or(|visible| visible.paths.contains(path))
+    }
+
+    fn apply_sort(&mut self) {
+        let Some(key) = self.sort_key else { return };
+        // Trier déplacerait les dossiers que l'analyse est en train de remplir
+        if self.scan.is_some() {
+            self.status = Some("Le tri sera appliqué à la fin de l'analyse.".to_string());
+            return;
+        }
+        filter::sort_tree(&mut self.root_folder, key, self.sort_descending);
+    }
+
+    /// Recherche par nom ou regex, filtres de taille et d'extension, tri de l'arbre.
+    fn display_toolbar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("🔍");
+            let search = ui.add(egui::TextEdit::singleline(&mut self.filter.query).hint_text("nom ou regex").desired_width(220.0));
+            let mut changed = search.changed();
+            changed |= ui.checkbox(&mut self.filter.regex, "regex").changed();
+            ui.label("Taille min :");
+            changed |= ui.add(egui::DragValue::new(&mut self.filter.min_size_mb).range(0.0..=f64::MAX).speed(10.0).suffix(" Mo")).changed();
+            ui.label("Extensions :");
+            changed |= ui.add(egui::TextEdit::singleline(&mut self.filter.extensions).hint_text("mkv, mp4").desired_width(100.0)).changed();
+            if self.filter.is_active() && ui.button("✖ Effacer").clicked() {
+                self.filter = TreeFilter::default();
+                changed = true;
+            }
+
+            if changed {
+                self.refresh_filter();
+                self.expand_matches = !self.filter.query.is_empty();
+            }
+            if let Some(error) = &self.filter_error {
+                ui.colored_label(egui::Color32::RED, error.lines().last().unwrap_or_default());
+            } else if let Some(visible) = &self.visible {
+                ui.label(format!("{} fichiers", visible.files));
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Trier par :");
+            let mut changed = false;
+            for key in SortKey::ALL {
+                if ui.selectable_value(&mut self.sort_key, Some(key), key.label()).changed() {
+                    self.sort_descending = key.default_descending();
+                    changed = true;
+                }
+            }
+            if self.sort_key.is_some() {
+                let direction = if self.sort_descending { "⬇ décroissant" } else { "⬆ croissant" };
+                if ui.button(direction).clicked() {
+                    self.sort_descending = !self.sort_descending;
+                    changed = true;
+                }
+            }
+            if changed {
+                self.apply_sort();
+            }
+        });
+    }
+
+    fn display_scan_progress(&self, ui: &mut egui::Ui) {
+        let Some(background) = &self.scan else { return };
+        ui.horizontal(|ui| {
+            ui.spinner();
+            ui.label(format!(
+                "Analyse en cours : {} / {} dossiers trouvés, {}",
+                background.scan.scanned,
+                background.scan.discovered,
+                format_size(self.root_folder.size, 2, SizeUnit::Decimal, None)
+            ));
+            if ui.button("✖ Annuler l'analyse").clicked() {
+                background.scan.cancel();
+            }
+        });
+    }
+
+    /// Libellé coloré et suivi de l'écart de taille si le chemin a changé depuis l'ancien scan.
+    fn change_label(&self, path: &str, text: String) -> egui::RichText {
+        match self.changes.get(path) {
+            Some((change, delta)) => egui::RichText::new(format!("{} [{}]", text, diff::format_delta(*delta))).color(change_color(*change)),
+            None => egui::RichText::new(text),
+        }
+    }
+
+    fn display_diff(&self, ui: &mut egui::Ui) {
+        let Some(diff) = &self.diff else { return };
+        ui.heading("Différences :");
+        ui.label(format!(
+            "{} → {} ({})",
+            format_size(diff.old_total, 2, SizeUnit::Decimal, None),
+            format_size(diff.new_total, 2, SizeUnit::Decimal, None),
+            diff::format_delta(diff.delta())
+        ));
+        for change in Change::ALL {
+            ui.colored_label(change_color(change), format!("{} {} : {}", change.symbol(), change.label(), diff.count(change)));
+        }
+        ui.separator();
+
+        egui::ScrollArea::vertical().id_salt("diff").show(ui, |ui| {
+            for entry in &diff.entries {
+                let text = format!("{} {}  {}", entry.change.symbol(), diff::format_delta(entry.delta), entry.relative);
+                ui.colored_label(change_color(entry.change), text).on_hover_text(&entry.path);
+            }
+        });
+    }
+
     fn apply_deletions(&mut self) {
         self.root_folder.files
             .retain(|f| !self.pending_deletions.contains(&f.path));
@@ -322,6 +873,10 @@ impl JsonViewerApp {
         }
         self.root_folder.subfolders
             .retain(|d| !self.pending_deletions.contains(&d.path));
+        for group in &mut self.duplicates {
+            group.remove_paths(|path| self.pending_deletions.iter().any(|p| Path::new(path).starts_with(p)));
+        }
+        self.duplicates.retain(|group| group.copies.len() > 1);
         self.pending_deletions.clear();
     }
 
@@ -333,6 +888,203 @@ impl JsonViewerApp {
         folder.subfolders.retain(|d| !pending.contains(&d.path));
     }
 
+    /// Chemins sélectionnés sans ancêtre sélectionné : un dossier part avec son contenu, et ses
+    /// descendants (cochés par `select_folder_recursive`) n'ont plus à être déplacés un par un.
+    fn topmost_paths(selected: HashSet<String>) -> Vec<String> {
+        let mut paths: Vec<String> = selected.into_iter().collect();
+        // Tri par composants : chaque dossier précède directement ses descendants
+        paths.sort_by(|a, b| Path::new(a).cmp(Path::new(b)));
+        let mut topmost: Vec<String> = Vec::new();
+        for path in paths {
+            if !topmost.last().is_some_and(|parent| Path::new(&path).starts_with(parent)) {
+                topmost.push(path);
+            }
+        }
+        topmost
+    }
+
+    fn move_selection_to_trash(&mut self) {
+        let mut failed = Vec::new();
+        for path in Self::topmost_paths(std::mem::take(&mut self.selected_files)) {
+            match trash::trash(Path::new(&path)) {
+                Ok(item) => {
+                    self.trashed.insert(0, item);
+                    self.pending_deletions.push(path);
+                }
+                Err(e) => failed.push(format!("{} : {}", path, e)),
+            }
+        }
+        self.status = Some(if failed.is_empty() { "Éléments déplacés dans la corbeille.".to_string() } else { failed.join("\n") });
+    }
+
+    /// Remet l'élément à sa place puis le rescanne pour le réinsérer dans l'arborescence.
+    fn restore(&mut self, index: usize) {
+        let item = self.trashed[index].clone();
+        if let Err(e) = trash::restore(&item) {
+            self.status = Some(format!("{} : {}", item.original.display(), e));
+            return;
+        }
+
+        self.trashed.remove(index);
+        Self::insert_restored(&mut self.root_folder, &item.original, self.include_all, self.media, &mut self.summary);
+        self.status = Some(format!("{} restauré.", item.original.display()));
+        self.apply_sort();
+        self.refresh_filter();
+    }
+
+    fn insert_restored(folder: &mut FolderNode, path: &Path, include_all: bool, media: bool, summary: &mut Summary) -> bool {
+        if path.parent() != Some(Path::new(&folder.path)) {
+            return folder.subfolders.iter_mut().any(|sub| Self::insert_restored(sub, path, include_all, media, summary));
+        }
+
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        if path.is_dir() {
+            if let Ok(subfolder) = scan::scan_blocking(path, include_all, media, summary) {
+                folder.subfolders.push(subfolder);
+            }
+        } else if let Ok(metadata) = path.metadata() {
+            let mut file = file_node(path, name, &metadata, summary);
+            if media {
+                file.media = media::probe(path, file.size);
+            }
+            folder.files.push(file);
+        }
+        true
+    }
+
+    fn empty_trash(&mut self) {
+        let mut reclaimed = 0;
+        let mut failed = Vec::new();
+        let sizes = trash::freed_sizes(&self.trashed);
+        for (item, size) in std::mem::take(&mut self.trashed).into_iter().zip(sizes) {
+            match trash::purge(&item) {
+                Ok(()) => reclaimed += size,
+                Err(e) => {
+                    failed.push(format!("{} : {}", item.original.display(), e));
+                    self.trashed.push(item);
+                }
+            }
+        }
+
+        let mut status = format!("Corbeille vidée : {} libérés.", format_size(reclaimed, 2, SizeUnit::Decimal, None));
+        for failure in failed {
+            status.push('\n');
+            status.push_str(&failure);
+        }
+        self.status = Some(status);
+    }
+
+    /// Place utilisée sur le volume de la racine, et date à laquelle il sera plein au rythme actuel.
+    fn display_volume(&self, ui: &mut egui::Ui) {
+        let Some(volume) = &self.volume else { return };
+        let sample = &volume.sample;
+        let fill = sample.fill_percent();
+        let color = match fill {
+            f if f >= 90.0 => egui::Color32::from_rgb(220, 90, 90),
+            f if f >= 75.0 => egui::Color32::from_rgb(230, 160, 60),
+            _ => egui::Color32::from_rgb(110, 170, 110),
+        };
+
+        ui.horizontal(|ui| {
+            ui.label(format!("💽 {}", sample.mount));
+            ui.add(egui::ProgressBar::new((fill / 100.0) as f32).desired_width(200.0).fill(color).text(format!("{:.1} %", fill)));
+            ui.label(format!(
+                "{} utilisés sur {}, {} libres",
+                format_size(sample.used, 2, SizeUnit::Decimal, None),
+                format_size(sample.total, 2, SizeUnit::Decimal, None),
+                format_size(sample.available, 2, SizeUnit::Decimal, None)
+            ));
+            ui.separator();
+            match (volume.growth, volume.days_until_full()) {
+                (Some(growth), days) => {
+                    ui.label(format!("{} par jour sur {:.0} jours", diff::format_delta(growth.bytes_per_day as i64), growth.over_days));
+                    match days {
+                        Some(days) if days < 30.0 => ui.colored_label(egui::Color32::from_rgb(220, 90, 90), format!("plein dans ~{:.0} jours", days)),
+                        Some(days) => ui.label(format!("plein dans ~{:.0} jours", days)),
+                        None => ui.weak("ne se remplit pas"),
+                    };
+                }
+                (None, _) => {
+                    ui.weak("tendance : pas encore assez d'analyses (au moins un jour d'écart)");
+                }
+            }
+        });
+    }
+
+    fn display_trash(&mut self, ui: &mut egui::Ui) {
+        let total: u64 = self.trashed.iter().map(|item| item.size).sum();
+        ui.horizontal(|ui| {
+            ui.heading(format!("Récemment supprimés ({})", self.trashed.len()));
+            if ui.button(format!("🔥 Vider la corbeille ({})", format_size(total, 2, SizeUnit::Decimal, None))).clicked() {
+                self.confirm_empty_trash = true;
+            }
+        });
+
+        let mut restore = None;
+        egui::ScrollArea::vertical().id_salt("trash").max_height(160.0).show(ui, |ui| {
+            for (index, item) in self.trashed.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.small_button("↩ Annuler").clicked() {
+                        restore = Some(index);
+                    }
+                    ui.label(format!(
+                        "{}  {} ({})",
+                        item.deleted_at.replace('T', " "),
+                        item.original.display(),
+                        format_size(item.size, 2, SizeUnit::Decimal, None)
+                    ));
+                });
+            }
+        });
+        if let Some(index) = restore {
+            self.restore(index);
+        }
+    }
+
+    /// Treemap ou sunburst du dossier zoomé, avec un fil d'Ariane pour remonter.
+    fn display_chart(&mut self, ui: &mut egui::Ui) {
+        let root = &self.root_folder;
+        let folder = self.focus.as_deref().and_then(|path| find_folder(root, path)).unwrap_or(root);
+
+        ui.horizontal(|ui| {
+            let mut crumbs = vec![root];
+            let mut current = root;
+            while current.path != folder.path {
+                let Some(next) = current.subfolders.iter().find(|sub| Path::new(&folder.path).starts_with(&sub.path)) else { break };
+                crumbs.push(next);
+                current = next;
+            }
+            for (index, crumb) in crumbs.iter().enumerate() {
+                if index > 0 {
+                    ui.label("›");
+                }
+                if ui.link(&crumb.name).clicked() {
+                    self.focus = Some(crumb.path.clone());
+                }
+            }
+        });
+        charts::legend(ui, self.color_by);
+
+        let event = match self.view {
+            View::Sunburst => charts::sunburst(ui, folder, self.color_by, &self.selected_files),
+            _ => charts::treemap(ui, folder, self.color_by, &self.selected_files),
+        };
+        match event {
+            // Clic sur le dossier affiché (centre du sunburst) : remonter d'un niveau
+            Some(ChartEvent::ZoomInto(path)) if path == folder.path => {
+                self.focus = Path::new(&path).parent().map(|p| p.display().to_string()).filter(|p| find_folder(root, p).is_some());
+            }
+            Some(ChartEvent::ZoomInto(path)) => self.focus = Some(path),
+            Some(ChartEvent::ToggleSelection(path)) => {
+                let was_selected = self.selected_files.remove(&path);
+                if !was_selected {
+                    self.selected_files.insert(path);
+                }
+            }
+            None => {}
+        }
+    }
+
     fn select_folder_recursive(&mut self, folder: &FolderNode) {
         self.selected_files.insert(folder.path.clone());
         for file in &folder.files {
@@ -366,11 +1118,12 @@ impl JsonViewerApp {
     }
 
     fn display_folder_tree(&mut self, ui: &mut egui::Ui, folder: &mut FolderNode) {
-        let label_text = format!(
-            "{} ({})",
+        let label_text = self.change_label(&folder.path, format!(
+            "{} ({}, {} sur disque)",
             &folder.name,
-            format_size(folder.size, 2, SizeUnit::Decimal, None)
-        );
+            format_size(folder.size, 2, SizeUnit::Decimal, None),
+            format_size(folder.allocated, 2, SizeUnit::Decimal, None)
+        ));
 
         let header_response = ui.horizontal(|ui| {
 
@@ -413,16 +1166,22 @@ impl JsonViewerApp {
             }
 
 
-            let collapsing = ui.collapsing(label_text, |ui| {
+            // Identifiant par chemin : l'état déplié survit aux changements de taille pendant l'analyse
+            let open = (self.expand_matches && self.visible.as_ref().is_some_and(|v| v.expand.contains(&folder.path))).then_some(true);
+            let collapsing = egui::CollapsingHeader::new(label_text).id_salt(&folder.path).open(open).show(ui, |ui| {
                 for file in &folder.files {
+                    if !self.is_visible(&file.path) {
+                        continue;
+                    }
                     let file_selected = self.selected_files.contains(&file.path);
                     let mut file_checked = file_selected;
 
-                    let file_label = format!(
-                        "{} ({})",
+                    let file_label = self.change_label(&file.path, format!(
+                        "{} ({}){}",
                         file.name,
-                        format_size(file.size, 2, SizeUnit::Decimal, None)
-                    );
+                        format_size(file.size, 2, SizeUnit::Decimal, None),
+                        if file.is_hardlink() { " 🔗" } else { "" }
+                    ));
 
                     ui.horizontal(|ui| {
                         if ui.checkbox(&mut file_checked, "").changed() {
@@ -433,7 +1192,17 @@ impl JsonViewerApp {
                             }
                         }
 
-                        let response = ui.add(egui::Label::new(file_label).sense(egui::Sense::click()));
+                        let mut response = ui.add(egui::Label::new(file_label).sense(egui::Sense::click()));
+                        if file.is_hardlink() {
+                            let counted = if file.allocated > 0 || file.size == 0 { "compté ici" } else { "déjà compté par un autre chemin" };
+                            response = response.on_hover_text(format!("Hardlink : {} liens, {}", file.links, counted));
+                        }
+                        if let Some(media) = &file.media {
+                            let per_minute = media.bytes_per_minute(file.size)
+                                .map(|bytes| format!(" · {}/min", format_size(bytes as u64, 1, SizeUnit::Decimal, None)))
+                                .unwrap_or_default();
+                            ui.weak(format!("{}{}", media.describe(), per_minute));
+                        }
 
                         if response.secondary_clicked() {
                             ui.ctx().memory_mut(|mem| {
@@ -451,7 +1220,7 @@ impl JsonViewerApp {
                                     open_file_or_folder(&file.path);
                                     ui.close_menu();
                                 }
-                                if ui.button("Delete file").clicked() {
+                                if ui.button("Move to trash").clicked() {
                                     self.selected_files.insert(file.path.clone());
                                     self.confirm_deletion = true;
                                     ui.close_menu();
@@ -461,15 +1230,11 @@ impl JsonViewerApp {
                     });
                 }
 
-                let mut i = 0;
-                while i < folder.subfolders.len() {
-                    let subfolder = &mut folder.subfolders[i];
-                    self.display_folder_tree(ui, subfolder);
-
-                    if !Path::new(&subfolder.path).exists() {
-                        folder.subfolders.remove(i);
-                    } else {
-                        i += 1;
+                // Les éléments mis à la corbeille sont retirés par `apply_deletions` : pas de stat à chaque frame,
+                // et un scan chargé depuis un JSON reste affiché même si ses chemins n'existent plus
+                for subfolder in &mut folder.subfolders {
+                    if self.is_visible(&subfolder.path) {
+                        self.display_folder_tree(ui, subfolder);
                     }
                 }
             });
@@ -493,7 +1258,7 @@ impl JsonViewerApp {
                     open_file_or_folder(&folder.path);
                     ui.close_menu();
                 }
-                if ui.button("Delete Folder").clicked() {
+                if ui.button("Move to trash").clicked() {
                     self.selected_files.insert(folder.path.clone());
                     self.confirm_deletion = true;
                     ui.close_menu();
@@ -503,22 +1268,128 @@ impl JsonViewerApp {
     }
 }
 
+impl JsonViewerApp {
+    /// Panneau des doublons : cocher les copies à supprimer, ou n'en garder qu'une.
+    fn display_duplicates(&mut self, ui: &mut egui::Ui) {
+        let reclaimable: u64 = self.duplicates.iter().map(DuplicateGroup::reclaimable).sum();
+        ui.heading("Doublons :");
+        ui.label(format!("{} groupes, {} récupérables", self.duplicates.len(), format_size(reclaimable, 2, SizeUnit::Decimal, None)));
+        ui.separator();
+
+        egui::ScrollArea::vertical().id_salt("duplicates").show(ui, |ui| {
+            for group in &self.duplicates {
+                let title = format!("{} × {}", format_size(group.size, 2, SizeUnit::Decimal, None), group.copies.len());
+                egui::CollapsingHeader::new(title).id_salt(&group.hash).default_open(true).show(ui, |ui| {
+                    // Une copie n'est libérée qu'avec tous ses hardlinks : ils sont cochés ensemble
+                    for (index, copy) in group.copies.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            let mut checked = copy.iter().all(|p| self.selected_files.contains(p));
+                            if ui.checkbox(&mut checked, "").changed() {
+                                for path in copy {
+                                    if checked {
+                                        self.selected_files.insert(path.clone());
+                                    } else {
+                                        self.selected_files.remove(path);
+                                    }
+                                }
+                            }
+                            if ui.small_button("Garder").on_hover_text("Sélectionne toutes les autres copies").clicked() {
+                                for (other, paths) in group.copies.iter().enumerate() {
+                                    for path in paths {
+                                        if other == index {
+                                            self.selected_files.remove(path);
+                                        } else {
+                                            self.selected_files.insert(path.clone());
+                                        }
+                                    }
+                                }
+                            }
+                            ui.label(copy.join(" 🔗 "));
+                            if group.is_pinned(index) {
+                                ui.colored_label(egui::Color32::from_rgb(230, 160, 60), format!("📌 +{} liens", group.outside_links[index]))
+                                    .on_hover_text("Liens hors de l'arborescence : supprimer cette copie ne libère rien");
+                            }
+                        });
+                    }
+                });
+            }
+        });
+    }
+}
+
 impl eframe::App for JsonViewerApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.receive_scan(ctx);
+
+        if !self.duplicates.is_empty() {
+            egui::SidePanel::right("duplicates").resizable(true).default_width(420.0).show(ctx, |ui| {
+                self.display_duplicates(ui);
+            });
+        }
+
+        if self.diff.is_some() {
+            egui::SidePanel::left("diff").resizable(true).default_width(360.0).show(ctx, |ui| {
+                self.display_diff(ui);
+            });
+        }
+
+        if self.volume.is_some() {
+            egui::TopBottomPanel::top("volume").show(ctx, |ui| {
+                self.display_volume(ui);
+            });
+        }
+
+        if !self.trashed.is_empty() {
+            egui::TopBottomPanel::bottom("trash").resizable(true).show(ctx, |ui| {
+                self.display_trash(ui);
+            });
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("Arborescence des Dossiers :");
+            self.display_scan_progress(ui);
 
-            if !self.selected_files.is_empty() {
-                if ui.button(format!("🗑 Supprimer les éléments sélectionnés ({})", self.selected_files.len())).clicked() {
-                    self.confirm_deletion = true;
-                }
+            if let Some(status) = &self.status {
+                ui.colored_label(egui::Color32::LIGHT_BLUE, status);
             }
 
-            egui::ScrollArea::vertical().show(ui, |ui| {
-                ui.allocate_space(egui::vec2(ui.available_width(), 0.0));
-                let mut folder = self.root_folder.clone();
-                self.display_folder_tree(ui, &mut folder);
+            ui.label(format!(
+                "Total : {}, {} sur disque (hardlinks comptés une fois)",
+                format_size(self.root_folder.size, 2, SizeUnit::Decimal, None),
+                format_size(self.root_folder.allocated, 2, SizeUnit::Decimal, None)
+            ));
+
+            if !self.selected_files.is_empty()
+                && ui.button(format!("🗑 Supprimer les éléments sélectionnés ({})", self.selected_files.len())).clicked()
+            {
+                self.confirm_deletion = true;
+            }
+
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.view, View::Tree, "🌲 Arbre");
+                ui.selectable_value(&mut self.view, View::Treemap, "▦ Treemap");
+                ui.selectable_value(&mut self.view, View::Sunburst, "◎ Sunburst");
+                if self.view != View::Tree {
+                    ui.separator();
+                    ui.label("Couleur :");
+                    ui.selectable_value(&mut self.color_by, ColorBy::Category, "catégorie");
+                    ui.selectable_value(&mut self.color_by, ColorBy::Extension, "extension");
+                }
             });
+            ui.separator();
+
+            if self.view == View::Tree {
+                self.display_toolbar(ui);
+                ui.separator();
+                egui::ScrollArea::vertical().id_salt("tree").show(ui, |ui| {
+                    ui.allocate_space(egui::vec2(ui.available_width(), 0.0));
+                    let mut folder = self.root_folder.clone();
+                    self.display_folder_tree(ui, &mut folder);
+                });
+                self.expand_matches = false;
+            } else {
+                self.display_chart(ui);
+            }
 
             if self.confirm_deletion {
                 egui::Window::new("Confirmer la suppression")
@@ -526,13 +1397,12 @@ impl eframe::App for JsonViewerApp {
                     .resizable(false)
                     .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
                     .show(ctx, |ui| {
-                        ui.label("Êtes-vous sûr de vouloir supprimer les éléments sélectionnés ?");
-                        if ui.button("Oui, supprimer").clicked() {
-                            for path in &self.selected_files {
-                                delete_file_or_folder(path);
-                                self.pending_deletions.push(path.clone());
-                            }
-                            self.selected_files.clear();
+                        ui.label(format!("Déplacer les {} éléments sélectionnés dans la corbeille ?", self.selected_files.len()));
+                        if self.scan.is_some() {
+                            ui.label("Disponible à la fin de l'analyse.");
+                        }
+                        if ui.add_enabled(self.scan.is_none(), egui::Button::new("Oui, mettre à la corbeille")).clicked() {
+                            self.move_selection_to_trash();
                             self.confirm_deletion = false;
                         }
                         if ui.button("Annuler").clicked() {
@@ -540,6 +1410,23 @@ impl eframe::App for JsonViewerApp {
                         }
                     });
             }
+
+            if self.confirm_empty_trash {
+                egui::Window::new("Vider la corbeille")
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                    .show(ctx, |ui| {
+                        ui.label(format!("Supprimer définitivement les {} éléments récemment supprimés ? Impossible à annuler.", self.trashed.len()));
+                        if ui.button("Oui, vider").clicked() {
+                            self.empty_trash();
+                            self.confirm_empty_trash = false;
+                        }
+                        if ui.button("Annuler").clicked() {
+                            self.confirm_empty_trash = false;
+                        }
+                    });
+            }
         });
 
         self.apply_deletions();
@@ -549,38 +1436,17 @@ use std::process::Command;
 
 fn open_file_or_folder(file_path: &str) {
     #[cfg(target_os = "macos")]
-    {
-        Command::new("open")
-            .arg(file_path)
-            .spawn()
-            .expect("Failed to open the file or folder");
-    }
-
-    #[cfg(target_os = "linux")]
-    {
-        Command::new("xdg-open")
-            .arg(file_path)
-            .spawn()
-            .expect("Failed to open the file or folder");
-    }
-
+    let opener = "open";
     #[cfg(target_os = "windows")]
-    {
-        Command::new("explorer")
-            .arg(file_path)
-            .spawn()
-            .expect("Failed to open the file or folder");
-    }
-}
-
-fn delete_file_or_folder(file_path: &str) {
-    if fs::remove_file(file_path).is_err() {
-        if let Err(e) = fs::remove_dir_all(file_path) {
-            println!("Failed to delete the file or folder: {}", e);
-        }else { 
-            println!("Successfully delete the file or folder");
+    let opener = "explorer";
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let opener = "xdg-open";
+
+    match Command::new(opener).arg(file_path).spawn() {
+        // Attendu à part pour ne pas bloquer l'UI ni laisser de processus zombie
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
         }
-    } else {
-        println!("File deleted: {}", file_path);
+        Err(e) => println!("Failed to open the file or folder: {}", e),
     }
 }
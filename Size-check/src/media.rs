@@ -0,0 +1,699 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+/// Éléments ou boîtes de premier niveau lus au plus avant d'abandonner, pour ne pas parcourir
+/// tout un fichier mal formé.
+const MAX_TOP_LEVEL: usize = 256;
+/// Taille maximale d'un élément d'en-tête chargé en mémoire (`Tracks`, `moov`, `hdrl`)
+const MAX_HEADER: u64 = 64 * 1024 * 1024;
+
+/// Infos lues dans l'en-tête du conteneur, sans décoder le flux.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct MediaInfo {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub video_codec: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    /// Transfert PQ (HDR10) ou HLG, ou Dolby Vision
+    #[serde(default)]
+    pub hdr: bool,
+    /// En secondes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
+    /// Débit global en bits par seconde, taille du fichier sur durée
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bitrate: Option<u64>,
+    /// Codes ISO 639 des pistes audio, dans l'ordre du fichier
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub audio_languages: Vec<String>,
+}
+
+impl MediaInfo {
+    /// Octets par minute de film : ce qui fait ressortir les remux trop lourds pour leur durée.
+    pub fn bytes_per_minute(&self, size: u64) -> Option<f64> {
+        self.duration.filter(|d| *d > 0.0).map(|duration| size as f64 * 60.0 / duration)
+    }
+
+    /// `HEVC 3840×2160 HDR · 2 h 05 · 13.1 Mb/s · fre, eng`
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        let mut video = self.video_codec.clone().unwrap_or_default();
+        if let (Some(width), Some(height)) = (self.width, self.height) {
+            video = format!("{} {}×{}", video, width, height).trim().to_string();
+        }
+        if self.hdr {
+            video.push_str(" HDR");
+        }
+        if !video.is_empty() {
+            parts.push(video);
+        }
+        if let Some(duration) = self.duration {
+            let minutes = (duration / 60.0).round() as u64;
+            parts.push(if minutes >= 60 { format!("{} h {:02}", minutes / 60, minutes % 60) } else { format!("{} min", minutes) });
+        }
+        if let Some(bitrate) = self.bitrate {
+            parts.push(format!("{:.1} Mb/s", bitrate as f64 / 1_000_000.0));
+        }
+        if !self.audio_languages.is_empty() {
+            parts.push(self.audio_languages.join(", "));
+        }
+        parts.join(" · ")
+    }
+}
+
+/// Lit l'en-tête d'un `.mkv`, `.mp4` ou `.avi`. `None` pour un autre format ou un en-tête illisible.
+pub fn probe(path: &Path, size: u64) -> Option<MediaInfo> {
+    let mut file = BufReader::new(File::open(path).ok()?);
+    let mut magic = [0; 12];
+    file.read_exact(&mut magic).ok()?;
+    file.rewind().ok()?;
+
+    let mut info = if magic[..4] == [0x1A, 0x45, 0xDF, 0xA3] {
+        matroska::probe(&mut file)
+    } else if &magic[4..8] == b"ftyp" {
+        mp4::probe(&mut file, size)
+    } else if &magic[..4] == b"RIFF" && &magic[8..12] == b"AVI " {
+        avi::probe(&mut file)
+    } else {
+        return None;
+    }
+    .ok()?;
+
+    info.bitrate = info.duration.filter(|d| *d > 0.0).map(|duration| (size as f64 * 8.0 / duration) as u64);
+    Some(info)
+}
+
+/// HDR d'après la fonction de transfert ISO/IEC 23091-2 : 16 = PQ (SMPTE ST 2084), 18 = HLG.
+fn is_hdr_transfer(transfer: u64) -> bool {
+    transfer == 16 || transfer == 18
+}
+
+fn read_vec(reader: &mut impl Read, len: u64) -> io::Result<Vec<u8>> {
+    if len > MAX_HEADER {
+        return Err(io::Error::other("en-tête trop grand"));
+    }
+    let mut buffer = vec![0; len as usize];
+    reader.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
+
+fn be_uint(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |value, &byte| (value << 8) | byte as u64)
+}
+
+fn le_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    bytes.get(at..at + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn be_u16(bytes: &[u8], at: usize) -> Option<u16> {
+    bytes.get(at..at + 2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn be_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    bytes.get(at..at + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn be_u64(bytes: &[u8], at: usize) -> Option<u64> {
+    bytes.get(at..at + 8).map(be_uint)
+}
+
+/// Matroska / WebM : éléments EBML, identifiant et taille codés en entiers de longueur variable.
+mod matroska {
+    use super::*;
+
+    const SEGMENT: u64 = 0x1853_8067;
+    const SEEK_HEAD: u64 = 0x114D_9B74;
+    const SEEK: u64 = 0x4DBB;
+    const SEEK_ID: u64 = 0x53AB;
+    const SEEK_POSITION: u64 = 0x53AC;
+    const INFO: u64 = 0x1549_A966;
+    const TIMESTAMP_SCALE: u64 = 0x2A_D7B1;
+    const DURATION: u64 = 0x4489;
+    const TRACKS: u64 = 0x1654_AE6B;
+    const TRACK_ENTRY: u64 = 0xAE;
+    const TRACK_TYPE: u64 = 0x83;
+    const CODEC_ID: u64 = 0x86;
+    const LANGUAGE: u64 = 0x22_B59C;
+    const LANGUAGE_BCP47: u64 = 0x22_B59D;
+    const VIDEO: u64 = 0xE0;
+    const PIXEL_WIDTH: u64 = 0xB0;
+    const PIXEL_HEIGHT: u64 = 0xBA;
+    const COLOUR: u64 = 0x55B0;
+    const TRANSFER_CHARACTERISTICS: u64 = 0x55BA;
+    const BLOCK_ADDITION_MAPPING: u64 = 0x41E4;
+    const BLOCK_ADD_ID_TYPE: u64 = 0x41E7;
+    const CLUSTER: u64 = 0x1F43_B675;
+
+    /// Taille « inconnue » (tous les bits à 1), courante pour le `Segment` d'un enregistrement en direct
+    const UNKNOWN_SIZE: u64 = u64::MAX;
+
+    /// Entier de longueur variable : le nombre de zéros en tête du premier octet donne la longueur.
+    /// L'identifiant garde son marqueur, la taille non.
+    fn read_vint(reader: &mut impl Read, keep_marker: bool) -> io::Result<(u64, u64)> {
+        let mut first = [0];
+        reader.read_exact(&mut first)?;
+        let len = first[0].leading_zeros() as usize + 1;
+        if len > 8 {
+            return Err(io::Error::other("vint invalide"));
+        }
+        let mut rest = [0; 7];
+        reader.read_exact(&mut rest[..len - 1])?;
+
+        let marker = 1u64 << (7 * len);
+        let mut value = if keep_marker { first[0] as u64 } else { first[0] as u64 & (0xFF >> len) };
+        for byte in &rest[..len - 1] {
+            value = (value << 8) | *byte as u64;
+        }
+        if !keep_marker && value == marker - 1 {
+            value = UNKNOWN_SIZE;
+        }
+        Ok((value, len as u64))
+    }
+
+    fn read_header(reader: &mut impl Read) -> io::Result<(u64, u64, u64)> {
+        let (id, id_len) = read_vint(reader, true)?;
+        let (size, size_len) = read_vint(reader, false)?;
+        Ok((id, size, id_len + size_len))
+    }
+
+    /// Enfants `(id, contenu)` d'un élément déjà chargé en mémoire.
+    fn children(mut data: &[u8]) -> Vec<(u64, &[u8])> {
+        let mut children = Vec::new();
+        while !data.is_empty() {
+            let mut cursor = data;
+            let Ok((id, size, header)) = read_header(&mut cursor) else { break };
+            let end = header.saturating_add(size).min(data.len() as u64) as usize;
+            children.push((id, &data[header as usize..end]));
+            data = &data[end..];
+        }
+        children
+    }
+
+    fn string(bytes: &[u8]) -> String {
+        String::from_utf8_lossy(bytes).trim_end_matches('\0').to_string()
+    }
+
+    fn float(bytes: &[u8]) -> Option<f64> {
+        match bytes.len() {
+            4 => Some(f32::from_be_bytes(bytes.try_into().ok()?) as f64),
+            8 => Some(f64::from_be_bytes(bytes.try_into().ok()?)),
+            _ => None,
+        }
+    }
+
+    fn codec_name(codec_id: &str) -> String {
+        match codec_id {
+            "V_MPEGH/ISO/HEVC" => "HEVC",
+            "V_MPEG4/ISO/AVC" => "H.264",
+            "V_AV1" => "AV1",
+            "V_VP9" => "VP9",
+            "V_VP8" => "VP8",
+            "V_MPEG4/ISO/ASP" | "V_MPEG4/ISO/SP" => "MPEG-4 ASP",
+            "V_MPEG2" => "MPEG-2",
+            other => other.strip_prefix("V_").unwrap_or(other),
+        }
+        .to_string()
+    }
+
+    fn parse_info(data: &[u8], info: &mut MediaInfo) {
+        let mut scale = 1_000_000;
+        let mut duration = None;
+        for (id, value) in children(data) {
+            match id {
+                TIMESTAMP_SCALE => scale = be_uint(value),
+                DURATION => duration = float(value),
+                _ => {}
+            }
+        }
+        info.duration = duration.map(|d| d * scale as f64 / 1e9).filter(|d| d.is_finite());
+    }
+
+    fn parse_tracks(data: &[u8], info: &mut MediaInfo) {
+        for (_, entry) in children(data).into_iter().filter(|(id, _)| *id == TRACK_ENTRY) {
+            let (mut kind, mut codec, mut language, mut bcp47) = (0, String::new(), None, None);
+            let mut video = None;
+            let mut dolby_vision = false;
+            for (id, value) in children(entry) {
+                match id {
+                    TRACK_TYPE => kind = be_uint(value),
+                    CODEC_ID => codec = string(value),
+                    LANGUAGE => language = Some(string(value)),
+                    LANGUAGE_BCP47 => bcp47 = Some(string(value)),
+                    VIDEO => video = Some(value),
+                    BLOCK_ADDITION_MAPPING => {
+                        dolby_vision |= children(value).iter().any(|(id, v)| *id == BLOCK_ADD_ID_TYPE && matches!(&v[..], b"dvcC" | b"dvvC"));
+                    }
+                    _ => {}
+                }
+            }
+
+            match kind {
+                // Première piste vidéo seulement : les suivantes sont souvent des couvertures
+                1 if info.video_codec.is_none() => {
+                    info.video_codec = Some(codec_name(&codec));
+                    info.hdr |= dolby_vision;
+                    for (id, value) in video.map(children).unwrap_or_default() {
+                        match id {
+                            PIXEL_WIDTH => info.width = Some(be_uint(value) as u32),
+                            PIXEL_HEIGHT => info.height = Some(be_uint(value) as u32),
+                            COLOUR => {
+                                info.hdr |= children(value).iter().any(|(id, v)| *id == TRANSFER_CHARACTERISTICS && is_hdr_transfer(be_uint(v)));
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                // Langue absente : « eng » par défaut selon la spécification
+                2 => info.audio_languages.push(bcp47.or(language).unwrap_or_else(|| "eng".to_string())),
+                _ => {}
+            }
+        }
+    }
+
+    pub fn probe<R: Read + Seek>(reader: &mut R) -> io::Result<MediaInfo> {
+        let (_, ebml_size, _) = read_header(reader)?;
+        // Une taille inconnue n'a pas de sens pour l'en-tête EBML
+        reader.seek(SeekFrom::Current(i64::try_from(ebml_size).map_err(|_| io::Error::other("en-tête EBML sans taille"))?))?;
+        let (id, _, _) = read_header(reader)?;
+        if id != SEGMENT {
+            return Err(io::Error::other("pas de Segment"));
+        }
+        let segment_start = reader.stream_position()?;
+
+        let mut info = MediaInfo::default();
+        let (mut found_info, mut found_tracks) = (false, false);
+        let mut seek_positions = Vec::new();
+        let mut visited = Vec::new();
+        let mut jumped = false;
+
+        for _ in 0..MAX_TOP_LEVEL {
+            if found_info && found_tracks {
+                break;
+            }
+            match read_header(reader) {
+                Ok((INFO, size, _)) => {
+                    parse_info(&read_vec(reader, size)?, &mut info);
+                    found_info = true;
+                }
+                Ok((TRACKS, size, _)) => {
+                    parse_tracks(&read_vec(reader, size)?, &mut info);
+                    found_tracks = true;
+                }
+                Ok((SEEK_HEAD, size, _)) => {
+                    for (_, seek) in children(&read_vec(reader, size)?).into_iter().filter(|(id, _)| *id == SEEK) {
+                        let fields = children(seek);
+                        let target = fields.iter().find(|(id, _)| *id == SEEK_ID).map(|(_, v)| be_uint(v));
+                        let position = fields.iter().find(|(id, _)| *id == SEEK_POSITION).map(|(_, v)| be_uint(v));
+                        if let (Some(target @ (INFO | TRACKS)), Some(position)) = (target, position) {
+                            seek_positions.push((target, position));
+                        }
+                    }
+                }
+                // Hors taille inconnue, une taille EBML tient sur 56 bits
+                Ok((id, size, _)) if !jumped && id != CLUSTER && size != UNKNOWN_SIZE => {
+                    reader.seek(SeekFrom::Current(size as i64))?;
+                }
+                // Les données commencent : le reste de l'en-tête n'est accessible que par le SeekHead
+                _ => jumped = true,
+            }
+
+            if jumped {
+                let missing = |target: u64| (target == INFO && !found_info) || (target == TRACKS && !found_tracks);
+                let Some(&(_, position)) = seek_positions.iter().find(|(target, position)| missing(*target) && !visited.contains(position)) else { break };
+                visited.push(position);
+                let target = segment_start.checked_add(position).ok_or_else(|| io::Error::other("SeekPosition hors du fichier"))?;
+                reader.seek(SeekFrom::Start(target))?;
+            }
+        }
+
+        if found_info || found_tracks { Ok(info) } else { Err(io::Error::other("en-tête Matroska introuvable")) }
+    }
+}
+
+/// MP4 / MOV (ISO BMFF) : boîtes `taille, type`, l'en-tête utile est dans `moov`.
+mod mp4 {
+    use super::*;
+
+    /// Boîtes `(type, contenu)` d'une boîte déjà chargée en mémoire.
+    fn boxes(mut data: &[u8]) -> Vec<([u8; 4], &[u8])> {
+        let mut boxes = Vec::new();
+        while data.len() >= 8 {
+            let size = be_u32(data, 0).unwrap_or(0) as u64;
+            let kind: [u8; 4] = data[4..8].try_into().unwrap();
+            let (header, size) = match size {
+                0 => (8, data.len() as u64),
+                1 => (16, be_u64(data, 8).unwrap_or(0)),
+                _ => (8, size),
+            };
+            if size < header || header as usize > data.len() {
+                break;
+            }
+            let end = size.min(data.len() as u64) as usize;
+            boxes.push((kind, &data[header as usize..end]));
+            data = &data[end..];
+        }
+        boxes
+    }
+
+    fn find<'a>(data: &'a [u8], kind: &[u8; 4]) -> Option<&'a [u8]> {
+        boxes(data).into_iter().find(|(k, _)| k == kind).map(|(_, content)| content)
+    }
+
+    /// Durée d'une boîte `mvhd` ou `mdhd` (version, flags, dates, timescale, duration).
+    fn timescale_and_duration(data: &[u8]) -> Option<(u32, u64)> {
+        match data.first()? {
+            1 => Some((be_u32(data, 20)?, be_u64(data, 24)?)),
+            _ => Some((be_u32(data, 12)?, be_u32(data, 16)? as u64)),
+        }
+    }
+
+    /// Langue ISO 639-2/T d'un `mdhd` : trois lettres de 5 bits, décalées de 0x60.
+    fn language(mdhd: &[u8]) -> Option<String> {
+        let at = if *mdhd.first()? == 1 { 32 } else { 20 };
+        let packed = be_u16(mdhd, at)?;
+        let code: String = [10, 5, 0].iter().map(|shift| (((packed >> shift) & 0x1F) as u8 + 0x60) as char).collect();
+        (code.chars().all(|c| c.is_ascii_lowercase()) && code != "und").then_some(code)
+    }
+
+    fn codec_name(fourcc: &[u8; 4]) -> String {
+        match fourcc {
+            b"hvc1" | b"hev1" => "HEVC",
+            b"dvh1" | b"dvhe" => "HEVC",
+            b"avc1" | b"avc3" => "H.264",
+            b"dva1" | b"dvav" => "H.264",
+            b"av01" | b"dav1" => "AV1",
+            b"vp09" => "VP9",
+            b"mp4v" => "MPEG-4 ASP",
+            other => return String::from_utf8_lossy(other).trim().to_string(),
+        }
+        .to_string()
+    }
+
+    /// Entrée d'échantillon vidéo : en-tête de 78 octets (largeur à 24), puis ses boîtes (`colr`, `dvcC`…).
+    fn parse_video_entry(kind: &[u8; 4], entry: &[u8], info: &mut MediaInfo) {
+        info.video_codec = Some(codec_name(kind));
+        info.width = be_u16(entry, 24).map(u32::from);
+        info.height = be_u16(entry, 26).map(u32::from);
+        info.hdr |= matches!(kind, b"dvh1" | b"dvhe" | b"dva1" | b"dvav" | b"dav1");
+
+        for (child, content) in entry.get(78..).map(boxes).unwrap_or_default() {
+            match &child {
+                b"colr" if content.get(..4) == Some(b"nclx") => {
+                    info.hdr |= be_u16(content, 6).is_some_and(|transfer| is_hdr_transfer(transfer as u64));
+                }
+                b"dvcC" | b"dvvC" => info.hdr = true,
+                _ => {}
+            }
+        }
+    }
+
+    fn parse_track(trak: &[u8], info: &mut MediaInfo) -> Option<()> {
+        let mdia = find(trak, b"mdia")?;
+        let handler: [u8; 4] = find(mdia, b"hdlr")?.get(8..12)?.try_into().ok()?;
+        let mdhd = find(mdia, b"mdhd")?;
+        match &handler {
+            b"vide" if info.video_codec.is_none() => {
+                let stsd = find(find(find(mdia, b"minf")?, b"stbl")?, b"stsd")?;
+                // stsd : version et flags, nombre d'entrées, puis les entrées
+                let (kind, entry) = boxes(stsd.get(8..)?).into_iter().next()?;
+                parse_video_entry(&kind, entry, info);
+            }
+            b"soun" => info.audio_languages.push(language(mdhd).unwrap_or_else(|| "und".to_string())),
+            _ => {}
+        }
+        Some(())
+    }
+
+    fn parse_moov(moov: &[u8], info: &mut MediaInfo) {
+        if let Some((timescale, duration)) = find(moov, b"mvhd").and_then(timescale_and_duration)
+            && timescale > 0
+        {
+            info.duration = Some(duration as f64 / timescale as f64);
+        }
+        for (kind, trak) in boxes(moov) {
+            if &kind == b"trak" {
+                parse_track(trak, info);
+            }
+        }
+    }
+
+    /// `moov` est au début d'un fichier préparé pour le streaming, sinon après `mdat` : on saute les autres boîtes.
+    pub fn probe<R: Read + Seek>(reader: &mut R, file_size: u64) -> io::Result<MediaInfo> {
+        let mut position = 0u64;
+        for _ in 0..MAX_TOP_LEVEL {
+            if position.checked_add(8).is_none_or(|end| end > file_size) {
+                break;
+            }
+            reader.seek(SeekFrom::Start(position))?;
+            let mut header = [0; 16];
+            reader.read_exact(&mut header[..8])?;
+            let kind: [u8; 4] = header[4..8].try_into().unwrap();
+            let (header_len, size) = match be_u32(&header, 0).unwrap_or(0) {
+                0 => (8, file_size - position),
+                1 => {
+                    reader.read_exact(&mut header[8..16])?;
+                    (16, be_u64(&header, 8).unwrap_or(0))
+                }
+                size => (8, size as u64),
+            };
+            if size < header_len {
+                break;
+            }
+
+            if &kind == b"moov" {
+                let mut info = MediaInfo::default();
+                parse_moov(&read_vec(reader, size - header_len)?, &mut info);
+                return Ok(info);
+            }
+            // Une `largesize` absurde ne mène nulle part
+            let Some(next) = position.checked_add(size) else { break };
+            position = next;
+        }
+        Err(io::Error::other("boîte moov introuvable"))
+    }
+}
+
+/// AVI (RIFF) : en-têtes dans `LIST hdrl`, petit-boutiste.
+mod avi {
+    use super::*;
+
+    /// Morceaux `(fourcc, contenu)` ; pour un `LIST`, le fourcc est le type de liste et le contenu ses morceaux.
+    fn chunks(mut data: &[u8]) -> Vec<([u8; 4], &[u8])> {
+        let mut chunks = Vec::new();
+        while data.len() >= 8 {
+            let mut id: [u8; 4] = data[..4].try_into().unwrap();
+            let size = le_u32(data, 4).unwrap_or(0) as usize;
+            let end = (8 + size).min(data.len());
+            let mut content = &data[8..end];
+            if &id == b"LIST" && content.len() >= 4 {
+                id = content[..4].try_into().unwrap();
+                content = &content[4..];
+            }
+            chunks.push((id, content));
+            // Les morceaux sont alignés sur deux octets
+            data = &data[(end + (size & 1)).min(data.len())..];
+        }
+        chunks
+    }
+
+    fn codec_name(fourcc: &[u8]) -> String {
+        match fourcc.to_ascii_uppercase().as_slice() {
+            b"XVID" | b"DIVX" | b"DX50" | b"FMP4" | b"MP4V" => "MPEG-4 ASP".to_string(),
+            b"H264" | b"X264" | b"AVC1" => "H.264".to_string(),
+            b"HEVC" | b"H265" | b"HVC1" => "HEVC".to_string(),
+            b"MJPG" => "MJPEG".to_string(),
+            _ => String::from_utf8_lossy(fourcc).trim_matches(['\0', ' ']).to_string(),
+        }
+    }
+
+    pub fn probe<R: Read + Seek>(reader: &mut R) -> io::Result<MediaInfo> {
+        reader.seek(SeekFrom::Start(12))?;
+        let mut header = [0; 12];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != b"LIST" || &header[8..12] != b"hdrl" {
+            return Err(io::Error::other("pas de LIST hdrl"));
+        }
+        let hdrl = read_vec(reader, le_u32(&header, 4).unwrap_or(0).saturating_sub(4) as u64)?;
+
+        let mut info = MediaInfo::default();
+        let (mut micro_per_frame, mut frames) = (0, 0);
+        let mut odml_frames = None;
+        for (id, content) in chunks(&hdrl) {
+            match &id {
+                // MainAVIHeader : µs par image, …, nombre d'images à 16, largeur et hauteur à 32
+                b"avih" => {
+                    micro_per_frame = le_u32(content, 0).unwrap_or(0);
+                    frames = le_u32(content, 16).unwrap_or(0);
+                    info.width = le_u32(content, 32);
+                    info.height = le_u32(content, 36);
+                }
+                b"strl" => {
+                    let stream = chunks(content);
+                    let strh = stream.iter().find(|(id, _)| id == b"strh").map(|(_, c)| *c);
+                    let strf = stream.iter().find(|(id, _)| id == b"strf").map(|(_, c)| *c);
+                    match strh.and_then(|strh| strh.get(..4)) {
+                        // BITMAPINFOHEADER : le fourcc du codec est biCompression, à 16
+                        Some(b"vids") if info.video_codec.is_none() => {
+                            let fourcc = strf.and_then(|strf| strf.get(16..20)).or(strh.and_then(|strh| strh.get(4..8)));
+                            info.video_codec = fourcc.map(codec_name);
+                        }
+                        // AVI n'a pas de champ langue standard
+                        Some(b"auds") => info.audio_languages.push("und".to_string()),
+                        _ => {}
+                    }
+                }
+                // OpenDML (fichiers de plus de 1 Go) : le vrai nombre d'images est dans dmlh
+                b"odml" => {
+                    odml_frames = chunks(content).iter().find(|(id, _)| id == b"dmlh").and_then(|(_, c)| le_u32(c, 0));
+                }
+                _ => {}
+            }
+        }
+
+        let frames = odml_frames.unwrap_or(frames);
+        if micro_per_frame > 0 && frames > 0 {
+            info.duration = Some(frames as f64 * micro_per_frame as f64 / 1e6);
+        }
+        Ok(info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Élément EBML, taille toujours codée sur 8 octets.
+    fn element(id: &[u8], content: &[u8]) -> Vec<u8> {
+        [id, &[0x01], &(content.len() as u64).to_be_bytes()[1..], content].concat()
+    }
+
+    /// En-tête EBML vide et `Segment` de taille inconnue, suivis de `body`.
+    fn matroska(body: &[u8]) -> Vec<u8> {
+        let segment = [0x18, 0x53, 0x80, 0x67, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+        [&element(&[0x1A, 0x45, 0xDF, 0xA3], &[]), &segment[..], body].concat()
+    }
+
+    fn mp4_box(kind: &[u8; 4], content: &[u8]) -> Vec<u8> {
+        [&(content.len() as u32 + 8).to_be_bytes(), kind.as_slice(), content].concat()
+    }
+
+    fn riff_chunk(id: &[u8; 4], content: &[u8]) -> Vec<u8> {
+        let padding: &[u8] = if content.len() % 2 == 1 { &[0] } else { &[] };
+        [id.as_slice(), &(content.len() as u32).to_le_bytes(), content, padding].concat()
+    }
+
+    fn riff_list(kind: &[u8; 4], content: &[u8]) -> Vec<u8> {
+        riff_chunk(b"LIST", &[kind.as_slice(), content].concat())
+    }
+
+    #[test]
+    fn reads_matroska_tracks() {
+        let info = element(&[0x15, 0x49, 0xA9, 0x66], &[
+            element(&[0x2A, 0xD7, 0xB1], &[0x0F, 0x42, 0x40]),
+            element(&[0x44, 0x89], &7_200_000f64.to_be_bytes()),
+        ].concat());
+        let colour = element(&[0x55, 0xB0], &element(&[0x55, 0xBA], &[16]));
+        let video = element(&[0xE0], &[element(&[0xB0], &[0x0F, 0x00]), element(&[0xBA], &[0x08, 0x70]), colour].concat());
+        let tracks = element(&[0x16, 0x54, 0xAE, 0x6B], &[
+            element(&[0xAE], &[element(&[0x83], &[1]), element(&[0x86], b"V_MPEGH/ISO/HEVC"), video].concat()),
+            element(&[0xAE], &[element(&[0x83], &[2]), element(&[0x22, 0xB5, 0x9C], b"fre")].concat()),
+            element(&[0xAE], &element(&[0x83], &[2])),
+        ].concat());
+
+        let info = matroska::probe(&mut Cursor::new(matroska(&[info, tracks].concat()))).unwrap();
+        assert_eq!(info.video_codec.as_deref(), Some("HEVC"));
+        assert_eq!((info.width, info.height, info.hdr), (Some(3840), Some(2160), true));
+        assert_eq!(info.duration, Some(7200.0));
+        assert_eq!(info.audio_languages, ["fre", "eng"]);
+    }
+
+    #[test]
+    fn absurd_matroska_headers_fail_without_panicking() {
+        // En-tête EBML de taille inconnue
+        assert!(matroska::probe(&mut Cursor::new(vec![0x1A, 0x45, 0xDF, 0xA3, 0xFF])).is_err());
+
+        // SeekHead qui renvoie au-delà de u64::MAX, atteint au premier Cluster
+        let seek = element(&[0x4D, 0xBB], &[element(&[0x53, 0xAB], &[0x15, 0x49, 0xA9, 0x66]), element(&[0x53, 0xAC], &[0xFF; 8])].concat());
+        let body = [element(&[0x11, 0x4D, 0x9B, 0x74], &seek), element(&[0x1F, 0x43, 0xB6, 0x75], &[])].concat();
+        assert!(matroska::probe(&mut Cursor::new(matroska(&body))).is_err());
+
+        // Tronqué au milieu de l'Info
+        let mut truncated = matroska(&element(&[0x15, 0x49, 0xA9, 0x66], &element(&[0x44, 0x89], &1f64.to_be_bytes())));
+        truncated.truncate(truncated.len() - 3);
+        assert!(matroska::probe(&mut Cursor::new(truncated)).is_err());
+    }
+
+    fn mp4_track(handler: &[u8; 4], language: u16, stbl: Option<Vec<u8>>) -> Vec<u8> {
+        let hdlr = mp4_box(b"hdlr", &[&[0; 8][..], handler, &[0; 12]].concat());
+        let mdhd = mp4_box(b"mdhd", &[&[0; 20][..], &language.to_be_bytes(), &[0; 2]].concat());
+        let minf = stbl.map(|stbl| mp4_box(b"minf", &mp4_box(b"stbl", &stbl))).unwrap_or_default();
+        mp4_box(b"trak", &mp4_box(b"mdia", &[hdlr, mdhd, minf].concat()))
+    }
+
+    #[test]
+    fn reads_mp4_moov_after_mdat() {
+        let mvhd = mp4_box(b"mvhd", &[&[0; 12][..], &1000u32.to_be_bytes(), &5_400_000u32.to_be_bytes(), &[0; 80]].concat());
+        let mut entry = vec![0; 78];
+        entry[24..28].copy_from_slice(&[0x07, 0x80, 0x04, 0x38]);
+        let colr = mp4_box(b"colr", &[b"nclx".as_slice(), &[0, 9, 0, 16, 0, 9, 0x80]].concat());
+        let stsd = mp4_box(b"stsd", &[&[0, 0, 0, 0, 0, 0, 0, 1][..], &mp4_box(b"avc1", &[entry, colr].concat())].concat());
+        // « fra » : trois lettres de 5 bits décalées de 0x60
+        let fra = (6 << 10) | (18 << 5) | 1;
+        let moov = mp4_box(b"moov", &[mvhd, mp4_track(b"vide", 0, Some(stsd)), mp4_track(b"soun", fra, None)].concat());
+        let data = [mp4_box(b"ftyp", b"isom"), mp4_box(b"mdat", &[0; 100]), moov].concat();
+
+        let info = mp4::probe(&mut Cursor::new(&data), data.len() as u64).unwrap();
+        assert_eq!(info.video_codec.as_deref(), Some("H.264"));
+        assert_eq!((info.width, info.height, info.hdr), (Some(1920), Some(1080), true));
+        assert_eq!(info.duration, Some(5400.0));
+        assert_eq!(info.audio_languages, ["fra"]);
+    }
+
+    #[test]
+    fn absurd_mp4_sizes_fail_without_panicking() {
+        // largesize qui déborde la position suivante
+        let huge = [&1u32.to_be_bytes()[..], b"mdat", &(u64::MAX - 4).to_be_bytes()].concat();
+        let data = [mp4_box(b"ftyp", b"isom"), huge].concat();
+        assert!(mp4::probe(&mut Cursor::new(&data), data.len() as u64).is_err());
+
+        // largesize plus petite que son propre en-tête
+        let tiny = [&1u32.to_be_bytes()[..], b"moov", &4u64.to_be_bytes()].concat();
+        assert!(mp4::probe(&mut Cursor::new(&tiny), tiny.len() as u64).is_err());
+
+        // moov annoncé plus grand que le fichier
+        let truncated = [mp4_box(b"ftyp", b"isom"), 4096u32.to_be_bytes().to_vec(), b"moov".to_vec()].concat();
+        assert!(mp4::probe(&mut Cursor::new(&truncated), truncated.len() as u64).is_err());
+    }
+
+    #[test]
+    fn reads_avi_hdrl() {
+        let mut avih = vec![0; 56];
+        avih[0..4].copy_from_slice(&40_000u32.to_le_bytes());
+        avih[16..20].copy_from_slice(&90_000u32.to_le_bytes());
+        avih[32..36].copy_from_slice(&720u32.to_le_bytes());
+        avih[36..40].copy_from_slice(&576u32.to_le_bytes());
+        let mut strf = vec![0; 40];
+        strf[16..20].copy_from_slice(b"XVID");
+        let video = riff_list(b"strl", &[riff_chunk(b"strh", &[b"vids".as_slice(), &[0; 52]].concat()), riff_chunk(b"strf", &strf)].concat());
+        let audio = riff_list(b"strl", &riff_chunk(b"strh", &[b"auds".as_slice(), &[0; 52]].concat()));
+        // Morceau de taille impaire : le suivant est aligné sur deux octets
+        let junk = riff_chunk(b"JUNK", &[0; 3]);
+        let hdrl = riff_list(b"hdrl", &[riff_chunk(b"avih", &avih), junk, video, audio].concat());
+        let data = riff_chunk(b"RIFF", &[b"AVI ".as_slice(), &hdrl].concat());
+
+        let info = avi::probe(&mut Cursor::new(data)).unwrap();
+        assert_eq!(info.video_codec.as_deref(), Some("MPEG-4 ASP"));
+        assert_eq!((info.width, info.height), (Some(720), Some(576)));
+        assert_eq!(info.duration, Some(3600.0));
+        assert_eq!(info.audio_languages, ["und"]);
+
+        // hdrl annoncé plus long que le fichier
+        let truncated = [b"RIFF".as_slice(), &[0; 4], b"AVI LIST", &u32::MAX.to_le_bytes(), b"hdrl"].concat();
+        assert!(avi::probe(&mut Cursor::new(truncated)).is_err());
+    }
+}
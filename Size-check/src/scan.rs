@@ -0,0 +1,475 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+use crate::{file_node, media, FolderNode, Summary};
+
+/// Ce que les workers envoient pour chaque dossier lu. `at` donne la position du dossier
+/// dans l'arbre : les indices des sous-dossiers depuis la racine.
+enum ScanEvent {
+    /// Les fichiers du dossier, et ses sous-dossiers encore vides dans l'ordre de `read_dir`
+    Folder { at: Vec<usize>, folder: FolderNode, errors: Vec<String> },
+    Unreadable { at: Vec<usize>, path: PathBuf, error: io::Error },
+}
+
+/// Dossiers à lire et nombre de workers occupés : la file n'est finie que vide et sans worker actif.
+struct QueueState {
+    pending: Vec<(PathBuf, Vec<usize>)>,
+    busy: usize,
+}
+
+struct Queue {
+    state: Mutex<QueueState>,
+    wakeup: Condvar,
+}
+
+struct Shared {
+    queue: Queue,
+    cancel: Arc<AtomicBool>,
+    summary: Arc<Mutex<Summary>>,
+    include_all: bool,
+    media: bool,
+}
+
+fn is_video(name: &str) -> bool {
+    name.ends_with(".mp4") || name.ends_with(".mkv") || name.ends_with(".avi")
+}
+
+/// Lit un seul dossier. Le type vient de `read_dir`, seuls les liens symboliques sont suivis avec un stat.
+fn read_folder(dir: &Path, shared: &Shared) -> io::Result<(FolderNode, Vec<PathBuf>, Vec<String>)> {
+    let mut folder = FolderNode::new(dir);
+    let mut errors = Vec::new();
+    let mut files = Vec::new();
+    let mut subdirs = Vec::new();
+
+    for entry in fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Ok(file_type) = entry.file_type() else { continue };
+        let metadata = if file_type.is_symlink() { fs::metadata(&path) } else { entry.metadata() };
+
+        if metadata.as_ref().is_ok_and(|m| m.is_dir()) {
+            if name.starts_with('.') || (!shared.include_all && name.contains(".trickplay")) {
+                continue;
+            }
+            subdirs.push(path);
+        } else if name != ".DS_Store" && (shared.include_all || is_video(&name)) {
+            match metadata {
+                Ok(metadata) => files.push((path, name, metadata)),
+                Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+            }
+        }
+    }
+
+    let mut summary = shared.summary.lock().unwrap();
+    summary.total_folders += 1;
+    for (path, name, metadata) in files {
+        let file = file_node(&path, name, &metadata, &mut summary);
+        folder.size += file.size;
+        folder.allocated += file.allocated;
+        folder.modified = folder.modified.max(file.modified);
+        folder.files.push(file);
+    }
+    drop(summary);
+
+    if shared.media {
+        for file in folder.files.iter_mut().filter(|file| is_video(&file.name.to_lowercase())) {
+            file.media = media::probe(Path::new(&file.path), file.size);
+        }
+    }
+
+    folder.subfolders = subdirs.iter().map(|path| FolderNode::new(path)).collect();
+    Ok((folder, subdirs, errors))
+}
+
+fn worker(shared: &Shared, sender: Sender<ScanEvent>) {
+    loop {
+        let (dir, at) = {
+            let mut state = shared.queue.state.lock().unwrap();
+            loop {
+                if shared.cancel.load(Ordering::Relaxed) {
+                    shared.queue.wakeup.notify_all();
+                    return;
+                }
+                if let Some(next) = state.pending.pop() {
+                    state.busy += 1;
+                    break next;
+                }
+                if state.busy == 0 {
+                    shared.queue.wakeup.notify_all();
+                    return;
+                }
+                state = shared.queue.wakeup.wait(state).unwrap();
+            }
+        };
+
+        let subdirs = match read_folder(&dir, shared) {
+            Ok((folder, subdirs, errors)) => {
+                // Le parent part avant que ses sous-dossiers soient dans la file : il arrive toujours en premier
+                let _ = sender.send(ScanEvent::Folder { at: at.clone(), folder, errors });
+                subdirs
+            }
+            Err(error) => {
+                let _ = sender.send(ScanEvent::Unreadable { at: at.clone(), path: dir, error });
+                Vec::new()
+            }
+        };
+
+        let mut state = shared.queue.state.lock().unwrap();
+        for (index, subdir) in subdirs.into_iter().enumerate().rev() {
+            let mut child = at.clone();
+            child.push(index);
+            state.pending.push((subdir, child));
+        }
+        state.busy -= 1;
+        shared.queue.wakeup.notify_all();
+    }
+}
+
+/// Passe `allocated` du fichier `path` à `allocated` et reporte l'écart sur ses dossiers.
+/// `None` si le fichier n'est pas dans l'arbre (dossier jamais reçu).
+fn set_allocated(folder: &mut FolderNode, path: &Path, allocated: u64) -> Option<i64> {
+    let delta = match folder.files.iter_mut().find(|file| Path::new(&file.path) == path) {
+        Some(file) => {
+            let delta = allocated as i64 - file.allocated as i64;
+            file.allocated = allocated;
+            delta
+        }
+        None => {
+            let subfolder = folder.subfolders.iter_mut().find(|subfolder| path.starts_with(&subfolder.path))?;
+            set_allocated(subfolder, path, allocated)?
+        }
+    };
+    folder.allocated = folder.allocated.saturating_add_signed(delta);
+    Some(delta)
+}
+
+fn folder_at<'a>(root: &'a mut FolderNode, at: &[usize]) -> Option<&'a mut FolderNode> {
+    at.iter().try_fold(root, |folder, &index| folder.subfolders.get_mut(index))
+}
+
+/// Ce qu'un appel à `Scan::receive` a fait de l'arbre.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Received {
+    /// Rien n'est arrivé pendant l'attente
+    Nothing,
+    /// Des dossiers ont été rangés dans l'arbre, l'analyse continue
+    Changed,
+    /// L'analyse est terminée (ou annulée) et tout a été rangé
+    Finished,
+}
+
+impl Received {
+    pub fn is_running(self) -> bool {
+        self != Received::Finished
+    }
+}
+
+/// Analyse en un seul parcours, répartie sur plusieurs threads. L'arbre se remplit au fur et à
+/// mesure des appels à `receive`, ce qui permet d'afficher une progression ou l'interface tout de suite.
+#[derive(Debug)]
+pub struct Scan {
+    receiver: Receiver<ScanEvent>,
+    cancel: Arc<AtomicBool>,
+    summary: Arc<Mutex<Summary>>,
+    debug: bool,
+    /// Dossiers trouvés jusqu'ici, racine comprise : une estimation du total qui grandit pendant l'analyse
+    pub discovered: u64,
+    pub scanned: u64,
+    /// Sous-dossiers illisibles, retirés de l'arbre à la fin comme le faisait l'ancien parcours
+    unreadable: Vec<Vec<usize>>,
+    root_error: Option<io::Error>,
+    finished: bool,
+}
+
+impl Scan {
+    /// Lance l'analyse de `root` sur `threads` workers (un par cœur si 0). Avec `media`, les workers
+    /// lisent aussi l'en-tête de chaque vidéo.
+    pub fn start(root: &Path, include_all: bool, media: bool, debug: bool, threads: usize) -> Self {
+        Self::start_with(root, include_all, media, debug, threads, Summary::default())
+    }
+
+    /// Comme `start`, en reprenant `summary` : ses hardlinks déjà comptés ne le sont pas une seconde fois.
+    fn start_with(root: &Path, include_all: bool, media: bool, debug: bool, threads: usize, summary: Summary) -> Self {
+        let threads = if threads == 0 { thread::available_parallelism().map_or(4, |n| n.get()) } else { threads };
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let summary = Arc::new(Mutex::new(summary));
+        let shared = Shared {
+            queue: Queue { state: Mutex::new(QueueState { pending: vec![(root.to_path_buf(), Vec::new())], busy: 0 }), wakeup: Condvar::new() },
+            cancel: cancel.clone(),
+            summary: summary.clone(),
+            include_all,
+            media,
+        };
+
+        // Le canal se ferme quand le dernier worker rend son `Sender` : c'est la fin de l'analyse
+        thread::spawn(move || {
+            thread::scope(|scope| {
+                for _ in 0..threads {
+                    let sender = sender.clone();
+                    scope.spawn(|| worker(&shared, sender));
+                }
+            });
+        });
+
+        Scan { receiver, cancel, summary, debug, discovered: 1, scanned: 0, unreadable: Vec::new(), root_error: None, finished: false }
+    }
+
+    /// Range dans `root` tout ce qui est arrivé, en attendant au plus `wait` le premier dossier.
+    pub fn receive(&mut self, root: &mut FolderNode, wait: Duration) -> Received {
+        if self.finished {
+            return Received::Finished;
+        }
+
+        let mut next = self.receiver.recv_timeout(wait);
+        let mut received = Received::Nothing;
+        loop {
+            match next {
+                Ok(event) => {
+                    self.apply(root, event);
+                    received = Received::Changed;
+                }
+                Err(RecvTimeoutError::Timeout) => return received,
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.finish(root);
+                    return Received::Finished;
+                }
+            }
+            next = self.receiver.try_recv().map_err(|e| match e {
+                mpsc::TryRecvError::Empty => RecvTimeoutError::Timeout,
+                mpsc::TryRecvError::Disconnected => RecvTimeoutError::Disconnected,
+            });
+        }
+    }
+
+    fn apply(&mut self, root: &mut FolderNode, event: ScanEvent) {
+        self.scanned += 1;
+        match event {
+            ScanEvent::Folder { at, folder, errors } => {
+                self.discovered += folder.subfolders.len() as u64;
+                if self.debug {
+                    for error in errors {
+                        eprintln!("Failed to get metadata for {}", error);
+                    }
+                }
+
+                let (size, allocated, modified) = (folder.size, folder.allocated, folder.modified);
+                let Some(slot) = folder_at(root, &at) else { return };
+                *slot = folder;
+                // La racine et les dossiers au-dessus n'ont compté que leurs propres fichiers jusqu'ici
+                for depth in 0..at.len() {
+                    if let Some(ancestor) = folder_at(root, &at[..depth]) {
+                        ancestor.size += size;
+                        ancestor.allocated += allocated;
+                        ancestor.modified = ancestor.modified.max(modified);
+                    }
+                }
+            }
+            ScanEvent::Unreadable { at, path, error } => {
+                if at.is_empty() {
+                    self.root_error = Some(error);
+                    return;
+                }
+                if self.debug {
+                    eprintln!("Failed to read directory {}: {}", path.display(), error);
+                }
+                self.unreadable.push(at);
+            }
+        }
+    }
+
+    fn finish(&mut self, root: &mut FolderNode) {
+        self.finished = true;
+        // Du plus profond et du dernier au premier, pour que les indices restants restent valables
+        self.unreadable.sort_by(|a, b| b.cmp(a));
+        for at in self.unreadable.drain(..) {
+            let (index, parent) = at.split_last().unwrap();
+            if let Some(parent) = folder_at(root, parent) {
+                parent.subfolders.remove(*index);
+            }
+        }
+
+        // Le premier chemin lu d'un hardlink dépend des threads : l'inode revient au plus petit,
+        // pour qu'une même arborescence donne toujours le même arbre
+        let mut summary = self.summary.lock().unwrap();
+        for charge in summary.hardlinks.values_mut().filter(|charge| charge.charged != charge.smallest) {
+            if set_allocated(root, Path::new(&charge.smallest), charge.allocated).is_some() {
+                set_allocated(root, Path::new(&charge.charged), 0);
+                charge.charged = charge.smallest.clone();
+            }
+        }
+    }
+
+    /// Arrête les workers : l'arbre garde ce qui a déjà été lu.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// Erreur de lecture de la racine elle-même, seule erreur qui fait échouer l'analyse.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.root_error.take()
+    }
+
+    pub fn summary(&self) -> Summary {
+        std::mem::take(&mut self.summary.lock().unwrap())
+    }
+}
+
+impl Drop for Scan {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// Analyse complète sans progression, pour rescanner un élément restauré. `summary` est celui
+/// de l'analyse de départ, mis à jour avec ce qui a été relu.
+pub fn scan_blocking(root: &Path, include_all: bool, media: bool, summary: &mut Summary) -> io::Result<FolderNode> {
+    let mut folder = FolderNode::new(root);
+    let mut scan = Scan::start_with(root, include_all, media, false, 0, std::mem::take(summary));
+    while scan.receive(&mut folder, Duration::from_secs(1)).is_running() {}
+    *summary = scan.summary();
+    scan.take_error().map_or(Ok(folder), Err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, bytes: usize) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, vec![7u8; bytes]).unwrap();
+    }
+
+    fn scan(root: &Path, threads: usize) -> (FolderNode, Summary) {
+        let mut folder = FolderNode::new(root);
+        let mut scan = Scan::start(root, true, false, false, threads);
+        while scan.receive(&mut folder, Duration::from_secs(1)).is_running() {}
+        assert!(scan.take_error().is_none());
+        (folder, scan.summary())
+    }
+
+    /// Taille et nombre de fichiers d'un dossier, par un parcours récursif ordinaire.
+    fn serial_walk(dir: &Path) -> (u64, u64, u64) {
+        let (mut size, mut files, mut folders) = (0, 0, 1);
+        for entry in fs::read_dir(dir).unwrap().flatten() {
+            let metadata = entry.metadata().unwrap();
+            if metadata.is_dir() {
+                let (sub_size, sub_files, sub_folders) = serial_walk(&entry.path());
+                size += sub_size;
+                files += sub_files;
+                folders += sub_folders;
+            } else {
+                size += metadata.len();
+                files += 1;
+            }
+        }
+        (size, files, folders)
+    }
+
+    fn assert_matches_serial_walk(folder: &FolderNode) {
+        assert_eq!(folder.size, serial_walk(Path::new(&folder.path)).0, "{}", folder.path);
+        let allocated: u64 = folder.files.iter().map(|file| file.allocated).sum::<u64>()
+            + folder.subfolders.iter().map(|subfolder| subfolder.allocated).sum::<u64>();
+        assert_eq!(folder.allocated, allocated, "{}", folder.path);
+        for subfolder in &folder.subfolders {
+            assert_matches_serial_walk(subfolder);
+        }
+    }
+
+    #[test]
+    fn parallel_scan_matches_serial_walk() {
+        let tmp = tempfile::tempdir().unwrap();
+        for (index, path) in ["a/1.mkv", "a/b/2.mkv", "a/b/c/3.bin", "a/b/c/d/4.txt", "e/5.mkv", "e/f/6.mkv", "g/7.avi", "8.mp4"].iter().enumerate() {
+            write(&tmp.path().join(path), 1000 * (index + 1));
+        }
+        fs::create_dir_all(tmp.path().join("empty/deeper")).unwrap();
+
+        let (size, files, folders) = serial_walk(tmp.path());
+        for threads in [1, 2, 8] {
+            let (root, summary) = scan(tmp.path(), threads);
+            assert_eq!((summary.total_size, summary.total_files, summary.total_folders), (size, files, folders));
+            assert_eq!(root.allocated, summary.total_allocated);
+            assert_matches_serial_walk(&root);
+        }
+    }
+
+    #[test]
+    fn unreadable_folder_is_dropped_without_shifting_siblings() {
+        let (sender, receiver) = mpsc::channel();
+        let mut scan = Scan {
+            receiver,
+            cancel: Arc::new(AtomicBool::new(false)),
+            summary: Arc::new(Mutex::new(Summary::default())),
+            debug: false,
+            discovered: 1,
+            scanned: 0,
+            unreadable: Vec::new(),
+            root_error: None,
+            finished: false,
+        };
+        let folder = |path: &str, size: u64, subfolders: &[&str]| {
+            let mut folder = FolderNode::new(Path::new(path));
+            folder.size = size;
+            folder.subfolders = subfolders.iter().map(|path| FolderNode::new(Path::new(path))).collect();
+            folder
+        };
+
+        // Arrivées dans le désordre des workers : /r/b (illisible) entre /r/c et /r/a
+        let events = [
+            ScanEvent::Folder { at: vec![], folder: folder("/r", 0, &["/r/a", "/r/b", "/r/c"]), errors: vec![] },
+            ScanEvent::Folder { at: vec![2], folder: folder("/r/c", 30, &["/r/c/x"]), errors: vec![] },
+            ScanEvent::Unreadable { at: vec![1], path: PathBuf::from("/r/b"), error: io::Error::from(io::ErrorKind::PermissionDenied) },
+            ScanEvent::Folder { at: vec![0], folder: folder("/r/a", 10, &[]), errors: vec![] },
+            ScanEvent::Folder { at: vec![2, 0], folder: folder("/r/c/x", 5, &[]), errors: vec![] },
+        ];
+        let mut root = FolderNode::new(Path::new("/r"));
+        assert_eq!(scan.receive(&mut root, Duration::ZERO), Received::Nothing);
+        for event in events {
+            sender.send(event).unwrap();
+        }
+        assert_eq!(scan.receive(&mut root, Duration::ZERO), Received::Changed);
+        assert_eq!(scan.receive(&mut root, Duration::ZERO), Received::Nothing);
+        drop(sender);
+        assert_eq!(scan.receive(&mut root, Duration::ZERO), Received::Finished);
+        let paths: Vec<&str> = root.subfolders.iter().map(|folder| folder.path.as_str()).collect();
+        assert_eq!(paths, ["/r/a", "/r/c"]);
+        assert_eq!((root.subfolders[0].size, root.subfolders[1].size), (10, 35));
+        assert_eq!(root.subfolders[1].subfolders[0].path, "/r/c/x");
+        assert_eq!(root.size, 45);
+    }
+
+    #[test]
+    fn hardlink_is_charged_to_smallest_path_whatever_the_threads() {
+        let tmp = tempfile::tempdir().unwrap();
+        let original = tmp.path().join("z/deep/x.mkv");
+        write(&original, 200_000);
+        for link in ["m/x.mkv", "a/x.mkv", "z/y.mkv"] {
+            fs::create_dir_all(tmp.path().join(link).parent().unwrap()).unwrap();
+            fs::hard_link(&original, tmp.path().join(link)).unwrap();
+        }
+
+        for threads in [1, 2, 8, 8, 8] {
+            let (root, summary) = scan(tmp.path(), threads);
+            let charged: Vec<(&str, u64)> = root
+                .subfolders
+                .iter()
+                .map(|folder| (folder.name.as_str(), folder.allocated))
+                .filter(|(_, allocated)| *allocated > 0)
+                .collect();
+            assert_eq!(charged.len(), 1, "{} threads", threads);
+            assert_eq!(charged[0].0, "a");
+            assert_eq!(charged[0].1, summary.total_allocated);
+            assert_eq!(summary.hardlink_savings, 3 * 200_000);
+            assert_matches_serial_walk(&root);
+        }
+    }
+}
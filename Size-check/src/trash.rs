@@ -0,0 +1,481 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+
+/// Un élément de la corbeille dont l'emplacement d'origine est connu.
+#[derive(Debug, Clone)]
+pub struct TrashedItem {
+    pub original: PathBuf,
+    /// `<corbeille>/files/<nom>`
+    pub trashed: PathBuf,
+    /// `<corbeille>/info/<nom>.trashinfo`
+    pub info: PathBuf,
+    /// `DeletionDate` du `.trashinfo`, heure locale `AAAA-MM-JJThh:mm:ss`
+    pub deleted_at: String,
+    /// Place allouée sur le disque, libérée quand la corbeille est vidée
+    pub size: u64,
+}
+
+/// Une corbeille et le dossier par rapport auquel ses chemins `Path=` sont écrits.
+struct TrashDir {
+    dir: PathBuf,
+    /// `None` pour la corbeille personnelle, dont les chemins sont absolus
+    topdir: Option<PathBuf>,
+}
+
+#[cfg(unix)]
+fn uid() -> u32 {
+    unsafe { libc::getuid() }
+}
+
+#[cfg(unix)]
+fn device(path: &Path) -> io::Result<u64> {
+    fs::symlink_metadata(path).map(|m| m.dev())
+}
+
+#[cfg(not(unix))]
+fn device(_path: &Path) -> io::Result<u64> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "corbeille freedesktop indisponible sur ce système"))
+}
+
+#[cfg(unix)]
+fn local_timestamp() -> String {
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        libc::localtime_r(&now, &mut tm);
+    }
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}", tm.tm_year + 1900, tm.tm_mon + 1, tm.tm_mday, tm.tm_hour, tm.tm_min, tm.tm_sec)
+}
+
+/// Place allouée par un fichier ou un dossier entier, sans suivre les liens symboliques.
+pub fn allocated_size(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else { return 0 };
+    #[cfg(unix)]
+    let own = metadata.blocks() * 512;
+    #[cfg(not(unix))]
+    let own = metadata.len();
+
+    if !metadata.is_dir() {
+        return own;
+    }
+    own + fs::read_dir(path).map_or(0, |entries| entries.flatten().map(|entry| allocated_size(&entry.path())).sum())
+}
+
+/// Place que libère la suppression définitive de `path`. Un fichier à plusieurs liens n'est compté
+/// qu'au moment où tous ses liens ont été vus dans `links`, partagé par toute la passe : sinon
+/// l'inode reste sur le disque par ses autres chemins.
+fn freed_size(path: &Path, links: &mut HashMap<(u64, u64), u64>) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else { return 0 };
+    if metadata.is_dir() {
+        #[cfg(unix)]
+        let own = metadata.blocks() * 512;
+        #[cfg(not(unix))]
+        let own = metadata.len();
+        return own + fs::read_dir(path).map_or(0, |entries| entries.flatten().map(|entry| freed_size(&entry.path(), links)).sum());
+    }
+
+    #[cfg(unix)]
+    {
+        if metadata.nlink() > 1 {
+            let seen = links.entry((metadata.dev(), metadata.ino())).or_default();
+            *seen += 1;
+            if *seen < metadata.nlink() {
+                return 0;
+            }
+        }
+        metadata.blocks() * 512
+    }
+    #[cfg(not(unix))]
+    metadata.len()
+}
+
+/// Place libérée par chaque élément si toute la liste est supprimée, à mesurer avant la
+/// première suppression : un inode partagé par deux éléments n'est compté qu'une fois.
+pub fn freed_sizes(items: &[TrashedItem]) -> Vec<u64> {
+    let mut links = HashMap::new();
+    items.iter().map(|item| freed_size(&item.trashed, &mut links)).collect()
+}
+
+/// `$XDG_DATA_HOME`, ou `~/.local/share` par défaut.
+pub fn data_home() -> Option<PathBuf> {
+    env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")))
+}
+
+fn home_trash() -> Option<PathBuf> {
+    Some(data_home()?.join("Trash"))
+}
+
+/// Point de montage de `path` : le plus haut ancêtre sur le même périphérique.
+pub fn topdir(path: &Path) -> io::Result<PathBuf> {
+    let path = path.canonicalize()?;
+    let dev = device(&path)?;
+    let mut top = path.clone();
+    for ancestor in path.ancestors().skip(1) {
+        if device(ancestor)? != dev {
+            break;
+        }
+        top = ancestor.to_path_buf();
+    }
+    Ok(top)
+}
+
+/// `$topdir/.Trash` n'est utilisable que s'il a été préparé par l'administrateur : dossier
+/// réel (pas un lien) avec le sticky bit.
+#[cfg(unix)]
+fn shared_trash_is_valid(shared: &Path) -> bool {
+    fs::symlink_metadata(shared).is_ok_and(|m| m.is_dir() && m.permissions().mode() & 0o1000 != 0)
+}
+
+/// Corbeilles possibles pour le volume de `path`, par ordre de préférence.
+#[cfg(unix)]
+fn candidate_trash_dirs(path: &Path) -> io::Result<Vec<TrashDir>> {
+    let dev = device(path)?;
+    let home = home_trash();
+    // La corbeille personnelle n'existe peut-être pas encore : on regarde son premier ancêtre existant
+    let home_dev = home.as_deref().and_then(|home| home.ancestors().find_map(|a| device(a).ok()));
+    if let Some(home) = home
+        && home_dev == Some(dev)
+    {
+        return Ok(vec![TrashDir { dir: home, topdir: None }]);
+    }
+
+    let topdir = topdir(path)?;
+    let mut dirs = Vec::new();
+    let shared = topdir.join(".Trash");
+    if shared_trash_is_valid(&shared) {
+        dirs.push(TrashDir { dir: shared.join(uid().to_string()), topdir: Some(topdir.clone()) });
+    }
+    dirs.push(TrashDir { dir: topdir.join(format!(".Trash-{}", uid())), topdir: Some(topdir) });
+    Ok(dirs)
+}
+
+#[cfg(not(unix))]
+fn candidate_trash_dirs(path: &Path) -> io::Result<Vec<TrashDir>> {
+    device(path).map(|_| Vec::new())
+}
+
+/// Octets bruts du chemin : un nom qui n'est pas de l'UTF-8 doit pouvoir être restauré tel quel.
+#[cfg(unix)]
+fn path_bytes(path: &Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(not(unix))]
+fn path_bytes(path: &Path) -> Vec<u8> {
+    path.to_string_lossy().into_owned().into_bytes()
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    PathBuf::from(std::ffi::OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Encode un chemin pour `Path=` : tout sauf les caractères non réservés d'une URL et `/`.
+fn encode_path(path: &Path) -> String {
+    let mut encoded = String::new();
+    for byte in path_bytes(path) {
+        if byte.is_ascii_alphanumeric() || b"-_.~/".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+fn decode_path(encoded: &str) -> PathBuf {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(std::str::from_utf8(h).ok()?, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    path_from_bytes(decoded)
+}
+
+fn create_trash_dirs(trash: &TrashDir) -> io::Result<()> {
+    for sub in ["files", "info"] {
+        let mut builder = fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        builder.mode(0o700);
+        builder.create(trash.dir.join(sub))?;
+    }
+    Ok(())
+}
+
+/// Réserve un nom libre en créant le `.trashinfo` de façon exclusive, comme le demande la spec.
+fn reserve_name(trash: &TrashDir, path: &Path) -> io::Result<(String, PathBuf, fs::File)> {
+    let name = path.file_name().ok_or_else(|| io::Error::other("chemin sans nom"))?.to_string_lossy().into_owned();
+    let (stem, extension) = match name.rfind('.') {
+        Some(dot) if dot > 0 => (&name[..dot], &name[dot..]),
+        _ => (name.as_str(), ""),
+    };
+
+    for n in 1.. {
+        let candidate = if n == 1 { name.clone() } else { format!("{}.{}{}", stem, n, extension) };
+        let info = trash.dir.join("info").join(format!("{}.trashinfo", candidate));
+        if trash.dir.join("files").join(&candidate).exists() {
+            continue;
+        }
+        match OpenOptions::new().write(true).create_new(true).open(&info) {
+            Ok(file) => return Ok((candidate, info, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    unreachable!()
+}
+
+fn trash_into(trash: &TrashDir, path: &Path, size: u64) -> io::Result<TrashedItem> {
+    create_trash_dirs(trash)?;
+    let (name, info, mut info_file) = reserve_name(trash, path)?;
+
+    let recorded = match &trash.topdir {
+        Some(topdir) => path.strip_prefix(topdir).unwrap_or(path),
+        None => path,
+    };
+    #[cfg(unix)]
+    let deleted_at = local_timestamp();
+    #[cfg(not(unix))]
+    let deleted_at = String::new();
+
+    let trashed = trash.dir.join("files").join(&name);
+    let moved = write!(info_file, "[Trash Info]\nPath={}\nDeletionDate={}\n", encode_path(recorded), deleted_at)
+        .and_then(|()| fs::rename(path, &trashed));
+    if let Err(e) = moved {
+        let _ = fs::remove_file(&info);
+        return Err(e);
+    }
+
+    Ok(TrashedItem { original: path.to_path_buf(), trashed, info, deleted_at, size })
+}
+
+/// Déplace `path` dans la corbeille freedesktop.org de son volume, sans rien copier :
+/// `$XDG_DATA_HOME/Trash` sur le volume du dossier personnel, sinon `$topdir/.Trash/$uid`
+/// (préparé par l'administrateur) ou `$topdir/.Trash-$uid` à la racine du volume.
+pub fn trash(path: &Path) -> io::Result<TrashedItem> {
+    let path = path.parent().map_or(Ok(path.to_path_buf()), |parent| {
+        parent.canonicalize().map(|parent| parent.join(path.file_name().unwrap_or_default()))
+    })?;
+    let size = allocated_size(&path);
+
+    let mut last_error = io::Error::other("aucune corbeille disponible");
+    for trash in candidate_trash_dirs(&path)? {
+        match trash_into(&trash, &path, size) {
+            Ok(item) => return Ok(item),
+            Err(e) => last_error = e,
+        }
+    }
+    Err(last_error)
+}
+
+/// Remet l'élément à sa place d'origine, si rien ne l'a remplacé entre-temps.
+pub fn restore(item: &TrashedItem) -> io::Result<()> {
+    if item.original.exists() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} existe déjà", item.original.display())));
+    }
+    if let Some(parent) = item.original.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(&item.trashed, &item.original)?;
+    fs::remove_file(&item.info)
+}
+
+/// Supprime définitivement l'élément de la corbeille. La place libérée se mesure avant, avec `freed_sizes`.
+pub fn purge(item: &TrashedItem) -> io::Result<()> {
+    if fs::symlink_metadata(&item.trashed)?.is_dir() {
+        fs::remove_dir_all(&item.trashed)?;
+    } else {
+        fs::remove_file(&item.trashed)?;
+    }
+    fs::remove_file(&item.info)
+}
+
+fn read_info(trash: &TrashDir, info: &Path) -> Option<TrashedItem> {
+    let content = fs::read_to_string(info).ok()?;
+    let mut original = None;
+    let mut deleted_at = String::new();
+    for line in content.lines() {
+        if let Some(path) = line.strip_prefix("Path=") {
+            let path = decode_path(path);
+            original = Some(match &trash.topdir {
+                Some(topdir) if path.is_relative() => topdir.join(path),
+                _ => path,
+            });
+        } else if let Some(date) = line.strip_prefix("DeletionDate=") {
+            deleted_at = date.to_string();
+        }
+    }
+
+    let name = info.file_name()?.to_string_lossy().strip_suffix(".trashinfo")?.to_string();
+    let trashed = trash.dir.join("files").join(name);
+    fs::symlink_metadata(&trashed).ok()?;
+    let size = allocated_size(&trashed);
+    Some(TrashedItem { original: original?, trashed, info: info.to_path_buf(), deleted_at, size })
+}
+
+/// Éléments des corbeilles du volume de `root` venant de `root`, les plus récents d'abord.
+pub fn list_under(root: &Path) -> Vec<TrashedItem> {
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let mut items: Vec<TrashedItem> = candidate_trash_dirs(&root)
+        .unwrap_or_default()
+        .iter()
+        .flat_map(|trash| {
+            let infos = fs::read_dir(trash.dir.join("info")).map(|entries| entries.flatten().map(|e| e.path()).collect()).unwrap_or_else(|_| Vec::new());
+            infos.into_iter().filter_map(|info| read_info(trash, &info)).collect::<Vec<_>>()
+        })
+        .filter(|item| item.original.starts_with(&root))
+        .collect();
+
+    items.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::OnceLock;
+
+    /// `$XDG_DATA_HOME` de toute la suite : les tests partagent la corbeille personnelle, chacun
+    /// dans son propre dossier créé dessous pour rester sur le même volume.
+    fn xdg_data_home() -> &'static Path {
+        static HOME: OnceLock<tempfile::TempDir> = OnceLock::new();
+        HOME.get_or_init(|| {
+            let home = tempfile::tempdir().unwrap();
+            unsafe { env::set_var("XDG_DATA_HOME", home.path()) };
+            home
+        })
+        .path()
+    }
+
+    fn workdir() -> tempfile::TempDir {
+        tempfile::tempdir_in(xdg_data_home()).unwrap()
+    }
+
+    fn write(path: &Path, bytes: usize) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, vec![1u8; bytes]).unwrap();
+    }
+
+    #[test]
+    fn path_encoding_round_trips_raw_bytes() {
+        let path = Path::new("/films/Amélie (2001)/100% pur.mkv");
+        let encoded = encode_path(path);
+        assert_eq!(encoded, "/films/Am%C3%A9lie%20%282001%29/100%25%20pur.mkv");
+        assert_eq!(decode_path(&encoded), path);
+
+        // Nom en Latin-1 venu d'un vieux disque : pas de l'UTF-8
+        let latin1 = path_from_bytes(b"/films/Am\xe9lie.mkv".to_vec());
+        assert_eq!(encode_path(&latin1), "/films/Am%E9lie.mkv");
+        assert_eq!(decode_path(&encode_path(&latin1)), latin1);
+        // Un `%` non suivi de deux chiffres hexadécimaux est gardé tel quel
+        assert_eq!(decode_path("/a%2/b%zz"), Path::new("/a%2/b%zz"));
+    }
+
+    #[test]
+    fn reserve_name_skips_taken_names() {
+        let work = workdir();
+        let trash = TrashDir { dir: work.path().join("Trash"), topdir: None };
+        create_trash_dirs(&trash).unwrap();
+
+        let reserve = |name: &str| reserve_name(&trash, Path::new(name)).unwrap().0;
+        assert_eq!(reserve("/films/film.mkv"), "film.mkv");
+        assert_eq!(reserve("/series/film.mkv"), "film.2.mkv");
+        // Fichier déjà là sans son `.trashinfo` : le nom reste pris
+        fs::write(trash.dir.join("files/film.3.mkv"), b"").unwrap();
+        assert_eq!(reserve("/autre/film.mkv"), "film.4.mkv");
+        assert_eq!(reserve("/a/.cache"), ".cache");
+        assert_eq!(reserve("/b/.cache"), ".cache.2");
+    }
+
+    #[test]
+    fn trash_list_and_restore_round_trip() {
+        let work = workdir();
+        let root = work.path().canonicalize().unwrap();
+        let file = root.join("Films/Alien.mkv");
+        write(&file, 4096);
+
+        let item = trash(&file).unwrap();
+        assert!(!file.exists());
+        assert!(item.trashed.starts_with(xdg_data_home().join("Trash/files")));
+        let info = fs::read_to_string(&item.info).unwrap();
+        assert!(info.contains(&format!("Path={}\n", encode_path(&file))), "{}", info);
+
+        let listed = list_under(&root);
+        assert_eq!(listed.len(), 1);
+        assert_eq!((&listed[0].original, &listed[0].trashed), (&file, &item.trashed));
+
+        // Remplacé entre-temps : rien n'est écrasé
+        write(&file, 10);
+        assert_eq!(restore(&item).unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+        fs::remove_file(&file).unwrap();
+
+        restore(&item).unwrap();
+        assert_eq!(fs::metadata(&file).unwrap().len(), 4096);
+        assert!(!item.info.exists());
+        assert!(list_under(&root).is_empty());
+    }
+
+    #[test]
+    fn topdir_trash_records_relative_paths() {
+        let work = workdir();
+        let topdir = work.path().canonicalize().unwrap();
+        let trash = TrashDir { dir: topdir.join(".Trash-1000"), topdir: Some(topdir.clone()) };
+        let file = topdir.join("Séries/b.mkv");
+        write(&file, 10);
+
+        let item = trash_into(&trash, &file, 0).unwrap();
+        let info = fs::read_to_string(&item.info).unwrap();
+        assert!(info.contains("Path=S%C3%A9ries/b.mkv\n"), "{}", info);
+
+        let read = read_info(&trash, &item.info).unwrap();
+        assert_eq!(read.original, file);
+        assert_eq!(read.trashed, topdir.join(".Trash-1000/files/b.mkv"));
+    }
+
+    #[test]
+    fn freed_sizes_count_each_inode_once_and_skip_outside_links() {
+        let work = workdir();
+        let folder = work.path().join("files/Dossier");
+        write(&folder.join("seul.mkv"), 20_000);
+        write(&folder.join("partage.mkv"), 30_000);
+        write(&folder.join("double.mkv"), 50_000);
+        // Un lien de `partage.mkv` reste hors de la corbeille, les deux de `double.mkv` y sont
+        fs::hard_link(folder.join("partage.mkv"), work.path().join("ailleurs.mkv")).unwrap();
+        fs::hard_link(folder.join("double.mkv"), work.path().join("files/double.mkv")).unwrap();
+
+        let item = |trashed: PathBuf| TrashedItem { original: trashed.clone(), info: trashed.clone(), trashed, deleted_at: String::new(), size: 0 };
+        let items = [item(folder.clone()), item(work.path().join("files/double.mkv"))];
+        let blocks = |path: &Path| fs::symlink_metadata(path).unwrap().blocks() * 512;
+
+        let sizes = freed_sizes(&items);
+        assert_eq!(sizes[0], blocks(&folder) + blocks(&folder.join("seul.mkv")));
+        assert_eq!(sizes[1], blocks(&folder.join("double.mkv")));
+        assert!(allocated_size(&folder) > sizes[0]);
+    }
+}
@@ -0,0 +1,211 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::trash;
+
+const DAY: f64 = 86_400.0;
+/// Seuls les relevés des 90 derniers jours comptent pour la tendance.
+const WINDOW_DAYS: f64 = 90.0;
+/// En dessous d'un jour d'historique, la tendance ne veut rien dire.
+const MIN_SPAN_DAYS: f64 = 1.0;
+
+/// Occupation d'un volume à un instant, d'après `statvfs`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VolumeSample {
+    /// Point de montage, qui identifie le volume dans l'historique
+    pub mount: String,
+    /// En secondes depuis l'epoch Unix
+    pub timestamp: u64,
+    pub total: u64,
+    /// Place utilisable sans être root (`f_bavail`)
+    pub available: u64,
+    pub used: u64,
+}
+
+impl VolumeSample {
+    /// Comme `df` : la place réservée à root ne compte ni comme utilisée ni comme libre.
+    pub fn fill_percent(&self) -> f64 {
+        let usable = self.used + self.available;
+        if usable == 0 { 0.0 } else { self.used as f64 * 100.0 / usable as f64 }
+    }
+}
+
+/// Tendance de la place utilisée, par régression linéaire sur l'historique du volume.
+#[derive(Debug, Clone, Copy)]
+pub struct Growth {
+    pub bytes_per_day: f64,
+    /// Ancienneté du plus vieux relevé utilisé
+    pub over_days: f64,
+}
+
+/// Relevé du volume d'une racine, avec sa tendance quand l'historique le permet.
+#[derive(Debug, Clone)]
+pub struct VolumeReport {
+    pub sample: VolumeSample,
+    pub growth: Option<Growth>,
+}
+
+impl VolumeReport {
+    /// Tendance de `sample` d'après les relevés précédents du même volume.
+    pub fn new(sample: VolumeSample) -> Self {
+        let growth = estimate_growth(&load_history(&sample.mount), &sample);
+        VolumeReport { sample, growth }
+    }
+
+    /// Relève le volume qui contient `root`. Avec `record`, le relevé est ajouté à l'historique
+    /// pour les prochaines projections.
+    pub fn measure(root: &Path, record: bool) -> io::Result<Self> {
+        let mount = trash::topdir(root)?;
+        let (total, available, used) = statvfs(&mount)?;
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |now| now.as_secs());
+        let report = Self::new(VolumeSample { mount: mount.display().to_string(), timestamp, total, available, used });
+        if record {
+            append_history(&report.sample)?;
+        }
+        Ok(report)
+    }
+
+    /// Jours avant que le volume soit plein au rythme actuel, `None` s'il ne se remplit pas.
+    pub fn days_until_full(&self) -> Option<f64> {
+        let growth = self.growth?;
+        (growth.bytes_per_day > 0.0).then(|| self.sample.available as f64 / growth.bytes_per_day)
+    }
+}
+
+#[cfg(unix)]
+fn statvfs(path: &Path) -> io::Result<(u64, u64, u64)> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).map_err(io::Error::other)?;
+    let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stats) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let block = stats.f_frsize as u64;
+    let (blocks, free, available) = (stats.f_blocks as u64, stats.f_bfree as u64, stats.f_bavail as u64);
+    // Certains systèmes de fichiers réseau annoncent plus de blocs libres que de blocs
+    Ok((blocks * block, available * block, blocks.saturating_sub(free) * block))
+}
+
+#[cfg(not(unix))]
+fn statvfs(_path: &Path) -> io::Result<(u64, u64, u64)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "statvfs indisponible sur ce système"))
+}
+
+/// Un relevé JSON par ligne, tous volumes confondus.
+fn history_path() -> Option<PathBuf> {
+    Some(trash::data_home()?.join("size_check").join("volumes.jsonl"))
+}
+
+fn load_history(mount: &str) -> Vec<VolumeSample> {
+    let Some(file) = history_path().and_then(|path| File::open(path).ok()) else { return Vec::new() };
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<VolumeSample>(&line).ok())
+        .filter(|sample| sample.mount == mount)
+        .collect()
+}
+
+fn append_history(sample: &VolumeSample) -> io::Result<()> {
+    let path = history_path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "ni XDG_DATA_HOME ni HOME"))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(sample)?)
+}
+
+/// Pente de la place utilisée sur les relevés antérieurs à `current` (et `current` lui-même).
+fn estimate_growth(history: &[VolumeSample], current: &VolumeSample) -> Option<Growth> {
+    let points: Vec<(f64, f64)> = history
+        .iter()
+        .filter(|sample| sample.timestamp < current.timestamp)
+        .chain(std::iter::once(current))
+        .map(|sample| ((sample.timestamp as f64 - current.timestamp as f64) / DAY, sample.used as f64))
+        .filter(|(days, _)| -*days <= WINDOW_DAYS)
+        .collect();
+
+    let over_days = -points.iter().map(|(days, _)| *days).fold(0.0, f64::min);
+    if over_days < MIN_SPAN_DAYS {
+        return None;
+    }
+
+    let count = points.len() as f64;
+    let mean_days = points.iter().map(|(days, _)| days).sum::<f64>() / count;
+    let mean_used = points.iter().map(|(_, used)| used).sum::<f64>() / count;
+    let covariance: f64 = points.iter().map(|(days, used)| (days - mean_days) * (used - mean_used)).sum();
+    let variance: f64 = points.iter().map(|(days, _)| (days - mean_days).powi(2)).sum();
+    Some(Growth { bytes_per_day: covariance / variance, over_days })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_760_000_000;
+    const GB: u64 = 1_000_000_000;
+
+    fn sample(days_ago: f64, used: u64) -> VolumeSample {
+        VolumeSample {
+            mount: "/mnt/nas".to_string(),
+            timestamp: NOW - (days_ago * DAY) as u64,
+            total: 1000 * GB,
+            available: 1000 * GB - used,
+            used,
+        }
+    }
+
+    #[test]
+    fn growth_is_the_slope_of_the_used_space() {
+        // 2 GB par jour, avec du bruit autour de la droite
+        let history: Vec<VolumeSample> = [(10.0, 480), (7.0, 487), (5.0, 489), (2.0, 497)]
+            .iter()
+            .map(|&(days, used)| sample(days, used * GB))
+            .collect();
+        let growth = estimate_growth(&history, &sample(0.0, 500 * GB)).unwrap();
+        assert!((growth.bytes_per_day / GB as f64 - 2.0).abs() < 0.1, "{:?}", growth);
+        assert_eq!(growth.over_days, 10.0);
+
+        let report = VolumeReport { sample: sample(0.0, 500 * GB), growth: Some(growth) };
+        assert!((report.days_until_full().unwrap() - 250.0).abs() < 15.0);
+
+        // Place qui diminue : jamais plein
+        let shrinking = estimate_growth(&[sample(4.0, 600 * GB)], &sample(0.0, 500 * GB)).unwrap();
+        assert_eq!(shrinking.bytes_per_day, -25.0 * GB as f64);
+        assert!(VolumeReport { sample: sample(0.0, 500 * GB), growth: Some(shrinking) }.days_until_full().is_none());
+    }
+
+    #[test]
+    fn only_the_last_90_days_count() {
+        // Un vieux relevé très bas ferait croire à une forte croissance
+        let history = [sample(200.0, 0), sample(91.0, 100 * GB), sample(90.0, 500 * GB), sample(30.0, 500 * GB)];
+        let growth = estimate_growth(&history, &sample(0.0, 500 * GB)).unwrap();
+        assert_eq!(growth.bytes_per_day, 0.0);
+        assert_eq!(growth.over_days, 90.0);
+
+        // Les relevés postérieurs au relevé courant (horloge revenue en arrière) sont ignorés
+        let growth = estimate_growth(&[sample(10.0, 400 * GB), sample(-5.0, 0)], &sample(0.0, 500 * GB)).unwrap();
+        assert_eq!(growth.bytes_per_day, 10.0 * GB as f64);
+    }
+
+    #[test]
+    fn no_growth_under_a_day_of_history() {
+        assert!(estimate_growth(&[], &sample(0.0, 500 * GB)).is_none());
+        assert!(estimate_growth(&[sample(0.5, 400 * GB), sample(0.9, 300 * GB)], &sample(0.0, 500 * GB)).is_none());
+        // Plus vieux que la fenêtre : ignoré, il ne reste que le relevé courant
+        assert!(estimate_growth(&[sample(120.0, 400 * GB)], &sample(0.0, 500 * GB)).is_none());
+        assert!(estimate_growth(&[sample(MIN_SPAN_DAYS, 400 * GB)], &sample(0.0, 500 * GB)).is_some());
+    }
+
+    #[test]
+    fn fill_percent_ignores_the_root_reserve() {
+        let mut full = sample(0.0, 950 * GB);
+        full.available = 0;
+        assert_eq!(full.fill_percent(), 100.0);
+        assert_eq!(sample(0.0, 250 * GB).fill_percent(), 25.0);
+    }
+}